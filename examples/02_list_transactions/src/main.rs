@@ -35,7 +35,7 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let requisitions = client.list_requisitions().await?;
     dbg!(&requisitions);
 
-    let linked_requisition = requisitions.results.iter().find(|requisition| requisition.status == "LN").unwrap();
+    let linked_requisition = requisitions.results.iter().find(|requisition| requisition.status.is_linked()).unwrap();
     dbg!(&linked_requisition);
 
     let account_id = &linked_requisition.accounts[0];
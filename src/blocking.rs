@@ -0,0 +1,450 @@
+//! A blocking `Client`, for use outside of an async runtime.
+//!
+//! The API of `blocking::Client` mirrors the async `Client` one to one, sharing its models, errors, `QuotaTracker` and `RetryPolicy`.
+//!
+//! Like `reqwest::blocking`, this client must not be used from within an async runtime.
+//!
+//! ```rs
+//! use gocardless_unofficial::blocking::Client;
+//!
+//! pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let secret_id = std::env::var("GOCARDLESS_SECRET_ID").unwrap();
+//!     let secret_key = std::env::var("GOCARDLESS_SECRET_KEY").unwrap();
+//!
+//!     let client = Client::new(secret_id, secret_key)?;
+//!     let balances = client.list_balances("account_id")?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use secrecy::SecretString;
+use serde::de::DeserializeOwned;
+
+use crate::endpoint::Endpoint;
+use crate::metrics::MetricsRecorder;
+use crate::model::*;
+use crate::protocol::{self, clone_request};
+use crate::quota::QuotaTracker;
+use crate::rate_limit::RateLimit;
+use crate::retry::RetryPolicy;
+use crate::state::{Call, ClientState, RequestDriver, Step};
+use crate::telemetry::RequestSpan;
+use crate::transport::TransportError;
+use crate::wait::{WaitOptions, Waiter};
+
+/// `HttpTransport` sends the HTTP requests made by the blocking `Client`.
+///
+/// This is the blocking counterpart of `crate::HttpTransport`.
+pub trait HttpTransport: Send + Sync {
+    /// `send` sends a request and returns its response, whatever its status code.
+    ///
+    /// Errors are only returned when no response was received at all, e.g. because the connection failed.
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, TransportError>;
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Box<T> {
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, TransportError> {
+        (**self).send(request)
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for std::sync::Arc<T> {
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, TransportError> {
+        (**self).send(request)
+    }
+}
+
+/// `ReqwestTransport` is the default blocking `HttpTransport`, sending requests with a `reqwest::blocking::Client`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::blocking::Client,
+}
+
+impl ReqwestTransport {
+    /// `new` creates a `ReqwestTransport` with a default `reqwest::blocking::Client`.
+    pub fn new() -> Self {
+        ReqwestTransport::default()
+    }
+
+    /// `with_client` creates a `ReqwestTransport` sending requests with the given `reqwest::blocking::Client`.
+    pub fn with_client(client: reqwest::blocking::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, TransportError> {
+        let request = reqwest::blocking::Request::try_from(request)?;
+        let response = self.client.execute(request)?;
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+
+        let body = response.bytes()?;
+        Ok(builder.body(body.to_vec())?)
+    }
+}
+
+/// `Client` is the blocking counterpart of `crate::Client`, with the same methods minus the `async`.
+pub struct Client {
+    transport: Box<dyn HttpTransport>,
+    state: ClientState,
+}
+
+impl Client {
+    /// `new` is an associated function that creates a new instance of the `Client` struct and its initial token.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret_id`: An implementor of the `Into<SecretString>` trait. This is converted into a `SecretString` that represents the client's secret ID.
+    /// * `secret_key`: An implementor of the `Into<SecretString>` trait. This is converted into a `SecretString` that represents the client's secret key.
+    pub fn new(
+        secret_id: impl Into<SecretString>,
+        secret_key: impl Into<SecretString>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Client::builder(secret_id, secret_key).build()
+    }
+
+    /// `builder` is an associated function that creates a `ClientBuilder`, used to configure optional behaviour of the `Client`.
+    pub fn builder(
+        secret_id: impl Into<SecretString>,
+        secret_key: impl Into<SecretString>,
+    ) -> ClientBuilder {
+        ClientBuilder {
+            base_url: protocol::DEFAULT_BASE_URL.to_string(),
+            transport: None,
+            secret_id: secret_id.into(),
+            secret_key: secret_key.into(),
+            quota_tracker: None,
+            retry_policy: RetryPolicy::none(),
+            metrics_recorder: None,
+        }
+    }
+
+    /// `create_token` sends a POST request to the `/api/v2/token/new/` endpoint to create a new token.
+    pub fn create_token(&self) -> Result<CreateTokenResponse, Box<dyn std::error::Error>> {
+        let request = protocol::create_token(
+            &self.state.base_url,
+            &self.state.secret_id,
+            &self.state.secret_key,
+        )?;
+
+        self.send(Call::new(Endpoint::CreateToken), request)
+    }
+
+    /// `get_institutions` sends a GET request to the `/api/v2/institutions/` endpoint to retrieve a list of institutions.
+    pub fn get_institutions(
+        &self,
+        country: &str,
+    ) -> Result<Vec<Institution>, Box<dyn std::error::Error>> {
+        let request =
+            protocol::get_institutions(&self.state.base_url, self.state.access_token(), country)?;
+
+        self.send(Call::new(Endpoint::GetInstitutions), request)
+    }
+
+    /// `create_end_user_agreement` sends a POST request to the `/api/v2/agreements/enduser/` endpoint to create an end user agreement.
+    pub fn create_end_user_agreement(
+        &self,
+        institution_id: &str,
+        max_historical_days: i32,
+    ) -> Result<EndUserAgreement, Box<dyn std::error::Error>> {
+        let request = protocol::create_end_user_agreement(
+            &self.state.base_url,
+            self.state.access_token(),
+            institution_id,
+            max_historical_days,
+        )?;
+
+        self.send(
+            Call::institution(Endpoint::CreateEndUserAgreement, institution_id),
+            request,
+        )
+    }
+
+    /// `create_end_user_agreement_with_access` sends a POST request to the `/api/v2/agreements/enduser/` endpoint to create an end user agreement with a custom validity and scope.
+    pub fn create_end_user_agreement_with_access(
+        &self,
+        institution_id: &str,
+        max_historical_days: i32,
+        access_valid_for_days: i32,
+        access_scope: &[&str],
+    ) -> Result<EndUserAgreement, Box<dyn std::error::Error>> {
+        let request = protocol::create_end_user_agreement_with_access(
+            &self.state.base_url,
+            self.state.access_token(),
+            institution_id,
+            max_historical_days,
+            access_valid_for_days,
+            access_scope,
+        )?;
+
+        self.send(
+            Call::institution(Endpoint::CreateEndUserAgreement, institution_id),
+            request,
+        )
+    }
+
+    /// `get_end_user_agreement` sends a GET request to the `/api/v2/agreements/enduser/{id}/` endpoint to retrieve a single end user agreement.
+    pub fn get_end_user_agreement(
+        &self,
+        agreement_id: &str,
+    ) -> Result<EndUserAgreement, Box<dyn std::error::Error>> {
+        let request = protocol::get_end_user_agreement(
+            &self.state.base_url,
+            self.state.access_token(),
+            agreement_id,
+        )?;
+
+        self.send(Call::new(Endpoint::GetEndUserAgreement), request)
+    }
+
+    /// `list_requisitions` sends a GET request to the `/api/v2/requisitions/` endpoint to retrieve a list of requisitions.
+    pub fn list_requisitions(
+        &self,
+    ) -> Result<ListRequisitionsResponse, Box<dyn std::error::Error>> {
+        let request = protocol::list_requisitions(&self.state.base_url, self.state.access_token())?;
+
+        self.send(Call::new(Endpoint::ListRequisitions), request)
+    }
+
+    /// `create_requisition` sends a POST request to the `/api/v2/requisitions/` endpoint to create a new requisition.
+    pub fn create_requisition(
+        &self,
+        redirect: &str,
+        institution_id: &str,
+        agreement_id: Option<&str>,
+        reference: Option<&str>,
+    ) -> Result<Requisition, Box<dyn std::error::Error>> {
+        let request = protocol::create_requisition(
+            &self.state.base_url,
+            self.state.access_token(),
+            redirect,
+            institution_id,
+            agreement_id,
+            reference,
+        )?;
+
+        self.send(
+            Call::institution(Endpoint::CreateRequisition, institution_id),
+            request,
+        )
+    }
+
+    /// `get_requisition` sends a GET request to the `/api/v2/requisitions/{id}/` endpoint to retrieve a single requisition.
+    pub fn get_requisition(
+        &self,
+        requisition_id: &str,
+    ) -> Result<Requisition, Box<dyn std::error::Error>> {
+        let request = protocol::get_requisition(
+            &self.state.base_url,
+            self.state.access_token(),
+            requisition_id,
+        )?;
+
+        self.send(Call::new(Endpoint::GetRequisition), request)
+    }
+
+    /// `wait_for_requisition` polls a requisition until it reaches a terminal status, blocking the current thread between polls.
+    ///
+    /// See `crate::Client::wait_for_requisition`.
+    pub fn wait_for_requisition(
+        &self,
+        requisition_id: &str,
+        options: &WaitOptions,
+    ) -> Result<Requisition, Box<dyn std::error::Error>> {
+        let mut waiter = Waiter::new(options);
+        loop {
+            let requisition = self.get_requisition(requisition_id)?;
+            match waiter.observe(&requisition)? {
+                Some(delay) => std::thread::sleep(delay),
+                None => return Ok(requisition),
+            }
+        }
+    }
+
+    /// `list_transactions` sends a GET request to the `/api/v2/accounts/{account_id}/transactions` endpoint to retrieve a list of transactions for a specific account.
+    pub fn list_transactions(
+        &self,
+        account_id: &str,
+    ) -> Result<ListTransactionsResponse, Box<dyn std::error::Error>> {
+        let request = protocol::list_transactions(
+            &self.state.base_url,
+            self.state.access_token(),
+            account_id,
+        )?;
+
+        self.send(
+            Call::account(Endpoint::ListTransactions, account_id),
+            request,
+        )
+    }
+
+    /// `list_transactions_between` sends a GET request to the `/api/v2/accounts/{account_id}/transactions` endpoint to retrieve the transactions of an account dated between `date_from` and `date_to`, both `YYYY-MM-DD` and inclusive.
+    pub fn list_transactions_between(
+        &self,
+        account_id: &str,
+        date_from: Option<&str>,
+        date_to: Option<&str>,
+    ) -> Result<ListTransactionsResponse, Box<dyn std::error::Error>> {
+        let request = protocol::list_transactions_between(
+            &self.state.base_url,
+            self.state.access_token(),
+            account_id,
+            date_from,
+            date_to,
+        )?;
+
+        self.send(
+            Call::account(Endpoint::ListTransactions, account_id),
+            request,
+        )
+    }
+
+    /// `list_balances` sends a GET request to the `/api/v2/accounts/{account_id}/balances` endpoint to retrieve a list of balances for a specific account.
+    pub fn list_balances(
+        &self,
+        account_id: &str,
+    ) -> Result<ListBalancesResponse, Box<dyn std::error::Error>> {
+        let request =
+            protocol::list_balances(&self.state.base_url, self.state.access_token(), account_id)?;
+
+        self.send(Call::account(Endpoint::ListBalances, account_id), request)
+    }
+
+    /// `get_account_details` sends a GET request to the `/api/v2/accounts/{account_id}/details` endpoint to retrieve the details of a specific account.
+    pub fn get_account_details(
+        &self,
+        account_id: &str,
+    ) -> Result<AccountDetailsResponse, Box<dyn std::error::Error>> {
+        let request = protocol::get_account_details(
+            &self.state.base_url,
+            self.state.access_token(),
+            account_id,
+        )?;
+
+        self.send(
+            Call::account(Endpoint::GetAccountDetails, account_id),
+            request,
+        )
+    }
+
+    /// `rate_limit` returns the general rate limit reported by the most recent API response.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.state.rate_limit()
+    }
+
+    /// `account_rate_limit` returns the per account rate limit reported by the most recent API response for the given account.
+    pub fn account_rate_limit(&self, account_id: &str) -> Option<RateLimit> {
+        self.state.account_rate_limit(account_id)
+    }
+
+    /// `quota_tracker` returns the `QuotaTracker` configured on the client, e.g. to persist its state.
+    pub fn quota_tracker(&self) -> Option<&QuotaTracker> {
+        self.state.quota_tracker.as_ref()
+    }
+
+    /// `send` sends a request, retrying it according to the configured `RetryPolicy`, and parses its response.
+    fn send<T: DeserializeOwned>(
+        &self,
+        call: Call<'_>,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let span = RequestSpan::new(&call);
+        let _entered = span.enter();
+
+        let mut driver = RequestDriver::new(&self.state, call, &span);
+        loop {
+            let attempt = driver.start_attempt()?;
+            let response = self.transport.send(clone_request(&request));
+            match driver.finish_attempt(attempt, response) {
+                Step::Done(result) => return result,
+                Step::Retry(delay) => std::thread::sleep(delay),
+            }
+        }
+    }
+}
+
+/// `ClientBuilder` is used to configure optional behaviour of a blocking `Client` before it is created.
+pub struct ClientBuilder {
+    base_url: String,
+    transport: Option<Box<dyn HttpTransport>>,
+    secret_id: SecretString,
+    secret_key: SecretString,
+    quota_tracker: Option<QuotaTracker>,
+    retry_policy: RetryPolicy,
+    metrics_recorder: Option<Box<dyn MetricsRecorder>>,
+}
+
+impl ClientBuilder {
+    /// `base_url` sets the URL the API is reached at, instead of `https://bankaccountdata.gocardless.com`, e.g. to use a proxy or a `testing::MockServer`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// `transport` sets the `HttpTransport` through which every request of the `Client` is sent, instead of the default `ReqwestTransport`.
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// `quota_tracker` sets a `QuotaTracker` which counts successful account data calls and refuses calls that would exceed the daily limit.
+    pub fn quota_tracker(mut self, quota_tracker: QuotaTracker) -> Self {
+        self.quota_tracker = Some(quota_tracker);
+        self
+    }
+
+    /// `retry_policy` sets the `RetryPolicy` used to retry requests that failed with a transient error. By default, requests are not retried.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// `metrics_recorder` sets a `MetricsRecorder` which is notified around every request sent by the `Client`.
+    pub fn metrics_recorder(mut self, metrics_recorder: impl MetricsRecorder + 'static) -> Self {
+        self.metrics_recorder = Some(Box::new(metrics_recorder));
+        self
+    }
+
+    /// `build` creates the `Client` and its initial token.
+    pub fn build(self) -> Result<Client, Box<dyn std::error::Error>> {
+        let transport = self
+            .transport
+            .unwrap_or_else(|| Box::new(ReqwestTransport::new()));
+
+        let mut c = Client {
+            transport,
+            state: ClientState::new(
+                self.base_url,
+                self.secret_id,
+                self.secret_key,
+                self.quota_tracker,
+                self.retry_policy,
+                self.metrics_recorder,
+            ),
+        };
+
+        let created_token = c.create_token()?;
+        c.state.created_token = Some(created_token);
+
+        Ok(c)
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::link::{LinkError, LinkState, LinkStore};
+use crate::model::RequisitionStatus;
+
+/// The `error` sent back when the end user cancelled the flow at GoCardless's or the institution's screens.
+const USER_CANCELLED: &str = "UserCancelledSession";
+
+/// The query parameters appended to the `redirect` URL of a requisition when the end user is sent back.
+///
+/// GoCardless always appends `ref`, the reference of the requisition, and `error` and `details` when the flow failed.
+///
+/// With the `axum` feature, `Callback` can be used as an extractor in axum handlers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Callback {
+    /// The reference of the requisition, from the `ref` parameter
+    pub reference: Option<String>,
+    /// The error code, from the `error` parameter, e.g. `UserCancelledSession`
+    pub error: Option<String>,
+    /// A human readable description of the error, from the `details` parameter
+    pub details: Option<String>,
+}
+
+impl Callback {
+    /// `parse` reads the parameters of a callback from a URL, a path with a query or a bare query.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let callback = Callback::parse("https://example.org/callback?ref=link-18f2a&error=UserCancelledSession");
+    /// assert_eq!(callback.reference.as_deref(), Some("link-18f2a"));
+    /// ```
+    pub fn parse(url: &str) -> Self {
+        let url = url.split('#').next().unwrap_or_default();
+        let query = match url.split_once('?') {
+            Some((_, query)) => query,
+            None if url.contains('=') => url,
+            None => "",
+        };
+
+        let mut callback = Callback::default();
+        for (key, value) in parse_query(query) {
+            let field = match key.as_str() {
+                "ref" => &mut callback.reference,
+                "error" => &mut callback.error,
+                "details" => &mut callback.details,
+                _ => continue,
+            };
+            if field.is_none() && !value.is_empty() {
+                *field = Some(value);
+            }
+        }
+        callback
+    }
+
+    /// `from_uri` reads the parameters of a callback from the URI of an incoming `http::Request`.
+    pub fn from_uri(uri: &http::Uri) -> Self {
+        Callback::parse(uri.query().unwrap_or_default())
+    }
+}
+
+/// `CallbackHandler` resolves the callbacks of requisitions created by a `LinkFlow` into a `CallbackOutcome`.
+///
+/// The reference of the callback is looked up in the `LinkStore` the flows were configured with, and the requisition is fetched to record its latest status and accounts in the store.
+///
+/// # Examples
+///
+/// ```
+/// let handler = CallbackHandler::new(store.clone());
+///
+/// match handler.handle(&client, &Callback::parse(&request_url)).await? {
+///     CallbackOutcome::Linked { accounts, .. } => println!("Linked accounts: {accounts:?}"),
+///     CallbackOutcome::Cancelled { .. } => println!("You cancelled linking your bank account"),
+///     CallbackOutcome::InstitutionError { details, .. } => println!("Your bank failed: {details:?}"),
+///     CallbackOutcome::Pending { state } => {
+///         let accounts = flow.finish(&client, &state).await?;
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CallbackHandler {
+    store: Arc<LinkStore>,
+}
+
+impl CallbackHandler {
+    /// `new` creates a `CallbackHandler` looking up references in the given `LinkStore`.
+    pub fn new(store: Arc<LinkStore>) -> Self {
+        CallbackHandler { store }
+    }
+
+    /// `handle` correlates a callback with its `LinkState` and fetches the requisition.
+    ///
+    /// # Returns
+    ///
+    /// This method returns the `CallbackOutcome` of the flow, or an error if:
+    /// * the callback has no reference or an unknown one, as a `CallbackError`,
+    /// * the requisition is `Rejected`, `Expired` or `Suspended` without the callback carrying an error, or no longer exists, as a `LinkError`,
+    /// * the requisition could not be fetched.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn handle(
+        &self,
+        client: &Client,
+        callback: &Callback,
+    ) -> Result<CallbackOutcome, Box<dyn std::error::Error>> {
+        let reference = callback
+            .reference
+            .clone()
+            .ok_or(CallbackError::MissingReference)?;
+        let mut state =
+            self.store
+                .get(&reference)
+                .ok_or_else(|| CallbackError::UnknownReference {
+                    reference: reference.clone(),
+                })?;
+        let requisition_id = state
+            .requisition_id
+            .clone()
+            .ok_or_else(|| LinkError::NotStarted {
+                reference: reference.clone(),
+            })?;
+
+        let requisition = match client.get_requisition(&requisition_id).await {
+            Ok(requisition) => requisition,
+            Err(error) => {
+                return Err(match error.downcast_ref() {
+                    Some(Error::Api { status: 404, .. }) => LinkError::RequisitionNotFound {
+                        reference,
+                        requisition_id,
+                    }
+                    .into(),
+                    _ => error,
+                })
+            }
+        };
+        state.status = Some(requisition.status.clone());
+        state.accounts = requisition.accounts.clone();
+        self.store.insert(state.clone());
+
+        if let Some(error) = &callback.error {
+            let details = callback.details.clone();
+            return Ok(if error == USER_CANCELLED {
+                CallbackOutcome::Cancelled { state, details }
+            } else {
+                CallbackOutcome::InstitutionError {
+                    state,
+                    error: error.clone(),
+                    details,
+                }
+            });
+        }
+
+        match requisition.status {
+            RequisitionStatus::Linked => Ok(CallbackOutcome::Linked {
+                state,
+                accounts: requisition.accounts,
+            }),
+            RequisitionStatus::Rejected => Err(LinkError::Rejected {
+                reference,
+                requisition_id,
+            }
+            .into()),
+            RequisitionStatus::Expired => Err(LinkError::Expired {
+                reference,
+                requisition_id,
+            }
+            .into()),
+            RequisitionStatus::Suspended => Err(LinkError::Suspended {
+                reference,
+                requisition_id,
+            }
+            .into()),
+            _ => Ok(CallbackOutcome::Pending { state }),
+        }
+    }
+}
+
+/// The outcome of a callback, resolved by `CallbackHandler::handle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallbackOutcome {
+    /// The end user authorized access to their accounts
+    Linked {
+        /// The updated state of the flow
+        state: LinkState,
+        /// The IDs of the linked accounts
+        accounts: Vec<String>,
+    },
+    /// The end user cancelled the flow
+    Cancelled {
+        /// The updated state of the flow
+        state: LinkState,
+        /// A description of the cancellation, if the callback contained one
+        details: Option<String>,
+    },
+    /// The flow failed at GoCardless or at the institution
+    InstitutionError {
+        /// The updated state of the flow
+        state: LinkState,
+        /// The error code of the callback
+        error: String,
+        /// A description of the error, if the callback contained one
+        details: Option<String>,
+    },
+    /// The end user was sent back before the requisition reached a terminal status; it can be awaited with `LinkFlow::finish`
+    Pending {
+        /// The updated state of the flow
+        state: LinkState,
+    },
+}
+
+/// Errors returned by `CallbackHandler` for callbacks which can't be correlated with a flow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallbackError {
+    /// The callback has no `ref` parameter
+    MissingReference,
+    /// No flow with the reference of the callback is kept in the `LinkStore`
+    UnknownReference {
+        /// The reference of the callback
+        reference: String,
+    },
+}
+
+impl fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallbackError::MissingReference => write!(f, "callback has no reference"),
+            CallbackError::UnknownReference { reference } => {
+                write!(f, "no link flow with reference {reference}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CallbackError {}
+
+#[cfg(feature = "axum")]
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for Callback {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Callback::from_uri(&parts.uri))
+    }
+}
+
+/// `parse_query` splits a query string into its percent decoded key value pairs.
+pub(crate) fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (escaped, bytes[i]) {
+            (Some(byte), _) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (None, b'+') => decoded.push(b' '),
+            (None, byte) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
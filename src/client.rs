@@ -1,30 +1,37 @@
-use secrecy::{ExposeSecret, SecretString};
-use serde_json::json;
+use std::time::Duration;
 
-use crate::model::*;
+use secrecy::SecretString;
+use serde::de::DeserializeOwned;
 
-const URL_CREATE_TOKEN: &str = "https://bankaccountdata.gocardless.com/api/v2/token/new/";
-const URL_GET_INSTITUTIONS: &str = "https://bankaccountdata.gocardless.com/api/v2/institutions/";
-const URL_CREATE_END_USER_AGREEMENT: &str =
-    "https://bankaccountdata.gocardless.com/api/v2/agreements/enduser/";
-const URL_REQUISITIONS: &str = "https://bankaccountdata.gocardless.com/api/v2/requisitions/";
+use crate::endpoint::Endpoint;
+use crate::metrics::MetricsRecorder;
+use crate::model::*;
+use crate::protocol::{self, clone_request};
+use crate::quota::QuotaTracker;
+use crate::rate_limit::RateLimit;
+use crate::retry::RetryPolicy;
+use crate::state::{Call, ClientState, RequestDriver, Step};
+use crate::telemetry::RequestSpan;
+use crate::transport::{HttpTransport, SleepFuture};
+use crate::wait::{WaitOptions, Waiter};
 
 /// `Client` is a public struct that represents a client for making requests to the API.
 ///
 /// Fields:
-/// * `req_client`: A `reqwest::Client` instance used for making HTTP requests.
-/// * `secret_id`: A `SecretString` that represents the client's secret ID.
-/// * `secret_key`: A `SecretString` that represents the client's secret key.
-/// * `created_token`: An `Option<CreateTokenResponse>` that represents the token created by the client. It is `None` if no token has been created yet.
+/// * `transport`: The `HttpTransport` used for making HTTP requests, a `ReqwestTransport` unless configured otherwise.
+/// * `state`: The state shared with the blocking client:
+///   * the `SecretString`s that represent the client's secret ID and secret key,
+///   * the `CreateTokenResponse` that represents the token created by the client,
+///   * the most recent rate limits reported by the API, in general and per account,
+///   * an optional `QuotaTracker` that refuses account data calls which would exceed the daily limit,
+///   * the `RetryPolicy` used to retry requests that failed with a transient error.
 ///
-/// The `Client` struct is used to interact with the API. It uses the `reqwest` crate for making HTTP requests and the `secrecy` crate for handling secret strings.
+/// The `Client` struct is used to interact with the API. It uses an `HttpTransport`, by default backed by the `reqwest` crate, for making HTTP requests and the `secrecy` crate for handling secret strings.
 /// The `secret_id` and `secret_key` are used for authentication with the API.
 /// The `created_token` field is used to store the token received from the API after successful authentication.
 pub struct Client {
-    req_client: reqwest::Client,
-    secret_id: SecretString,
-    secret_key: SecretString,
-    created_token: Option<CreateTokenResponse>,
+    transport: Box<dyn HttpTransport>,
+    state: ClientState,
 }
 
 impl Client {
@@ -54,22 +61,40 @@ impl Client {
         secret_id: impl Into<SecretString>,
         secret_key: impl Into<SecretString>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let req_client = reqwest::Client::new();
+        Client::builder(secret_id, secret_key).build().await
+    }
 
-        let mut c = Client {
-            req_client,
+    /// `builder` is an associated function that creates a `ClientBuilder`, used to configure optional behaviour of the `Client`.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret_id`: An implementor of the `Into<SecretString>` trait. This is converted into a `SecretString` that represents the client's secret ID.
+    /// * `secret_key`: An implementor of the `Into<SecretString>` trait. This is converted into a `SecretString` that represents the client's secret key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = Client::builder(secret_id, secret_key)
+    ///     .quota_tracker(QuotaTracker::default())
+    ///     .build()
+    ///     .await?;
+    /// ```
+    pub fn builder(
+        secret_id: impl Into<SecretString>,
+        secret_key: impl Into<SecretString>,
+    ) -> ClientBuilder {
+        ClientBuilder {
+            base_url: protocol::DEFAULT_BASE_URL.to_string(),
+            transport: None,
             secret_id: secret_id.into(),
             secret_key: secret_key.into(),
-            created_token: None,
-        };
-
-        let created_token = c.create_token().await?;
-        c.created_token = Some(created_token);
-
-        Ok(c)
+            quota_tracker: None,
+            retry_policy: RetryPolicy::none(),
+            metrics_recorder: None,
+        }
     }
 
-    /// `create_token` is an async method that sends a POST request to the `URL_CREATE_TOKEN` endpoint to create a new token.
+    /// `create_token` is an async method that sends a POST request to the `/api/v2/token/new/` endpoint to create a new token.
     ///
     /// # Returns
     ///
@@ -90,27 +115,16 @@ impl Client {
     ///
     /// This method is typically called within the `Client::new` method to automatically create a token when a new `Client` is created.
     pub async fn create_token(&self) -> Result<CreateTokenResponse, Box<dyn std::error::Error>> {
-        let response: CreateTokenResponse = self
-            .req_client
-            .post(URL_CREATE_TOKEN)
-            .body(
-                json!({
-                    "secret_id": self.secret_id.expose_secret(),
-                    "secret_key": self.secret_key.expose_secret(),
-                })
-                .to_string(),
-            )
-            .header("Accept", "application/json")
-            .header("Content-Type", "application/json")
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(response)
+        let request = protocol::create_token(
+            &self.state.base_url,
+            &self.state.secret_id,
+            &self.state.secret_key,
+        )?;
+
+        self.send(Call::new(Endpoint::CreateToken), request).await
     }
 
-    /// `get_institutions` is an async method that sends a GET request to the `URL_GET_INSTITUTIONS` endpoint to retrieve a list of institutions.
+    /// `get_institutions` is an async method that sends a GET request to the `/api/v2/institutions/` endpoint to retrieve a list of institutions.
     ///
     /// # Returns
     ///
@@ -134,22 +148,14 @@ impl Client {
         &self,
         country: &str,
     ) -> Result<Vec<Institution>, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let response: Vec<Institution> = self
-            .req_client
-            .get(format!("{URL_GET_INSTITUTIONS}?country={}", country))
-            .header("Accept", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(response)
+        let request =
+            protocol::get_institutions(&self.state.base_url, self.state.access_token(), country)?;
+
+        self.send(Call::new(Endpoint::GetInstitutions), request)
+            .await
     }
 
-    /// `create_end_user_agreement` is an async method that sends a POST request to the `URL_CREATE_END_USER_AGREEMENT` endpoint to create an end user agreement.
+    /// `create_end_user_agreement` is an async method that sends a POST request to the `/api/v2/agreements/enduser/` endpoint to create an end user agreement.
     ///
     /// # Arguments
     ///
@@ -179,38 +185,102 @@ impl Client {
         institution_id: &str,
         max_historical_days: i32,
     ) -> Result<EndUserAgreement, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let response = self
-            .req_client
-            .post(URL_CREATE_END_USER_AGREEMENT)
-            .body(
-                json!({
-                    "institution_id": institution_id,
-                    "max_historical_days": max_historical_days,
-                    "access_valid_for_days": "30",
-                    "access_scope": [
-                        "balances",
-                        "details",
-                        "transactions"
-                    ]
-                })
-                .to_string(),
-            )
-            .header("Accept", "application/json")
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .text()
-            .await?;
-
-        let agreement: EndUserAgreement = serde_json::from_str(&response)?;
-
-        Ok(agreement)
+        let request = protocol::create_end_user_agreement(
+            &self.state.base_url,
+            self.state.access_token(),
+            institution_id,
+            max_historical_days,
+        )?;
+
+        self.send(
+            Call::institution(Endpoint::CreateEndUserAgreement, institution_id),
+            request,
+        )
+        .await
+    }
+
+    /// `create_end_user_agreement_with_access` is an async method that sends a POST request to the `/api/v2/agreements/enduser/` endpoint to create an end user agreement with a custom validity and scope.
+    ///
+    /// # Arguments
+    ///
+    /// * `institution_id`: A reference to a string that represents the ID of the institution for which the end user agreement is being created.
+    /// * `max_historical_days`: The number of days of transaction history to request.
+    /// * `access_valid_for_days`: The number of days access to the accounts is granted for once the agreement is accepted.
+    /// * `access_scope`: The scopes of the agreement, among `balances`, `details` and `transactions`.
+    ///
+    /// # Returns
+    ///
+    /// This method returns a `Result` that is either an `EndUserAgreement` on success or a `Box<dyn std::error::Error>` on failure.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let end_user_agreement = client
+    ///     .create_end_user_agreement_with_access(&institution_id, 90, 180, &["balances", "transactions"])
+    ///     .await?;
+    /// ```
+    pub async fn create_end_user_agreement_with_access(
+        &self,
+        institution_id: &str,
+        max_historical_days: i32,
+        access_valid_for_days: i32,
+        access_scope: &[&str],
+    ) -> Result<EndUserAgreement, Box<dyn std::error::Error>> {
+        let request = protocol::create_end_user_agreement_with_access(
+            &self.state.base_url,
+            self.state.access_token(),
+            institution_id,
+            max_historical_days,
+            access_valid_for_days,
+            access_scope,
+        )?;
+
+        self.send(
+            Call::institution(Endpoint::CreateEndUserAgreement, institution_id),
+            request,
+        )
+        .await
+    }
+
+    /// `get_end_user_agreement` is an async method that sends a GET request to the `/api/v2/agreements/enduser/{id}/` endpoint to retrieve a single end user agreement.
+    ///
+    /// # Arguments
+    ///
+    /// * `agreement_id`: A reference to a string that represents the ID of the end user agreement.
+    ///
+    /// # Returns
+    ///
+    /// This method returns a `Result` that is either an `EndUserAgreement` on success or a `Box<dyn std::error::Error>` on failure.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let agreement = client.get_end_user_agreement(&agreement_id).await?;
+    /// println!("Accepted at {:?}", agreement.accepted);
+    /// ```
+    pub async fn get_end_user_agreement(
+        &self,
+        agreement_id: &str,
+    ) -> Result<EndUserAgreement, Box<dyn std::error::Error>> {
+        let request = protocol::get_end_user_agreement(
+            &self.state.base_url,
+            self.state.access_token(),
+            agreement_id,
+        )?;
+
+        self.send(Call::new(Endpoint::GetEndUserAgreement), request)
+            .await
     }
 
-    /// `list_requisitions` is an async method that sends a GET request to the `URL_REQUISITIONS` endpoint to retrieve a list of requisitions.
+    /// `list_requisitions` is an async method that sends a GET request to the `/api/v2/requisitions/` endpoint to retrieve a list of requisitions.
     ///
     /// # Returns
     ///
@@ -233,22 +303,13 @@ impl Client {
     pub async fn list_requisitions(
         &self,
     ) -> Result<ListRequisitionsResponse, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let response: ListRequisitionsResponse = self
-            .req_client
-            .get(URL_REQUISITIONS)
-            .header("Accept", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(response)
+        let request = protocol::list_requisitions(&self.state.base_url, self.state.access_token())?;
+
+        self.send(Call::new(Endpoint::ListRequisitions), request)
+            .await
     }
 
-    /// `create_requisition` is an async method that sends a POST request to the `URL_REQUISITIONS` endpoint to create a new requisition.
+    /// `create_requisition` is an async method that sends a POST request to the `/api/v2/requisitions/` endpoint to create a new requisition.
     ///
     /// # Arguments
     ///
@@ -286,33 +347,96 @@ impl Client {
         agreement_id: Option<&str>,
         reference: Option<&str>,
     ) -> Result<Requisition, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let mut request = json!({
-            "redirect": redirect,
-            "institution_id": institution_id,
-            "user_language": "EN" // TODO: configurable
-        });
-        if let Some(reference) = reference {
-            request["reference"] = json!(reference);
-        }
-        if let Some(agreement_id) = agreement_id {
-            request["agreement"] = json!(agreement_id);
-        }
+        let request = protocol::create_requisition(
+            &self.state.base_url,
+            self.state.access_token(),
+            redirect,
+            institution_id,
+            agreement_id,
+            reference,
+        )?;
+
+        self.send(
+            Call::institution(Endpoint::CreateRequisition, institution_id),
+            request,
+        )
+        .await
+    }
+
+    /// `get_requisition` is an async method that sends a GET request to the `/api/v2/requisitions/{id}/` endpoint to retrieve a single requisition.
+    ///
+    /// # Arguments
+    ///
+    /// * `requisition_id`: A reference to a string that represents the ID of the requisition.
+    ///
+    /// # Returns
+    ///
+    /// This method returns a `Result` that is either a `Requisition` on success or a `Box<dyn std::error::Error>` on failure.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let requisition = client.get_requisition(&requisition_id).await?;
+    /// println!("{}", requisition.status.description());
+    /// ```
+    pub async fn get_requisition(
+        &self,
+        requisition_id: &str,
+    ) -> Result<Requisition, Box<dyn std::error::Error>> {
+        let request = protocol::get_requisition(
+            &self.state.base_url,
+            self.state.access_token(),
+            requisition_id,
+        )?;
+
+        self.send(Call::new(Endpoint::GetRequisition), request)
+            .await
+    }
 
-        let response: Requisition = self
-            .req_client
-            .post(URL_REQUISITIONS)
-            .body(request.to_string())
-            .header("Accept", "application/json")
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(response)
+    /// `wait_for_requisition` is an async method that polls a requisition until it reaches a terminal status: `Linked`, `Rejected`, `Suspended` or `Expired`.
+    ///
+    /// Polls back off as configured by the `WaitOptions`, and every status change is reported to their `on_transition` callback, e.g. to show the progress of the end user.
+    ///
+    /// # Arguments
+    ///
+    /// * `requisition_id`: A reference to a string that represents the ID of the requisition.
+    /// * `options`: The `WaitOptions` controlling the polling interval, timeout and callback.
+    ///
+    /// # Returns
+    ///
+    /// This method returns a `Result` that is either the `Requisition` in its terminal status, which may not be `Linked`, or a `Box<dyn std::error::Error>` on failure. `WaitError::TimedOut` is returned if the timeout elapses first.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let options = WaitOptions::new()
+    ///     .on_transition(|transition| println!("{}", transition.to.description()));
+    /// let requisition = client.wait_for_requisition(&requisition_id, &options).await?;
+    /// if requisition.status.is_linked() {
+    ///     println!("Linked accounts: {:?}", requisition.accounts);
+    /// }
+    /// ```
+    pub async fn wait_for_requisition(
+        &self,
+        requisition_id: &str,
+        options: &WaitOptions,
+    ) -> Result<Requisition, Box<dyn std::error::Error>> {
+        let mut waiter = Waiter::new(options);
+        loop {
+            let requisition = self.get_requisition(requisition_id).await?;
+            match waiter.observe(&requisition)? {
+                Some(delay) => self.sleep(delay).await,
+                None => return Ok(requisition),
+            }
+        }
     }
 
     /// `list_transactions` is an async method that sends a GET request to the `https://bankaccountdata.gocardless.com/api/v2/accounts/{account_id}/transactions` endpoint to retrieve a list of transactions for a specific account.
@@ -344,24 +468,61 @@ impl Client {
         &self,
         account_id: &str,
     ) -> Result<ListTransactionsResponse, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let response = self
-            .req_client
-            .get(format!(
-                "https://bankaccountdata.gocardless.com/api/v2/accounts/{}/transactions",
-                account_id
-            ))
-            .header("Accept", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .text()
-            .await?;
-
-        let parsed: ListTransactionsResponse = serde_json::from_str(&response)?;
-
-        Ok(parsed)
+        let request = protocol::list_transactions(
+            &self.state.base_url,
+            self.state.access_token(),
+            account_id,
+        )?;
+
+        self.send(
+            Call::account(Endpoint::ListTransactions, account_id),
+            request,
+        )
+        .await
+    }
+
+    /// `list_transactions_between` is an async method that sends a GET request to the `/api/v2/accounts/{account_id}/transactions` endpoint to retrieve the transactions of an account within a date range.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id`: A reference to a string that represents the ID of the account for which the transactions are being retrieved.
+    /// * `date_from`: The `YYYY-MM-DD` date of the oldest transactions to retrieve, inclusive, or `None` for the whole history.
+    /// * `date_to`: The `YYYY-MM-DD` date of the newest transactions to retrieve, inclusive, or `None` for today.
+    ///
+    /// # Returns
+    ///
+    /// This method returns a `Result` that is either a `ListTransactionsResponse` on success or a `Box<dyn std::error::Error>` on failure.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let transactions = client
+    ///     .list_transactions_between(&account_id, Some("2024-04-01"), Some("2024-04-30"))
+    ///     .await?;
+    /// ```
+    pub async fn list_transactions_between(
+        &self,
+        account_id: &str,
+        date_from: Option<&str>,
+        date_to: Option<&str>,
+    ) -> Result<ListTransactionsResponse, Box<dyn std::error::Error>> {
+        let request = protocol::list_transactions_between(
+            &self.state.base_url,
+            self.state.access_token(),
+            account_id,
+            date_from,
+            date_to,
+        )?;
+
+        self.send(
+            Call::account(Endpoint::ListTransactions, account_id),
+            request,
+        )
+        .await
     }
 
     /// `list_balances` is an async method that sends a GET request to the `https://bankaccountdata.gocardless.com/api/v2/accounts/{account_id}/balances` endpoint to retrieve a list of balances for a specific account.
@@ -393,22 +554,11 @@ impl Client {
         &self,
         account_id: &str,
     ) -> Result<ListBalancesResponse, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let response: ListBalancesResponse = self
-            .req_client
-            .get(format!(
-                "https://bankaccountdata.gocardless.com/api/v2/accounts/{}/balances",
-                account_id
-            ))
-            .header("Accept", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(response)
+        let request =
+            protocol::list_balances(&self.state.base_url, self.state.access_token(), account_id)?;
+
+        self.send(Call::account(Endpoint::ListBalances, account_id), request)
+            .await
     }
 
     /// `get_account_details` is an async method that sends a GET request to the `https://bankaccountdata.gocardless.com/api/v2/accounts/{account_id}/details` endpoint to retrieve the details of a specific account.
@@ -440,21 +590,185 @@ impl Client {
         &self,
         account_id: &str,
     ) -> Result<AccountDetailsResponse, Box<dyn std::error::Error>> {
-        let access_token = self.created_token.clone().unwrap().access;
-
-        let response: AccountDetailsResponse = self
-            .req_client
-            .get(format!(
-                "https://bankaccountdata.gocardless.com/api/v2/accounts/{}/details",
-                account_id
-            ))
-            .header("Accept", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(response)
+        let request = protocol::get_account_details(
+            &self.state.base_url,
+            self.state.access_token(),
+            account_id,
+        )?;
+
+        self.send(
+            Call::account(Endpoint::GetAccountDetails, account_id),
+            request,
+        )
+        .await
+    }
+
+    /// `rate_limit` returns the general rate limit reported by the most recent API response.
+    ///
+    /// # Returns
+    ///
+    /// This method returns `None` if no response carrying rate limit headers has been received yet.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.state.rate_limit()
+    }
+
+    /// `account_rate_limit` returns the per account rate limit reported by the most recent API response for the given account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id`: A reference to a string that represents the ID of the account.
+    ///
+    /// # Returns
+    ///
+    /// This method returns `None` if no response carrying account rate limit headers has been received for the account yet.
+    pub fn account_rate_limit(&self, account_id: &str) -> Option<RateLimit> {
+        self.state.account_rate_limit(account_id)
+    }
+
+    /// `quota_tracker` returns the `QuotaTracker` configured on the client, e.g. to persist its state.
+    pub fn quota_tracker(&self) -> Option<&QuotaTracker> {
+        self.state.quota_tracker.as_ref()
+    }
+
+    /// `sleep` waits for the given duration with the timer of the transport.
+    pub(crate) fn sleep(&self, duration: Duration) -> SleepFuture {
+        self.transport.sleep(duration)
+    }
+
+    /// `send` sends a request, records the rate limits reported by the response and parses its body.
+    ///
+    /// Account data calls are checked against and recorded in the `QuotaTracker`, if one is configured.
+    ///
+    /// Failed requests are retried according to the configured `RetryPolicy`.
+    ///
+    /// Unsuccessful responses are turned into an `Error`: `Error::RateLimited` for `429 Too Many Requests` and `Error::Api` otherwise.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        call: Call<'_>,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let span = RequestSpan::new(&call);
+        span.instrument(async {
+            let mut driver = RequestDriver::new(&self.state, call, &span);
+            loop {
+                // Errors are not `Send`, so they must be dropped before sleeping for the future to be `Send`
+                let delay = {
+                    let attempt = driver.start_attempt()?;
+                    let response = self.transport.send(clone_request(&request)).await;
+                    match driver.finish_attempt(attempt, response) {
+                        Step::Done(result) => return result,
+                        Step::Retry(delay) => delay,
+                    }
+                };
+                self.transport.sleep(delay).await;
+            }
+        })
+        .await
+    }
+}
+
+/// `ClientBuilder` is used to configure optional behaviour of a `Client` before it is created.
+///
+/// A `ClientBuilder` is created with `Client::builder`.
+pub struct ClientBuilder {
+    base_url: String,
+    transport: Option<Box<dyn HttpTransport>>,
+    secret_id: SecretString,
+    secret_key: SecretString,
+    quota_tracker: Option<QuotaTracker>,
+    retry_policy: RetryPolicy,
+    metrics_recorder: Option<Box<dyn MetricsRecorder>>,
+}
+
+impl ClientBuilder {
+    /// `base_url` sets the URL the API is reached at, instead of `https://bankaccountdata.gocardless.com`, e.g. to use a proxy or a `testing::MockServer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url`: The URL of the API, without the `/api/v2` path.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// `transport` sets the `HttpTransport` through which every request of the `Client` is sent, instead of the default `ReqwestTransport`.
+    ///
+    /// A transport must be set when the `reqwest` feature is disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport`: The `HttpTransport` to use.
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// `quota_tracker` sets a `QuotaTracker` which counts successful account data calls and refuses calls that would exceed the daily limit with `Error::QuotaExceeded`.
+    ///
+    /// # Arguments
+    ///
+    /// * `quota_tracker`: The `QuotaTracker` to use, which may have been restored with `QuotaTracker::load`.
+    pub fn quota_tracker(mut self, quota_tracker: QuotaTracker) -> Self {
+        self.quota_tracker = Some(quota_tracker);
+        self
+    }
+
+    /// `retry_policy` sets the `RetryPolicy` used to retry requests that failed with a transient error. By default, requests are not retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_policy`: The `RetryPolicy` to use.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// `metrics_recorder` sets a `MetricsRecorder` which is notified around every request sent by the `Client`.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics_recorder`: The `MetricsRecorder` to use, e.g. a `MetricsCrateRecorder` with the `metrics` feature enabled.
+    pub fn metrics_recorder(mut self, metrics_recorder: impl MetricsRecorder + 'static) -> Self {
+        self.metrics_recorder = Some(Box::new(metrics_recorder));
+        self
+    }
+
+    /// `build` creates the `Client` and its initial token.
+    ///
+    /// # Async
+    ///
+    /// This function is async and should be awaited.
+    pub async fn build(self) -> Result<Client, Box<dyn std::error::Error>> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => default_transport()?,
+        };
+
+        let mut c = Client {
+            transport,
+            state: ClientState::new(
+                self.base_url,
+                self.secret_id,
+                self.secret_key,
+                self.quota_tracker,
+                self.retry_policy,
+                self.metrics_recorder,
+            ),
+        };
+
+        let created_token = c.create_token().await?;
+        c.state.created_token = Some(created_token);
+
+        Ok(c)
     }
 }
+
+#[cfg(feature = "reqwest")]
+fn default_transport() -> Result<Box<dyn HttpTransport>, Box<dyn std::error::Error>> {
+    Ok(Box::new(crate::transport::ReqwestTransport::new()))
+}
+
+#[cfg(not(feature = "reqwest"))]
+fn default_transport() -> Result<Box<dyn HttpTransport>, Box<dyn std::error::Error>> {
+    Err("no HttpTransport configured: enable the `reqwest` feature or set one with `ClientBuilder::transport`".into())
+}
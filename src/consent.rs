@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use crate::client::Client;
+use crate::date::Date;
+use crate::link::{LinkFlow, LinkState, LinkStore};
+use crate::model::RequisitionStatus;
+
+/// `ConsentTracker` computes when access to the accounts linked by `LinkFlow`s lapses, from the `LinkState`s kept in a `LinkStore`.
+///
+/// Access is granted for the `access_valid_for_days` of the end user agreement, counted from when the end user accepted it. As the acceptance time is only known to the API, `refresh` must be called once flows are linked to record it in the store.
+///
+/// # Examples
+///
+/// ```
+/// let tracker = ConsentTracker::new(store.clone());
+/// tracker.refresh(&client).await?;
+///
+/// for consent in tracker.expiring_within(7) {
+///     send_reminder(&consent.state.reference, &consent.expires)?;
+///     let renewal = tracker.renew(&client, &consent).await?;
+///     println!("Renew access at {}", renewal.link.as_deref().unwrap_or_default());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConsentTracker {
+    store: Arc<LinkStore>,
+}
+
+impl ConsentTracker {
+    /// `new` creates a `ConsentTracker` for the flows kept in the given `LinkStore`.
+    pub fn new(store: Arc<LinkStore>) -> Self {
+        ConsentTracker { store }
+    }
+
+    /// `refresh` fetches the end user agreement of every linked flow whose acceptance time is not recorded yet, and records it in the store.
+    ///
+    /// Agreements which don't report when they were accepted are considered accepted when they were created, so that expiries are never later than the actual ones.
+    ///
+    /// # Returns
+    ///
+    /// This method returns the number of flows updated, or the first error encountered while fetching agreements.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn refresh(&self, client: &Client) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut updated = 0;
+        for mut state in self.store.states() {
+            let agreement_id = match (&state.status, &state.agreement_id) {
+                (Some(RequisitionStatus::Linked), Some(agreement_id))
+                    if state.agreement_accepted.is_none() =>
+                {
+                    agreement_id.clone()
+                }
+                _ => continue,
+            };
+
+            let agreement = client.get_end_user_agreement(&agreement_id).await?;
+            state.agreement_accepted = Some(agreement.accepted.unwrap_or(agreement.created));
+            state.access_valid_for_days = Some(agreement.access_valid_for_days);
+            state.access_scope = agreement.access_scope;
+            self.store.insert(state);
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    /// `consents` returns the consent of every linked flow whose acceptance time is recorded, ordered by expiry.
+    pub fn consents(&self) -> Vec<Consent> {
+        let today = Date::today();
+        let mut consents: Vec<Consent> = self
+            .store
+            .states()
+            .into_iter()
+            .filter_map(|state| Consent::new(state, today))
+            .collect();
+        consents.sort_by(|a, b| a.expires.cmp(&b.expires));
+        consents
+    }
+
+    /// `consent_for_account` returns the consent giving the longest access to an account, if it was linked by a tracked flow.
+    pub fn consent_for_account(&self, account_id: &str) -> Option<Consent> {
+        self.consents()
+            .into_iter()
+            .rev()
+            .find(|consent| consent.state.accounts.iter().any(|id| id == account_id))
+    }
+
+    /// `expiring_within` returns the consents which expire within `days` days, or have already expired, ordered by expiry.
+    ///
+    /// Consents renewed by a linked flow created with `renew` are left out.
+    pub fn expiring_within(&self, days: u32) -> Vec<Consent> {
+        let consents = self.consents();
+        let renewed: Vec<String> = consents
+            .iter()
+            .filter_map(|consent| consent.state.renewal_of.clone())
+            .collect();
+
+        consents
+            .into_iter()
+            .filter(|consent| consent.days_remaining <= days as i64)
+            .filter(|consent| !renewed.contains(&consent.state.reference))
+            .collect()
+    }
+
+    /// `renew` starts a `LinkFlow` renewing the access of a consent, with the same institution, redirect, history and scopes, recorded in the store of the tracker.
+    ///
+    /// # Returns
+    ///
+    /// This method returns the `LinkState` of the renewal, whose `link` the end user must be sent to. Once linked, the renewal replaces the consent it renews in `expiring_within`.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn renew(
+        &self,
+        client: &Client,
+        consent: &Consent,
+    ) -> Result<LinkState, Box<dyn std::error::Error>> {
+        LinkFlow::renewal(&consent.state)
+            .store(self.store.clone())
+            .start(client)
+            .await
+    }
+}
+
+/// The access granted to the accounts of a linked flow, computed by `ConsentTracker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consent {
+    /// The state of the flow, with its agreement, requisition and accounts
+    pub state: LinkState,
+    /// When the end user accepted the agreement
+    pub accepted: String,
+    /// The date on which access lapses, in UTC
+    ///
+    /// It is always formatted as `YYYY-MM-DD`, so it can be parsed as an ISO 8601 date and consents sort by expiry when compared as strings.
+    pub expires: String,
+    /// The number of days until access lapses, zero or negative once it has
+    pub days_remaining: i64,
+}
+
+impl Consent {
+    fn new(state: LinkState, today: Date) -> Option<Self> {
+        if state.status != Some(RequisitionStatus::Linked) {
+            return None;
+        }
+        let accepted = state.agreement_accepted.clone()?;
+        let expires = Date::parse(&accepted)?.add_days(state.access_valid_for_days?);
+
+        Some(Consent {
+            state,
+            accepted,
+            expires: expires.to_string(),
+            days_remaining: expires.days_since(&today),
+        })
+    }
+
+    /// Returns `true` if access to the accounts has lapsed
+    pub fn is_expired(&self) -> bool {
+        self.days_remaining <= 0
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A calendar date in the proleptic Gregorian calendar, as used by the `YYYY-MM-DD` dates of the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Date {
+    /// Days since 1970-01-01
+    days: i64,
+}
+
+impl Date {
+    /// Returns the current date in UTC
+    pub(crate) fn today() -> Self {
+        Date::from_system_time(SystemTime::now())
+    }
+
+    /// Returns the date of a point in time, in UTC
+    pub(crate) fn from_system_time(time: SystemTime) -> Self {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(error) => -(error.duration().as_secs() as i64),
+        };
+
+        Date {
+            days: seconds.div_euclid(86_400),
+        }
+    }
+
+    /// Returns the date of the given year, month and day, or `None` if it does not exist
+    pub(crate) fn from_ymd(year: i64, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        Some(Date {
+            days: days_from_civil(year, month, day),
+        })
+    }
+
+    /// Parses the date of a `YYYY-MM-DD` date or of an RFC 3339 timestamp, ignoring its time
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let date = value.as_bytes().get(..10)?;
+        let well_formed = date.iter().enumerate().all(|(index, byte)| match index {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        });
+        if !well_formed {
+            return None;
+        }
+
+        // The date is ASCII, so it can be sliced, and each slice is only digits
+        let number = |range: std::ops::Range<usize>| value[range].parse().ok();
+        Date::from_ymd(number(0..4)?, number(5..7)? as u32, number(8..10)? as u32)
+    }
+
+    /// Returns the year, month and day of the date
+    pub(crate) fn ymd(&self) -> (i64, u32, u32) {
+        civil_from_days(self.days)
+    }
+
+    /// Returns the day of the week, from 0 for Monday to 6 for Sunday
+    #[cfg(feature = "testing")]
+    pub(crate) fn weekday(&self) -> u32 {
+        // 1970-01-01 was a Thursday
+        (self.days + 3).rem_euclid(7) as u32
+    }
+
+    /// Returns the date `days` days later, or earlier if negative
+    pub(crate) fn add_days(&self, days: i64) -> Self {
+        Date {
+            days: self.days + days,
+        }
+    }
+
+    /// Returns the number of days from `other` to this date, negative if `other` is later
+    pub(crate) fn days_since(&self, other: &Date) -> i64 {
+        self.days - other.days
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// Formats a point in time as an RFC 3339 timestamp in UTC, the format of the timestamps of the API
+#[cfg(feature = "testing")]
+pub(crate) fn timestamp(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let date = Date::from_system_time(time);
+    let seconds_of_day = duration.as_secs() % 86_400;
+
+    format!(
+        "{date}T{:02}:{:02}:{:02}.{:06}Z",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60,
+        duration.subsec_micros()
+    )
+}
+
+/// Returns the number of days in a month of a year
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+// Converts a civil date to days since 1970-01-01, from Howard Hinnant's date algorithms.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Converts days since 1970-01-01 to a civil date, from Howard Hinnant's date algorithms.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_and_timestamps() {
+        let date = Date::parse("2024-02-29").unwrap();
+        assert_eq!(date.ymd(), (2024, 2, 29));
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert_eq!(Date::parse("2024-02-29T23:59:59.123456Z"), Some(date));
+        assert_eq!(Date::parse("1970-01-01").unwrap().days, 0);
+        assert_eq!(Date::parse("1969-12-31").unwrap().days, -1);
+        assert_eq!(Date::parse("0000-03-01").unwrap().ymd(), (0, 3, 1));
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        for value in [
+            "",
+            "2024-01",
+            "2024-1-1",
+            "2024-01-1",
+            "24-01-01",
+            "+024-01-01",
+            "-024-01-01",
+            "2024-+1-01",
+            "2024-01- 1",
+            "2024/01/01",
+            "2024-01-0",
+            "２０２４-01-01",
+            "2024-13-01",
+            "2024-00-01",
+            "2024-01-00",
+            "2024-02-30",
+            "2023-02-29",
+            "1900-02-29",
+        ] {
+            assert_eq!(Date::parse(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn counts_leap_days() {
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(-4, 2), 29);
+
+        let leap = Date::from_ymd(2024, 2, 28).unwrap();
+        assert_eq!(leap.add_days(1).to_string(), "2024-02-29");
+        assert_eq!(leap.add_days(2).to_string(), "2024-03-01");
+        let century = Date::from_ymd(1900, 2, 28).unwrap();
+        assert_eq!(century.add_days(1).to_string(), "1900-03-01");
+        let quadricentennial = Date::from_ymd(2000, 2, 28).unwrap();
+        assert_eq!(quadricentennial.add_days(1).to_string(), "2000-02-29");
+        assert_eq!(
+            Date::from_ymd(2025, 1, 1)
+                .unwrap()
+                .days_since(&Date::from_ymd(2024, 1, 1).unwrap()),
+            366
+        );
+        assert_eq!(
+            Date::from_ymd(2101, 1, 1)
+                .unwrap()
+                .days_since(&Date::from_ymd(2100, 1, 1).unwrap()),
+            365
+        );
+    }
+
+    #[test]
+    fn adds_negative_days() {
+        let date = Date::from_ymd(2024, 3, 1).unwrap();
+        assert_eq!(date.add_days(-1).to_string(), "2024-02-29");
+        assert_eq!(date.add_days(-366).to_string(), "2023-03-01");
+        assert_eq!(date.add_days(0), date);
+        assert_eq!(
+            Date::from_ymd(1970, 1, 1).unwrap().add_days(-1).ymd(),
+            (1969, 12, 31)
+        );
+        assert_eq!(
+            Date::from_ymd(2024, 1, 1)
+                .unwrap()
+                .days_since(&Date::from_ymd(2024, 3, 1).unwrap()),
+            -60
+        );
+    }
+
+    #[test]
+    fn round_trips_every_day_across_centuries() {
+        let mut date = Date::from_ymd(1899, 12, 31).unwrap();
+        let end = Date::from_ymd(2101, 1, 1).unwrap();
+        while date < end {
+            let next = date.add_days(1);
+            let (year, month, day) = next.ymd();
+            assert_eq!(Date::from_ymd(year, month, day), Some(next));
+            assert_eq!(Date::parse(&next.to_string()), Some(next));
+            date = next;
+        }
+    }
+
+    #[test]
+    fn converts_times_before_the_epoch() {
+        let before = UNIX_EPOCH - std::time::Duration::from_secs(1);
+        assert_eq!(Date::from_system_time(before).to_string(), "1969-12-31");
+        let after = UNIX_EPOCH + std::time::Duration::from_secs(86_399);
+        assert_eq!(Date::from_system_time(after).to_string(), "1970-01-01");
+    }
+}
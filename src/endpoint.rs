@@ -0,0 +1,94 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The API endpoints called by `Client`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    /// `POST /api/v2/token/new/`
+    CreateToken,
+    /// `GET /api/v2/institutions/`
+    GetInstitutions,
+    /// `POST /api/v2/agreements/enduser/`
+    CreateEndUserAgreement,
+    /// `GET /api/v2/agreements/enduser/{id}/`
+    GetEndUserAgreement,
+    /// `GET /api/v2/requisitions/`
+    ListRequisitions,
+    /// `POST /api/v2/requisitions/`
+    CreateRequisition,
+    /// `GET /api/v2/requisitions/{id}/`
+    GetRequisition,
+    /// `GET /api/v2/accounts/{id}/transactions`
+    ListTransactions,
+    /// `GET /api/v2/accounts/{id}/balances`
+    ListBalances,
+    /// `GET /api/v2/accounts/{id}/details`
+    GetAccountDetails,
+}
+
+impl Endpoint {
+    /// Returns the name of the endpoint, matching the `Client` method calling it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endpoint::CreateToken => "create_token",
+            Endpoint::GetInstitutions => "get_institutions",
+            Endpoint::CreateEndUserAgreement => "create_end_user_agreement",
+            Endpoint::GetEndUserAgreement => "get_end_user_agreement",
+            Endpoint::ListRequisitions => "list_requisitions",
+            Endpoint::CreateRequisition => "create_requisition",
+            Endpoint::GetRequisition => "get_requisition",
+            Endpoint::ListTransactions => "list_transactions",
+            Endpoint::ListBalances => "list_balances",
+            Endpoint::GetAccountDetails => "get_account_details",
+        }
+    }
+
+    /// Returns the HTTP method of the endpoint
+    pub fn method(&self) -> &'static str {
+        match self {
+            Endpoint::CreateToken
+            | Endpoint::CreateEndUserAgreement
+            | Endpoint::CreateRequisition => "POST",
+            _ => "GET",
+        }
+    }
+
+    /// Returns the path of the endpoint, with placeholders for its parameters
+    pub fn path(&self) -> &'static str {
+        match self {
+            Endpoint::CreateToken => "/api/v2/token/new/",
+            Endpoint::GetInstitutions => "/api/v2/institutions/",
+            Endpoint::CreateEndUserAgreement => "/api/v2/agreements/enduser/",
+            Endpoint::GetEndUserAgreement => "/api/v2/agreements/enduser/{id}/",
+            Endpoint::ListRequisitions | Endpoint::CreateRequisition => "/api/v2/requisitions/",
+            Endpoint::GetRequisition => "/api/v2/requisitions/{id}/",
+            Endpoint::ListTransactions => "/api/v2/accounts/{id}/transactions",
+            Endpoint::ListBalances => "/api/v2/accounts/{id}/balances",
+            Endpoint::GetAccountDetails => "/api/v2/accounts/{id}/details",
+        }
+    }
+
+    /// Returns `true` if calling the endpoint more than once has the same effect as calling it once
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(
+            self,
+            Endpoint::CreateToken | Endpoint::CreateEndUserAgreement | Endpoint::CreateRequisition
+        )
+    }
+
+    /// Returns `true` for the account data endpoints, which GoCardless limits per account per day
+    pub fn is_account_data(&self) -> bool {
+        matches!(
+            self,
+            Endpoint::ListTransactions | Endpoint::ListBalances | Endpoint::GetAccountDetails
+        )
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::endpoint::Endpoint;
+use crate::rate_limit::RateLimits;
+
+/// Errors returned by the API.
+///
+/// `Client` methods return a `Box<dyn std::error::Error>`; these errors can be recovered from it
+/// with `downcast_ref::<Error>()`.
+#[derive(Debug)]
+pub enum Error {
+    /// The API responded with `429 Too Many Requests`
+    RateLimited {
+        /// How long to wait before retrying, from the `Retry-After` header or the reset time of the
+        /// exhausted rate limit
+        retry_after: Option<Duration>,
+        /// The rate limits reported by the response
+        rate_limits: RateLimits,
+    },
+    /// The API responded with an unsuccessful status code
+    Api {
+        /// The HTTP status code of the response
+        status: u16,
+        /// A short summary of the error, if the response contained one
+        summary: Option<String>,
+        /// A detailed description of the error, if the response contained one
+        detail: Option<String>,
+    },
+    /// The call was refused locally by the `QuotaTracker`, as it would exceed the daily limit of
+    /// the account
+    QuotaExceeded {
+        /// The account the call was made for
+        account_id: String,
+        /// The endpoint that was called
+        endpoint: Endpoint,
+        /// How long until a call can be made again, if known
+        retry_after: Option<Duration>,
+    },
+}
+
+impl Error {
+    pub(crate) fn from_response(status: u16, body: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            summary: Option<String>,
+            detail: Option<String>,
+        }
+
+        let (summary, detail) = match serde_json::from_slice::<ErrorBody>(body) {
+            Ok(body) => (body.summary, body.detail),
+            Err(_) => (None, None),
+        };
+
+        Error::Api {
+            status,
+            summary,
+            detail,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            } => write!(
+                f,
+                "rate limited, retry after {} seconds",
+                retry_after.as_secs()
+            ),
+            Error::RateLimited {
+                retry_after: None, ..
+            } => write!(f, "rate limited"),
+            Error::Api {
+                status,
+                summary,
+                detail,
+            } => {
+                write!(f, "request failed with status {status}")?;
+                if let Some(summary) = summary {
+                    write!(f, ": {summary}")?;
+                }
+                if let Some(detail) = detail {
+                    write!(f, " ({detail})")?;
+                }
+                Ok(())
+            }
+            Error::QuotaExceeded {
+                account_id,
+                endpoint,
+                retry_after,
+            } => {
+                write!(
+                    f,
+                    "daily quota of {endpoint} exceeded for account {account_id}"
+                )?;
+                if let Some(retry_after) = retry_after {
+                    write!(f, ", retry after {} seconds", retry_after.as_secs())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::date::Date;
+use crate::model::{Transaction, Transactions};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A deterministic 64 bit FNV-1a hash identifying a transaction.
+///
+/// Unlike `std::hash::Hash`, fingerprints are stable across runs, platforms and versions of this crate, so they can be stored and compared later. They are displayed as 16 hexadecimal digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TransactionFingerprint(pub u64);
+
+impl fmt::Display for TransactionFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl Transaction {
+    /// Returns the fingerprint of every identifying field of the transaction: its IDs, booking and value dates, amount, counterparty and remittance information.
+    ///
+    /// Fields are normalised first, so that formatting differences between calls, such as `-10.50` and `-10.5` or differences in case and whitespace, don't change the fingerprint.
+    pub fn fingerprint(&self) -> TransactionFingerprint {
+        let mut hasher = Fnv1a::new();
+        hasher.field(self.transaction_id.as_deref());
+        hasher.field(self.internal_transaction_id.as_deref());
+        hasher.field(date(
+            self.booking_date.as_deref(),
+            self.booking_date_time.as_deref(),
+        ));
+        self.hash_content(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the fingerprint of the content of the transaction, leaving out its IDs and booking date: its value date, amount, counterparty and remittance information.
+    ///
+    /// It identifies transactions whose ID changes between calls, which some banks do, and is usually shared by a pending transaction and its booked version.
+    pub fn content_fingerprint(&self) -> TransactionFingerprint {
+        let mut hasher = Fnv1a::new();
+        self.hash_content(&mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_content(&self, hasher: &mut Fnv1a) {
+        hasher.field(value_date(self).as_deref());
+        hasher.field(Some(&normalise_amount(&self.transaction_amount.amount)));
+        hasher.field(Some(&self.transaction_amount.currency.to_ascii_uppercase()));
+        hasher.field(normalise_text(self.creditor_name.as_deref()).as_deref());
+        hasher.field(normalise_text(self.debtor_name.as_deref()).as_deref());
+        for account in [&self.creditor_account, &self.debtor_account] {
+            let account = account.as_ref();
+            hasher.field(
+                normalise_account(account.and_then(|account| account.iban.as_deref())).as_deref(),
+            );
+            hasher.field(
+                normalise_account(account.and_then(|account| account.bban.as_deref())).as_deref(),
+            );
+        }
+        hasher.field(remittance(self).as_deref());
+    }
+}
+
+/// `Deduplicator` matches pending transactions with their later booked versions, so that spending isn't counted twice when both are kept.
+///
+/// Booked versions often differ from the pending ones: they may gain or change their ID, be booked a few days later, and carry different remittance information. A booked transaction matches a pending one if it has the same amount and currency and is dated at most `max_days` after it. When several transactions match, pairs sharing an ID, then content, then counterparty or remittance information, then the closest dates are preferred. Every transaction is matched at most once.
+///
+/// # Examples
+///
+/// ```
+/// let transactions = client.list_transactions(&account_id).await?.transactions;
+/// let spending: Vec<Transaction> = Deduplicator::new().deduplicate(&transactions);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deduplicator {
+    max_days: u32,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Deduplicator { max_days: 5 }
+    }
+}
+
+impl Deduplicator {
+    /// `new` creates a `Deduplicator` matching transactions booked up to 5 days after they were pending.
+    pub fn new() -> Self {
+        Deduplicator::default()
+    }
+
+    /// `max_days` sets how many days after a pending transaction its booked version may be dated.
+    pub fn max_days(mut self, days: u32) -> Self {
+        self.max_days = days;
+        self
+    }
+
+    /// `match_pending` pairs pending transactions with their booked versions, returning the index of the pending transaction and of the booked one for every pair, ordered by pending index.
+    ///
+    /// The pending transactions may come from a previous call, as they are no longer returned once booked.
+    pub fn match_pending(
+        &self,
+        pending: &[Transaction],
+        booked: &[Transaction],
+    ) -> Vec<PendingMatch> {
+        let mut candidates = Vec::new();
+        for (pending_index, pending) in pending.iter().enumerate() {
+            for (booked_index, booked) in booked.iter().enumerate() {
+                if let Some(score) = self.score(pending, booked) {
+                    candidates.push((score, pending_index, booked_index));
+                }
+            }
+        }
+        // Best scores first, ties broken by position so that matching is deterministic
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+        let mut pending_matched = vec![false; pending.len()];
+        let mut booked_matched = vec![false; booked.len()];
+        let mut matches = Vec::new();
+        for (_, pending, booked) in candidates {
+            if !pending_matched[pending] && !booked_matched[booked] {
+                pending_matched[pending] = true;
+                booked_matched[booked] = true;
+                matches.push(PendingMatch { pending, booked });
+            }
+        }
+        matches.sort_by_key(|m| m.pending);
+        matches
+    }
+
+    /// `deduplicate` returns the booked transactions, without those returned more than once with the same IDs and fingerprint, followed by the pending transactions which have no booked version yet.
+    pub fn deduplicate(&self, transactions: &Transactions) -> Vec<Transaction> {
+        let mut fingerprints = HashSet::new();
+        let mut deduplicated = Vec::new();
+        for transaction in &transactions.booked {
+            // Identical transactions without IDs may be repeated purchases, e.g. two coffees
+            let identified = transaction.transaction_id.is_some()
+                || transaction.internal_transaction_id.is_some();
+            if identified && !fingerprints.insert(transaction.fingerprint()) {
+                continue;
+            }
+            deduplicated.push(transaction.clone());
+        }
+
+        let matched: HashSet<usize> = self
+            .match_pending(&transactions.pending, &deduplicated)
+            .into_iter()
+            .map(|m| m.pending)
+            .collect();
+        deduplicated.extend(
+            transactions
+                .pending
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !matched.contains(index))
+                .map(|(_, transaction)| transaction.clone()),
+        );
+        deduplicated
+    }
+
+    /// Scores how likely `booked` is the booked version of `pending`, or `None` if it can't be.
+    fn score(&self, pending: &Transaction, booked: &Transaction) -> Option<i64> {
+        if normalise_amount(&pending.transaction_amount.amount)
+            != normalise_amount(&booked.transaction_amount.amount)
+            || !pending
+                .transaction_amount
+                .currency
+                .eq_ignore_ascii_case(&booked.transaction_amount.currency)
+        {
+            return None;
+        }
+
+        // Dates are compared by day, allowing a day of difference between time zones
+        let pending_date = transaction_date(pending)?;
+        let booked_date = transaction_date(booked)?;
+        let days = booked_date.days_since(&pending_date);
+        if days < -1 || days > self.max_days as i64 {
+            return None;
+        }
+
+        let mut score = -days.abs();
+        if pending.transaction_id.is_some() && pending.transaction_id == booked.transaction_id {
+            score += 1000;
+        }
+        if pending.content_fingerprint() == booked.content_fingerprint() {
+            score += 100;
+        }
+        let counterparty = |transaction: &Transaction| {
+            normalise_text(
+                transaction
+                    .creditor_name
+                    .as_deref()
+                    .or(transaction.debtor_name.as_deref()),
+            )
+        };
+        if counterparty(pending).is_some() && counterparty(pending) == counterparty(booked) {
+            score += 20;
+        }
+        match (remittance(pending), remittance(booked)) {
+            (Some(pending), Some(booked)) if pending == booked => score += 20,
+            (Some(pending), Some(booked))
+                if booked.contains(&pending) || pending.contains(&booked) =>
+            {
+                score += 10
+            }
+            _ => {}
+        }
+        Some(score)
+    }
+}
+
+/// A pending transaction matched with its booked version by `Deduplicator::match_pending`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingMatch {
+    /// The index of the pending transaction
+    pub pending: usize,
+    /// The index of the booked transaction
+    pub booked: usize,
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Hashes a field, distinguishing missing fields from empty ones and delimiting it from the next one.
+    fn field(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.write(&[1]);
+                self.write(value.as_bytes());
+            }
+            None => self.write(&[0]),
+        }
+        self.write(&[0xff]);
+    }
+
+    fn finish(&self) -> TransactionFingerprint {
+        TransactionFingerprint(self.0)
+    }
+}
+
+/// Returns the `YYYY-MM-DD` date of a date or of a timestamp.
+fn date<'a>(date: Option<&'a str>, date_time: Option<&'a str>) -> Option<&'a str> {
+    date.or(date_time).and_then(|date| date.get(..10))
+}
+
+fn value_date(transaction: &Transaction) -> Option<String> {
+    date(
+        transaction.value_date.as_deref(),
+        transaction.value_date_time.as_deref(),
+    )
+    .map(str::to_string)
+}
+
+/// Returns the date of a transaction, its value date for pending ones which have no booking date.
+fn transaction_date(transaction: &Transaction) -> Option<Date> {
+    date(
+        transaction.booking_date.as_deref(),
+        transaction.booking_date_time.as_deref(),
+    )
+    .or(date(
+        transaction.value_date.as_deref(),
+        transaction.value_date_time.as_deref(),
+    ))
+    .and_then(Date::parse)
+}
+
+/// Joins the remittance information fields of a transaction, normalised.
+fn remittance(transaction: &Transaction) -> Option<String> {
+    let fields: Vec<&str> = transaction
+        .remittance_information_unstructured
+        .iter()
+        .chain(
+            transaction
+                .remittance_information_unstructured_array
+                .iter()
+                .flatten(),
+        )
+        .chain(transaction.remittance_information_structured.iter())
+        .chain(
+            transaction
+                .remittance_information_structured_array
+                .iter()
+                .flatten(),
+        )
+        .map(String::as_str)
+        .collect();
+    normalise_text(Some(&fields.join(" ")))
+}
+
+/// Lowercases text and collapses whitespace, returning `None` for blank text.
+fn normalise_text(text: Option<&str>) -> Option<String> {
+    let words: Vec<String> = text?.split_whitespace().map(str::to_lowercase).collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+/// Removes spaces from account numbers and uppercases them.
+fn normalise_account(account: Option<&str>) -> Option<String> {
+    let account: String = account?
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if account.is_empty() {
+        None
+    } else {
+        Some(account)
+    }
+}
+
+/// Normalises a decimal amount, e.g. `+010.50` to `10.5` and `-0.00` to `0`.
+fn normalise_amount(amount: &str) -> String {
+    let amount = amount.trim();
+    let (negative, amount) = match amount.strip_prefix('-') {
+        Some(amount) => (true, amount),
+        None => (false, amount.strip_prefix('+').unwrap_or(amount)),
+    };
+    let (units, decimals) = amount.split_once('.').unwrap_or((amount, ""));
+    let units = units.trim_start_matches('0');
+    let decimals = decimals.trim_end_matches('0');
+
+    let units = if units.is_empty() { "0" } else { units };
+    let normalised = if decimals.is_empty() {
+        units.to_string()
+    } else {
+        format!("{units}.{decimals}")
+    };
+    if negative && normalised != "0" {
+        format!("-{normalised}")
+    } else {
+        normalised
+    }
+}
@@ -31,6 +31,98 @@
 //!
 //! See [here](/examples) for more examples.
 //!
+//! ## Blocking client
+//!
+//! Enable the `blocking` feature to use `blocking::Client`, which mirrors the async `Client` without requiring an async runtime.
+//! ```toml
+//! [dependencies]
+//! gocardless-unofficial = { version = "0.1", features = ["blocking"] }
+//! ```
+//!
+//! ## Other runtimes
+//!
+//! The default `reqwest` feature provides `ReqwestTransport`, which requires tokio. Without it, `Client` works on any async runtime given a custom `HttpTransport`, and the `protocol` module builds requests and parses responses without doing any IO.
+//! ```toml
+//! [dependencies]
+//! gocardless-unofficial = { version = "0.1", default-features = false }
+//! ```
+//!
+//! ## Tracing
+//!
+//! Enable the `tracing` feature to instrument every API call with a `gocardless.request` span, recording the method, endpoint template, account and institution IDs, status code, latency, retry attempts and remaining rate limit. Tokens, secrets, bodies and full URLs are never recorded.
+//! ```toml
+//! [dependencies]
+//! gocardless-unofficial = { version = "0.1", features = ["tracing"] }
+//! ```
+//!
+//! ## Metrics
+//!
+//! Pass a `MetricsRecorder` to `ClientBuilder::metrics_recorder` to be notified around every request, with its endpoint, status class, error kind and latency, whenever a call is refused by the `QuotaTracker` without being sent, and whenever a token is created. Enable the `metrics` feature for `MetricsCrateRecorder`, which reports to the [metrics](https://crates.io/crates/metrics) crate.
+//! ```toml
+//! [dependencies]
+//! gocardless-unofficial = { version = "0.1", features = ["metrics"] }
+//! ```
+//!
+//! ## Redacting personal data
+//!
+//! Call `set_debug_redaction(true)` to mask personal data in the `Debug` output of `Account` and `Transaction`, e.g. when they end up in logs through `dbg!` or `{:?}`. IBANs are then shown as `GB** **** 1234` and names, addresses and remittance information as `[REDACTED]`. The tokens of `CreateTokenResponse` are never printed.
+//!
+//! ## Linking bank accounts
+//!
+//! `LinkFlow` chains the calls needed to link the accounts of an end user: it checks the institution, creates an end user agreement and a requisition with a unique reference, then polls the requisition until the end user has authorized access at its `link`, resolving to the linked account IDs. Rejected, expired and suspended requisitions fail with a typed `LinkError`, and the progress of every flow can be kept in a `LinkStore` to resume it after a restart. To drive the polling yourself, `Client::wait_for_requisition` polls a requisition with backoff until it reaches a terminal status or times out, reporting every status change to the `on_transition` callback of its `WaitOptions`, e.g. to show the end user that they are authenticating or selecting accounts.
+//!
+//! ## Handling the redirect
+//!
+//! When the end user is done, GoCardless sends them back to the `redirect` URL of the requisition with its reference in the `ref` query parameter, and `error` and `details` parameters if the flow failed. `Callback::parse` reads these parameters from the URL, and `CallbackHandler` correlates them with the `LinkState` kept in a `LinkStore`, fetches the requisition and returns a typed `CallbackOutcome`: the linked accounts, a cancellation by the end user, an institution error, or a requisition still pending. With the `axum` feature, which requires Rust 1.75, `Callback` is an axum extractor.
+//! ```rs
+//! async fn callback(State(app): State<App>, callback: Callback) -> String {
+//!     match app.handler.handle(&app.client, &callback).await {
+//!         Ok(CallbackOutcome::Linked { accounts, .. }) => format!("Linked {} accounts", accounts.len()),
+//!         Ok(CallbackOutcome::Cancelled { .. }) => "Linking was cancelled".to_string(),
+//!         Ok(_) | Err(_) => "Linking failed".to_string(),
+//!     }
+//! }
+//! ```
+//!
+//! ## Consent expiry
+//!
+//! Access to linked accounts lapses `access_valid_for_days` after the end user accepts the agreement, 30 days by default with `LinkFlow::access_valid_for_days` to change it. `ConsentTracker` computes when the access of every flow kept in a `LinkStore` lapses, per agreement, requisition and account, and lists the consents expiring within a number of days, e.g. to remind end users before their data stops syncing. `ConsentTracker::renew` starts a renewal `LinkFlow` with the same institution, history and scopes.
+//! ```rs
+//! let tracker = ConsentTracker::new(store.clone());
+//! tracker.refresh(&client).await?;
+//!
+//! for consent in tracker.expiring_within(7) {
+//!     let renewal = tracker.renew(&client, &consent).await?;
+//!     println!("Access expires on {}, renew it at {}", consent.expires, renewal.link.unwrap_or_default());
+//! }
+//! ```
+//!
+//! ## Syncing transactions
+//!
+//! `TransactionSync` downloads transactions incrementally instead of the whole history on every run. For every account it remembers the most recent booking date, requests the transactions from a few days before it, and reports only the transactions added, changed, removed or booked since the previous sync. Accounts whose daily quota, as tracked by the `QuotaTracker` of the client, is exhausted or down to the configured reserve are not synced. Its state can be saved to resume syncs in the next run.
+//! ```rs
+//! let sync = TransactionSync::load("sync.json")?;
+//! let report = sync.sync(&client, &account_id).await?;
+//! println!("{} changes since {:?}", report.changes.len(), report.date_from);
+//! sync.save("sync.json")?;
+//! ```
+//!
+//! ## Deduplicating transactions
+//!
+//! Transaction IDs are optional, some banks change them between calls, and pending transactions have none, so a pending payment and its booked version are easily counted twice. `Transaction::fingerprint` hashes the IDs, dates, amount, counterparty and remittance information of a transaction into a `TransactionFingerprint` which is stable across runs, and `Transaction::content_fingerprint` leaves out the IDs and booking date. `Deduplicator` matches pending transactions with the booked transactions of the same amount dated a few days later, preferring the same counterparty and remittance information, and drops booked transactions returned twice. `TransactionSync` uses both to report rebooked and renumbered transactions as `Booked` and `Changed` rather than as additions.
+//! ```rs
+//! let transactions = client.list_transactions(&account_id).await?.transactions;
+//! let spending: Vec<Transaction> = Deduplicator::new().deduplicate(&transactions);
+//! ```
+//!
+//! ## Testing
+//!
+//! Enable the `testing` feature for `testing::MockServer`, an in-process fake of the API to test code built on this crate without network access or sandbox credentials. It serves institutions, agreements, requisitions and realistic accounts generated by `testing::SandboxGenerator`, links requisitions when their link is visited, and can inject failures, expired tokens and rate limits. Build a client pointed at it with `MockServer::client`, or `MockServer::client_builder` to configure it further. To replay real API interactions instead, record them once with `testing::RecordingTransport`, which scrubs tokens, IBANs and names, and serve them back with `testing::ReplayTransport`.
+//! ```toml
+//! [dev-dependencies]
+//! gocardless-unofficial = { version = "0.1", features = ["testing"] }
+//! ```
+//!
 //! ## Authorization
 //!
 //! Head to the [User Secrets](https://bankaccountdata.gocardless.com/user-secrets/) page, generate a new user secret and copy both the secret ID and secret key.
@@ -40,5 +132,62 @@
 mod model;
 pub use model::*;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+mod callback;
+pub use callback::*;
+
 mod client;
 pub use client::*;
+
+mod consent;
+pub use consent::*;
+
+mod endpoint;
+pub use endpoint::*;
+
+mod error;
+pub use error::*;
+
+mod identity;
+pub use identity::*;
+
+mod link;
+pub use link::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod quota;
+pub use quota::*;
+
+mod rate_limit;
+pub use rate_limit::*;
+
+mod retry;
+pub use retry::*;
+
+mod sync;
+pub use sync::*;
+
+mod wait;
+pub use wait::*;
+
+mod date;
+mod state;
+mod telemetry;
+
+mod transport;
+pub use transport::*;
+
+pub mod protocol;
+
+mod redact;
+pub use redact::*;
+
+mod remittance;
+pub use remittance::*;
+
+#[cfg(feature = "testing")]
+pub mod testing;
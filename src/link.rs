@@ -0,0 +1,517 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::model::RequisitionStatus;
+use crate::wait::{WaitError, WaitOptions};
+
+/// `LinkFlow` runs the steps needed to link the bank accounts of an end user:
+/// 1. checking that the institution is available in the country,
+/// 2. creating an end user agreement, limited to the transaction history the institution provides,
+/// 3. creating a requisition with a unique reference, whose `link` the end user must be sent to,
+/// 4. polling the requisition until the end user has authorized access, resolving to the linked accounts.
+///
+/// The progress of every flow is recorded in a `LinkState`, kept in a `LinkStore` if one is configured, so that a flow can be resumed with `finish` after a restart or from another process, e.g. the one handling the redirect.
+///
+/// Requisitions which end up `Rejected`, `Expired` or `Suspended` fail with the matching `LinkError`, which can be recovered from the returned error with `downcast_ref::<LinkError>()`.
+///
+/// # Examples
+///
+/// ```
+/// let store = Arc::new(LinkStore::load("links.json")?);
+/// let flow = LinkFlow::new("GB", "MONZO_MONZGB2L", "https://example.org/callback")
+///     .max_historical_days(180)
+///     .store(store.clone());
+///
+/// let state = flow.start(&client).await?;
+/// println!("Link your bank account at {}", state.link.as_deref().unwrap_or_default());
+///
+/// let accounts = flow.finish(&client, &state).await?;
+/// store.save("links.json")?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct LinkFlow {
+    country: String,
+    institution_id: String,
+    redirect: String,
+    max_historical_days: u32,
+    access_valid_for_days: u32,
+    access_scope: Vec<String>,
+    reference_prefix: String,
+    renewal_of: Option<String>,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+    store: Option<Arc<LinkStore>>,
+}
+
+impl LinkFlow {
+    /// `new` creates a `LinkFlow` linking accounts of the institution `institution_id` available in `country`, redirecting the end user to `redirect` once done.
+    pub fn new(country: &str, institution_id: &str, redirect: &str) -> Self {
+        LinkFlow {
+            country: country.to_string(),
+            institution_id: institution_id.to_string(),
+            redirect: redirect.to_string(),
+            max_historical_days: 90,
+            access_valid_for_days: 30,
+            access_scope: vec![
+                "balances".to_string(),
+                "details".to_string(),
+                "transactions".to_string(),
+            ],
+            reference_prefix: "link".to_string(),
+            renewal_of: None,
+            poll_interval: Duration::from_secs(5),
+            timeout: Some(Duration::from_secs(15 * 60)),
+            store: None,
+        }
+    }
+
+    /// `max_historical_days` sets the number of days of transaction history to request, 90 by default.
+    ///
+    /// The value is lowered to the `transaction_total_days` of the institution if it provides less history.
+    pub fn max_historical_days(mut self, days: u32) -> Self {
+        self.max_historical_days = days;
+        self
+    }
+
+    /// `renewal` creates a `LinkFlow` which links the accounts of a previous flow again, with the same institution, redirect, history and access, e.g. before its end user agreement expires.
+    ///
+    /// The renewal creates a new requisition, with a new reference, which the end user must authorize again.
+    pub fn renewal(state: &LinkState) -> Self {
+        let mut flow = LinkFlow::new(&state.country, &state.institution_id, &state.redirect)
+            .max_historical_days(state.max_historical_days);
+        if let Some(days) = state.access_valid_for_days {
+            flow.access_valid_for_days = days.clamp(1, u32::MAX as i64) as u32;
+        }
+        if !state.access_scope.is_empty() {
+            flow.access_scope = state.access_scope.clone();
+        }
+        flow.renewal_of = Some(state.reference.clone());
+        flow
+    }
+
+    /// `access_valid_for_days` sets the number of days access to the accounts is granted for once the end user accepts the agreement, 30 by default.
+    pub fn access_valid_for_days(mut self, days: u32) -> Self {
+        self.access_valid_for_days = days;
+        self
+    }
+
+    /// `access_scope` sets the scopes of the end user agreement, `balances`, `details` and `transactions` by default.
+    pub fn access_scope(mut self, scopes: &[&str]) -> Self {
+        self.access_scope = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    /// `reference_prefix` sets the prefix of the unique references generated for requisitions, `link` by default.
+    pub fn reference_prefix(mut self, prefix: &str) -> Self {
+        self.reference_prefix = prefix.to_string();
+        self
+    }
+
+    /// `poll_interval` sets the fixed interval at which `finish` polls the requisition, 5 seconds by default.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// `timeout` sets how long `finish` polls the requisition before failing with `LinkError::TimedOut`, 15 minutes by default, or indefinitely if `None`.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// `store` sets the `LinkStore` the state of the flow is recorded in after every step.
+    pub fn store(mut self, store: Arc<LinkStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// `start` checks the institution, then creates the end user agreement and the requisition.
+    ///
+    /// # Returns
+    ///
+    /// This method returns the `LinkState` of the flow, whose `link` the end user must be sent to, or `LinkError::InstitutionNotFound` if the institution is not available in the country.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn start(&self, client: &Client) -> Result<LinkState, Box<dyn std::error::Error>> {
+        let mut state = LinkState {
+            reference: generate_reference(&self.reference_prefix),
+            country: self.country.clone(),
+            institution_id: self.institution_id.clone(),
+            redirect: self.redirect.clone(),
+            max_historical_days: self.max_historical_days,
+            agreement_id: None,
+            agreement_accepted: None,
+            access_valid_for_days: None,
+            access_scope: Vec::new(),
+            requisition_id: None,
+            link: None,
+            status: None,
+            accounts: Vec::new(),
+            renewal_of: self.renewal_of.clone(),
+        };
+        self.save(&state);
+
+        let institution = client
+            .get_institutions(&self.country)
+            .await?
+            .into_iter()
+            .find(|institution| institution.id == self.institution_id)
+            .ok_or_else(|| LinkError::InstitutionNotFound {
+                institution_id: self.institution_id.clone(),
+                country: self.country.clone(),
+            })?;
+        if institution.transaction_total_days > 0 {
+            state.max_historical_days = state
+                .max_historical_days
+                .min(institution.transaction_total_days);
+        }
+
+        let access_scope: Vec<&str> = self.access_scope.iter().map(String::as_str).collect();
+        let agreement = client
+            .create_end_user_agreement_with_access(
+                &state.institution_id,
+                state.max_historical_days.min(i32::MAX as u32) as i32,
+                self.access_valid_for_days.min(i32::MAX as u32) as i32,
+                &access_scope,
+            )
+            .await?;
+        state.agreement_id = Some(agreement.id);
+        state.access_valid_for_days = Some(agreement.access_valid_for_days);
+        state.access_scope = agreement.access_scope;
+        self.save(&state);
+
+        let requisition = client
+            .create_requisition(
+                &state.redirect,
+                &state.institution_id,
+                state.agreement_id.as_deref(),
+                Some(&state.reference),
+            )
+            .await?;
+        state.requisition_id = Some(requisition.id);
+        state.link = Some(requisition.link);
+        state.status = Some(requisition.status);
+        self.save(&state);
+
+        Ok(state)
+    }
+
+    /// `finish` polls the requisition of a flow started with `start` until it reaches a terminal status.
+    ///
+    /// # Returns
+    ///
+    /// This method returns the IDs of the linked accounts once the requisition is `Linked`, or a `LinkError` if it ends up in another terminal status, the polling times out, or the flow was never started.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn finish(
+        &self,
+        client: &Client,
+        state: &LinkState,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut state = state.clone();
+        let requisition_id = match state.requisition_id.clone() {
+            Some(requisition_id) => requisition_id,
+            None => {
+                return Err(LinkError::NotStarted {
+                    reference: state.reference,
+                }
+                .into())
+            }
+        };
+
+        let mut options = WaitOptions::new()
+            .interval(self.poll_interval)
+            .max_interval(self.poll_interval)
+            .multiplier(1.0)
+            .timeout(self.timeout);
+        if let Some(store) = self.store.clone() {
+            let state = state.clone();
+            options = options.on_transition(move |transition| {
+                let mut state = state.clone();
+                state.status = Some(transition.to.clone());
+                store.insert(state);
+            });
+        }
+
+        let reference = state.reference.clone();
+        let requisition = match client.wait_for_requisition(&requisition_id, &options).await {
+            Ok(requisition) => requisition,
+            Err(error) => {
+                return Err(match error.downcast_ref() {
+                    Some(Error::Api { status: 404, .. }) => LinkError::RequisitionNotFound {
+                        reference,
+                        requisition_id,
+                    }
+                    .into(),
+                    _ => match error.downcast_ref() {
+                        Some(WaitError::TimedOut { status, .. }) => LinkError::TimedOut {
+                            reference,
+                            requisition_id,
+                            status: status.clone(),
+                        }
+                        .into(),
+                        _ => error,
+                    },
+                })
+            }
+        };
+
+        state.status = Some(requisition.status.clone());
+        state.accounts = requisition.accounts.clone();
+        self.save(&state);
+
+        match requisition.status {
+            RequisitionStatus::Rejected => Err(LinkError::Rejected {
+                reference,
+                requisition_id,
+            }
+            .into()),
+            RequisitionStatus::Expired => Err(LinkError::Expired {
+                reference,
+                requisition_id,
+            }
+            .into()),
+            RequisitionStatus::Suspended => Err(LinkError::Suspended {
+                reference,
+                requisition_id,
+            }
+            .into()),
+            _ => Ok(requisition.accounts),
+        }
+    }
+
+    fn save(&self, state: &LinkState) {
+        if let Some(store) = &self.store {
+            store.insert(state.clone());
+        }
+    }
+}
+
+/// The progress of a `LinkFlow`, recorded after every step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkState {
+    /// The unique reference of the requisition, sent back as the `ref` query parameter of the redirect
+    pub reference: String,
+    /// The country the institution was looked up in
+    #[serde(default)]
+    pub country: String,
+    /// The ID of the institution
+    pub institution_id: String,
+    /// The URL the end user is redirected to once done
+    pub redirect: String,
+    /// The number of days of transaction history requested
+    pub max_historical_days: u32,
+    /// The ID of the end user agreement, once created
+    pub agreement_id: Option<String>,
+    /// When the end user accepted the agreement, once recorded by `ConsentTracker::refresh`
+    #[serde(default)]
+    pub agreement_accepted: Option<String>,
+    /// The number of days the end user agreement gives access for, once created
+    pub access_valid_for_days: Option<i64>,
+    /// The scopes of the end user agreement, once created
+    #[serde(default)]
+    pub access_scope: Vec<String>,
+    /// The ID of the requisition, once created
+    pub requisition_id: Option<String>,
+    /// The link the end user must be sent to, once the requisition is created
+    pub link: Option<String>,
+    /// The last known status of the requisition
+    pub status: Option<RequisitionStatus>,
+    /// The IDs of the linked accounts
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    /// The reference of the flow this flow renews, if created with `LinkFlow::renewal`
+    #[serde(default)]
+    pub renewal_of: Option<String>,
+}
+
+/// `LinkStore` keeps the `LinkState` of every `LinkFlow` configured with it, by reference.
+///
+/// The states can be persisted with `save`/`load` or `to_json`/`from_json`, so that flows survive restarts.
+#[derive(Debug, Default)]
+pub struct LinkStore {
+    states: Mutex<BTreeMap<String, LinkState>>,
+}
+
+impl LinkStore {
+    /// `new` creates an empty `LinkStore`.
+    pub fn new() -> Self {
+        LinkStore::default()
+    }
+
+    /// `get` returns the state of the flow with the given reference.
+    pub fn get(&self, reference: &str) -> Option<LinkState> {
+        self.states.lock().unwrap().get(reference).cloned()
+    }
+
+    /// `insert` records the state of a flow, replacing any previous state with the same reference.
+    pub fn insert(&self, state: LinkState) {
+        self.states
+            .lock()
+            .unwrap()
+            .insert(state.reference.clone(), state);
+    }
+
+    /// `remove` removes the state of the flow with the given reference, returning it.
+    pub fn remove(&self, reference: &str) -> Option<LinkState> {
+        self.states.lock().unwrap().remove(reference)
+    }
+
+    /// `states` returns the states of every flow, ordered by reference.
+    pub fn states(&self) -> Vec<LinkState> {
+        self.states.lock().unwrap().values().cloned().collect()
+    }
+
+    /// `to_json` serializes the states, to be restored with `from_json`.
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string(&self.states())?)
+    }
+
+    /// `from_json` restores a store from the states serialized by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let states: Vec<LinkState> = serde_json::from_str(json)?;
+
+        let store = LinkStore::new();
+        for state in states {
+            store.insert(state);
+        }
+        Ok(store)
+    }
+
+    /// `save` writes the states to a file, to be restored with `load`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// `load` restores a store from a file written by `save`, or creates an empty one if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => LinkStore::from_json(&json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LinkStore::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Errors returned by `LinkFlow`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// The institution is not available in the country
+    InstitutionNotFound {
+        /// The ID of the institution
+        institution_id: String,
+        /// The country the institution was looked up in
+        country: String,
+    },
+    /// The flow has no requisition, as `start` did not complete
+    NotStarted {
+        /// The reference of the flow
+        reference: String,
+    },
+    /// The requisition no longer exists, e.g. because it was deleted
+    RequisitionNotFound {
+        /// The reference of the requisition
+        reference: String,
+        /// The ID of the requisition
+        requisition_id: String,
+    },
+    /// The end user failed to authenticate or refused access, the requisition is `Rejected`
+    Rejected {
+        /// The reference of the requisition
+        reference: String,
+        /// The ID of the requisition
+        requisition_id: String,
+    },
+    /// The end user agreement of the requisition has expired, the requisition is `Expired`
+    Expired {
+        /// The reference of the requisition
+        reference: String,
+        /// The ID of the requisition
+        requisition_id: String,
+    },
+    /// Accessing the accounts failed too many times, the requisition is `Suspended`
+    Suspended {
+        /// The reference of the requisition
+        reference: String,
+        /// The ID of the requisition
+        requisition_id: String,
+    },
+    /// The requisition did not reach a terminal status before the timeout
+    TimedOut {
+        /// The reference of the requisition
+        reference: String,
+        /// The ID of the requisition
+        requisition_id: String,
+        /// The last status of the requisition
+        status: RequisitionStatus,
+    },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::InstitutionNotFound {
+                institution_id,
+                country,
+            } => write!(
+                f,
+                "institution {institution_id} is not available in {country}"
+            ),
+            LinkError::NotStarted { reference } => {
+                write!(f, "link flow {reference} has no requisition")
+            }
+            LinkError::RequisitionNotFound { requisition_id, .. } => {
+                write!(f, "requisition {requisition_id} not found")
+            }
+            LinkError::Rejected { requisition_id, .. } => {
+                write!(f, "requisition {requisition_id} was rejected")
+            }
+            LinkError::Expired { requisition_id, .. } => {
+                write!(f, "requisition {requisition_id} has expired")
+            }
+            LinkError::Suspended { requisition_id, .. } => {
+                write!(f, "requisition {requisition_id} is suspended")
+            }
+            LinkError::TimedOut {
+                requisition_id,
+                status,
+                ..
+            } => write!(
+                f,
+                "requisition {requisition_id} is still {} after the timeout",
+                status.description()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// Generates a reference unique across processes, from the time, a process wide counter and random bits.
+fn generate_reference(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0);
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let random = RandomState::new().build_hasher().finish();
+
+    format!("{prefix}-{millis:x}-{counter:x}-{:08x}", random as u32)
+}
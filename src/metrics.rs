@@ -0,0 +1,253 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+
+/// `MetricsRecorder` is notified by the client around every request it sends, e.g. to feed dashboards with request counts, error rates and latencies.
+///
+/// A recorder is configured with `ClientBuilder::metrics_recorder`. With the `metrics` feature enabled, `MetricsCrateRecorder` forwards everything to the `metrics` crate.
+///
+/// # Examples
+///
+/// ```
+/// struct PrintingRecorder;
+///
+/// impl MetricsRecorder for PrintingRecorder {
+///     fn request_finished(&self, metrics: &RequestMetrics) {
+///         println!("{} took {:?}", metrics.endpoint, metrics.latency);
+///     }
+/// }
+///
+/// let client = Client::builder(secret_id, secret_key)
+///     .metrics_recorder(PrintingRecorder)
+///     .build()
+///     .await?;
+/// ```
+pub trait MetricsRecorder: Send + Sync {
+    /// `request_started` is called before every request is sent, including retries.
+    fn request_started(&self, endpoint: Endpoint) {
+        let _ = endpoint;
+    }
+
+    /// `request_finished` is called after every request, including retries and requests abandoned because the future of the call was dropped, whose `error_kind` is `ErrorKind::Cancelled`.
+    fn request_finished(&self, metrics: &RequestMetrics);
+
+    /// `request_refused` is called for calls refused by the `QuotaTracker`, which are never sent and so not reported to `request_started` or `request_finished`.
+    fn request_refused(&self, endpoint: Endpoint) {
+        let _ = endpoint;
+    }
+
+    /// `token_created` is called whenever a new access token has been created.
+    fn token_created(&self) {}
+}
+
+impl<T: MetricsRecorder + ?Sized> MetricsRecorder for Box<T> {
+    fn request_started(&self, endpoint: Endpoint) {
+        (**self).request_started(endpoint)
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        (**self).request_finished(metrics)
+    }
+
+    fn request_refused(&self, endpoint: Endpoint) {
+        (**self).request_refused(endpoint)
+    }
+
+    fn token_created(&self) {
+        (**self).token_created()
+    }
+}
+
+impl<T: MetricsRecorder + ?Sized> MetricsRecorder for Arc<T> {
+    fn request_started(&self, endpoint: Endpoint) {
+        (**self).request_started(endpoint)
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        (**self).request_finished(metrics)
+    }
+
+    fn request_refused(&self, endpoint: Endpoint) {
+        (**self).request_refused(endpoint)
+    }
+
+    fn token_created(&self) {
+        (**self).token_created()
+    }
+}
+
+/// The measurements of a single request, passed to `MetricsRecorder::request_finished`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// The endpoint that was called
+    pub endpoint: Endpoint,
+    /// The attempt of the call, starting at 1 and increasing with every retry
+    pub attempt: u32,
+    /// The class of the status code of the response, `None` if no response was received
+    pub status_class: Option<StatusClass>,
+    /// The kind of error the request failed with, `None` if it succeeded
+    pub error_kind: Option<ErrorKind>,
+    /// The time between sending the request and parsing its response
+    pub latency: Duration,
+}
+
+/// The class of an HTTP status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StatusClass {
+    /// `1xx`
+    Informational,
+    /// `2xx`
+    Success,
+    /// `3xx`
+    Redirection,
+    /// `4xx`
+    ClientError,
+    /// `5xx`
+    ServerError,
+}
+
+impl StatusClass {
+    /// Returns the class of the given status code
+    pub fn from_status(status: http::StatusCode) -> Self {
+        match status.as_u16() {
+            100..=199 => StatusClass::Informational,
+            200..=299 => StatusClass::Success,
+            300..=399 => StatusClass::Redirection,
+            400..=499 => StatusClass::ClientError,
+            _ => StatusClass::ServerError,
+        }
+    }
+
+    /// Returns the class as `1xx`, `2xx`, `3xx`, `4xx` or `5xx`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusClass::Informational => "1xx",
+            StatusClass::Success => "2xx",
+            StatusClass::Redirection => "3xx",
+            StatusClass::ClientError => "4xx",
+            StatusClass::ServerError => "5xx",
+        }
+    }
+}
+
+impl fmt::Display for StatusClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The kind of error a request failed with.
+///
+/// Unlike the error itself, the kind never contains data returned by the API and is safe to record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// `Error::RateLimited`
+    RateLimited,
+    /// `Error::Api`
+    Api,
+    /// `Error::QuotaExceeded`
+    QuotaExceeded,
+    /// The response could not be parsed into the expected model
+    Decode,
+    /// No response was received, e.g. because the connection failed
+    Transport,
+    /// The request was abandoned before its response was handled, because the future of the call was dropped
+    Cancelled,
+}
+
+impl ErrorKind {
+    /// Returns the kind of an error returned by a `Client` method
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        match error.downcast_ref::<Error>() {
+            Some(Error::RateLimited { .. }) => ErrorKind::RateLimited,
+            Some(Error::Api { .. }) => ErrorKind::Api,
+            Some(Error::QuotaExceeded { .. }) => ErrorKind::QuotaExceeded,
+            None if error.is::<serde_json::Error>() => ErrorKind::Decode,
+            None => ErrorKind::Transport,
+        }
+    }
+
+    /// Returns the name of the kind in snake case
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::Api => "api",
+            ErrorKind::QuotaExceeded => "quota_exceeded",
+            ErrorKind::Decode => "decode",
+            ErrorKind::Transport => "transport",
+            ErrorKind::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `MetricsCrateRecorder` is a `MetricsRecorder` reporting to the recorder installed in the `metrics` crate.
+///
+/// The following metrics are reported, labelled with `endpoint`, `status_class` and `error_kind` where applicable:
+/// * `gocardless_requests_total`, a counter of finished requests,
+/// * `gocardless_request_errors_total`, a counter of failed requests,
+/// * `gocardless_request_duration_seconds`, a histogram of request latencies,
+/// * `gocardless_requests_in_flight`, a gauge of requests waiting for a response,
+/// * `gocardless_requests_refused_total`, a counter of calls refused by the `QuotaTracker` without being sent,
+/// * `gocardless_tokens_created_total`, a counter of created access tokens.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsCrateRecorder;
+
+#[cfg(feature = "metrics")]
+impl MetricsRecorder for MetricsCrateRecorder {
+    fn request_started(&self, endpoint: Endpoint) {
+        ::metrics::gauge!("gocardless_requests_in_flight", "endpoint" => endpoint.name())
+            .increment(1.0);
+    }
+
+    fn request_finished(&self, request: &RequestMetrics) {
+        let endpoint = request.endpoint.name();
+        let status_class = request.status_class.map_or("none", |class| class.as_str());
+        let error_kind = request.error_kind.map_or("none", |kind| kind.as_str());
+
+        ::metrics::gauge!("gocardless_requests_in_flight", "endpoint" => endpoint).decrement(1.0);
+
+        ::metrics::counter!(
+            "gocardless_requests_total",
+            "endpoint" => endpoint,
+            "status_class" => status_class,
+            "error_kind" => error_kind,
+        )
+        .increment(1);
+
+        if request.error_kind.is_some() {
+            ::metrics::counter!(
+                "gocardless_request_errors_total",
+                "endpoint" => endpoint,
+                "status_class" => status_class,
+                "error_kind" => error_kind,
+            )
+            .increment(1);
+        }
+
+        ::metrics::histogram!(
+            "gocardless_request_duration_seconds",
+            "endpoint" => endpoint,
+            "status_class" => status_class,
+        )
+        .record(request.latency.as_secs_f64());
+    }
+
+    fn request_refused(&self, endpoint: Endpoint) {
+        ::metrics::counter!("gocardless_requests_refused_total", "endpoint" => endpoint.name())
+            .increment(1);
+    }
+
+    fn token_created(&self) {
+        ::metrics::counter!("gocardless_tokens_created_total").increment(1);
+    }
+}
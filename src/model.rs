@@ -1,12 +1,93 @@
-use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Deserialize, Clone)]
+use secrecy::SecretString;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+/// Declares an enum that is (de)serialized from the string codes used by the API.
+///
+/// Every enum gets an additional `Unknown(String)` variant which holds any code not recognised by
+/// this version of the crate, so that new codes added by GoCardless don't break deserialization
+/// and are preserved when the value is serialized again.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $code:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+            /// A code not recognised by this version of the crate
+            Unknown(String),
+        }
+
+        impl $name {
+            /// Returns the code used by the API for this value
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $code,)*
+                    $name::Unknown(code) => code,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(code: &str) -> Self {
+                match code {
+                    $($code => $name::$variant,)*
+                    _ => $name::Unknown(code.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(code: String) -> Self {
+                match code.as_str() {
+                    $($code => $name::$variant,)*
+                    _ => $name::Unknown(code),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Unknown(code) => code,
+                    known => known.as_str().to_string(),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+/// The tokens returned when creating a new token.
+///
+/// The tokens are kept as `SecretString`s, which are zeroized on drop and never printed.
+#[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateTokenResponse {
-    pub access: String,
+    pub access: SecretString,
     #[serde(rename = "access_expires")]
     pub access_expires: i32,
-    pub refresh: String,
+    pub refresh: SecretString,
     #[serde(rename = "refresh_expires")]
     pub refresh_expires: i32,
 }
@@ -17,10 +98,66 @@ pub struct Institution {
     pub id: String,
     pub name: String,
     pub bic: String,
-    #[serde(rename = "transaction_total_days")]
-    pub transaction_total_days: String,
+    /// Number of days of transaction history the institution gives access to, `0` if not returned by the API
+    #[serde(
+        rename = "transaction_total_days",
+        default,
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub transaction_total_days: u32,
+    /// Maximum number of days an end user agreement with the institution can be valid for
+    #[serde(
+        rename = "max_access_valid_for_days",
+        default,
+        deserialize_with = "deserialize_option_number_from_string"
+    )]
+    pub max_access_valid_for_days: Option<u32>,
     pub countries: Vec<String>,
     pub logo: String,
+    /// Payment products supported by the institution, mapped to the payment schemes available for each
+    #[serde(rename = "supported_payments", default)]
+    pub supported_payments: BTreeMap<String, Vec<String>>,
+    /// Features supported by the institution
+    #[serde(rename = "supported_features", default)]
+    pub supported_features: BTreeSet<SupportedFeature>,
+    /// Identification codes of the institution, such as sort codes
+    #[serde(rename = "identification_codes", default)]
+    pub identification_codes: Vec<String>,
+    /// Any fields returned by the API that are not modelled above
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Institution {
+    /// Returns `true` if the institution advertises support for the given feature
+    pub fn supports(&self, feature: &SupportedFeature) -> bool {
+        self.supported_features.contains(feature)
+    }
+}
+
+string_enum! {
+    pub enum SupportedFeature {
+        /// Access to balances, details and transactions can be requested separately
+        AccessScopes = "access_scopes",
+        /// The end user can select which accounts to link
+        AccountSelection = "account_selection",
+        /// Business accounts can be linked
+        BusinessAccounts = "business_accounts",
+        /// Card accounts can be linked
+        CardAccounts = "card_accounts",
+        /// Corporate accounts can be linked
+        CorporateAccounts = "corporate_accounts",
+        /// Pending transactions are reported
+        PendingTransactions = "pending_transactions",
+        /// Private accounts can be linked
+        PrivateAccounts = "private_accounts",
+        /// Payments can be initiated
+        Payments = "payments",
+        /// Payments can be submitted
+        SubmitPayment = "submit_payment",
+        /// Continuous access and transaction history require separate consents
+        SeparateContinuousHistoryConsent = "separate_continuous_history_consent",
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -36,6 +173,9 @@ pub struct EndUserAgreement {
     pub access_valid_for_days: i64,
     #[serde(rename = "access_scope")]
     pub access_scope: Vec<String>,
+    /// When the end user accepted the agreement, from which `access_valid_for_days` are counted
+    #[serde(default)]
+    pub accepted: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -67,27 +207,71 @@ pub struct Requisition {
     pub account_selection: bool,
     #[serde(rename = "redirect_immediate")]
     pub redirect_immediate: bool,
+    /// Any fields returned by the API that are not modelled above
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum RequisitionStatus {
+        #[default]
+        /// Requisition has been successfully created
+        Created = "CR",
+        /// End user is giving consent at GoCardless's consent screen
+        GivingConsent = "GC",
+        /// End user is redirected to the financial institution for authentication
+        UndergoingAuthentication = "UA",
+        /// Either SSN verification has failed or end user has entered incorrect credentials
+        Rejected = "RJ",
+        /// End user is selecting accounts
+        SelectingAccounts = "SA",
+        /// End user is granting access to their account information
+        GrantingAccess = "GA",
+        /// Account has been successfully linked to requisition
+        Linked = "LN",
+        /// Requisition is suspended due to numerous consecutive errors that happened while accessing its accounts
+        Suspended = "SU",
+        /// Access to accounts has expired as set in End User Agreement
+        Expired = "EX",
+    }
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
-pub enum RequisitionStatus {
-    #[default]
-    #[serde(rename = "CR")]
-    Created,
-    #[serde(rename = "GC")]
-    GivingConsent,
-    #[serde(rename = "UA")]
-    UndergoingAuthentication,
-    #[serde(rename = "RJ")]
-    Rejected,
-    #[serde(rename = "SA")]
-    SelectingAccounts,
-    #[serde(rename = "GA")]
-    GrantingAccess,
-    #[serde(rename = "LN")]
-    Linked,
-    #[serde(rename = "EX")]
-    Expired,
+impl RequisitionStatus {
+    /// Returns `true` if the requisition has been linked and its accounts can be accessed
+    pub fn is_linked(&self) -> bool {
+        matches!(self, RequisitionStatus::Linked)
+    }
+
+    /// Returns `true` if the requisition can no longer progress through the linking flow.
+    ///
+    /// Unknown statuses are not considered terminal, as they are most likely new intermediate
+    /// steps of the flow.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            RequisitionStatus::Linked
+                | RequisitionStatus::Rejected
+                | RequisitionStatus::Suspended
+                | RequisitionStatus::Expired
+        )
+    }
+
+    /// Returns a short human readable description of the status
+    pub fn description(&self) -> &str {
+        match self {
+            RequisitionStatus::Created => "created",
+            RequisitionStatus::GivingConsent => "giving consent",
+            RequisitionStatus::UndergoingAuthentication => "authenticating",
+            RequisitionStatus::Rejected => "rejected",
+            RequisitionStatus::SelectingAccounts => "selecting accounts",
+            RequisitionStatus::GrantingAccess => "granting access",
+            RequisitionStatus::Linked => "linked",
+            RequisitionStatus::Suspended => "suspended",
+            RequisitionStatus::Expired => "expired",
+            RequisitionStatus::Unknown(_) => "unknown",
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -100,28 +284,41 @@ pub struct ListTransactionsResponse {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Transactions {
+    #[serde(default)]
     pub booked: Vec<Transaction>,
+    /// Not returned at all by banks which don't report pending transactions
+    #[serde(default)]
     pub pending: Vec<Transaction>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Transaction {
-    pub transaction_id: String,
-    pub booking_date: String,
+    /// Not provided by every bank, and rarely for pending transactions
+    pub transaction_id: Option<String>,
+    /// Not provided by every bank for pending transactions, which may only have a `value_date`
+    pub booking_date: Option<String>,
     pub value_date: Option<String>,
-    pub booking_date_time: String,
+    /// Only provided by some banks
+    pub booking_date_time: Option<String>,
     pub value_date_time: Option<String>,
     pub transaction_amount: TransactionAmount,
     pub creditor_name: Option<String>,
     pub remittance_information_unstructured: Option<String>,
+    pub remittance_information_unstructured_array: Option<Vec<String>>,
+    pub remittance_information_structured: Option<String>,
+    pub remittance_information_structured_array: Option<Vec<String>>,
     pub proprietary_bank_transaction_code: Option<String>,
     pub internal_transaction_id: Option<String>,
     pub debtor_name: Option<String>,
     pub creditor_account: Option<CreditorAccount>,
-    // TODO: this field is either an array of objects or just a single object.
-    //       perhaps there is a way in serde to default to array of just 1 object?
-    // pub currency_exchange: Vec<CurrencyExchange>,
+    pub debtor_account: Option<CreditorAccount>,
+    /// Exchange rates applied to the transaction. Banks return either a single object or an array, both are parsed into a `Vec`
+    #[serde(default, deserialize_with = "deserialize_option_one_or_many")]
+    pub currency_exchange: Option<Vec<CurrencyExchange>>,
+    /// Any fields returned by the API that are not modelled above
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -131,9 +328,10 @@ pub struct TransactionAmount {
     pub currency: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreditorAccount {
+    pub iban: Option<String>,
     pub bban: Option<String>,
 }
 
@@ -142,8 +340,16 @@ pub struct CreditorAccount {
 pub struct CurrencyExchange {
     pub source_currency: String,
     pub exchange_rate: String,
-    pub unit_currency: String,
-    pub target_currency: String,
+    /// Not provided by every bank, in which case it is the `source_currency`
+    pub unit_currency: Option<String>,
+    /// Not provided by every bank, in which case it is the currency of the account
+    pub target_currency: Option<String>,
+    pub quotation_date: Option<String>,
+    /// The original amount of the transaction, in the `source_currency`
+    pub instructed_amount: Option<TransactionAmount>,
+    /// Any fields returned by the API that are not modelled above
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -153,12 +359,91 @@ pub struct ListBalancesResponse {
     pub balances: Vec<Balance>,
 }
 
+impl ListBalancesResponse {
+    /// Returns the balance that best represents the funds currently available on the account.
+    ///
+    /// Banks report different subsets of balance types, so the first balance found in the
+    /// following order of precedence is returned:
+    ///
+    /// 1. `interimAvailable`
+    /// 2. `expected`
+    /// 3. `interimBooked`
+    /// 4. `closingBooked`
+    /// 5. `closingAvailable`
+    /// 6. `forwardAvailable`
+    /// 7. `openingBooked`
+    /// 8. `openingAvailable`
+    /// 9. any other balance type, in the order returned by the API
+    ///
+    /// Returns `None` if the response contains no balances at all.
+    pub fn best_available(&self) -> Option<&Balance> {
+        self.balances
+            .iter()
+            .min_by_key(|balance| balance.balance_type.precedence())
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Balance {
     pub balance_amount: BalanceAmount,
-    pub balance_type: String,
+    pub balance_type: BalanceType,
     pub reference_date: String,
+    /// Whether the credit limit of the account is included in the balance amount
+    pub credit_limit_included: Option<bool>,
+    /// Date and time of the last change to the balance
+    pub last_change_date_time: Option<String>,
+    /// Any fields returned by the API that are not modelled above
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum BalanceType {
+        #[default]
+        /// Balance of the account at the end of the pre-agreed account reporting period
+        ClosingBooked = "closingBooked",
+        /// Closing balance of amount of money that is at the disposal of the account owner on the date specified
+        ClosingAvailable = "closingAvailable",
+        /// Balance composed of booked entries and pending items known at the time of calculation
+        Expected = "expected",
+        /// Available balance calculated in the course of the account servicer's business day
+        InterimAvailable = "interimAvailable",
+        /// Balance calculated in the course of the account servicer's business day
+        InterimBooked = "interimBooked",
+        /// Book balance of the account at the beginning of the account reporting period
+        OpeningBooked = "openingBooked",
+        /// Opening balance of amount of money that is at the disposal of the account owner on the date specified
+        OpeningAvailable = "openingAvailable",
+        /// Forward available balance of money that is at the disposal of the account owner on the date specified
+        ForwardAvailable = "forwardAvailable",
+        /// Only for card accounts, the amount which has not yet been invoiced
+        NonInvoiced = "nonInvoiced",
+        /// Balance provided for information only
+        Information = "information",
+        /// Balance of the account at the end of the previous reporting period
+        PreviouslyClosedBooked = "previouslyClosedBooked",
+        /// Balance including all authorised but not yet booked transactions
+        Authorised = "authorised",
+    }
+}
+
+impl BalanceType {
+    /// Ordering used by [`ListBalancesResponse::best_available`], lower is better
+    fn precedence(&self) -> u8 {
+        match self {
+            BalanceType::InterimAvailable => 0,
+            BalanceType::Expected => 1,
+            BalanceType::InterimBooked => 2,
+            BalanceType::ClosingBooked => 3,
+            BalanceType::ClosingAvailable => 4,
+            BalanceType::ForwardAvailable => 5,
+            BalanceType::OpeningBooked => 6,
+            BalanceType::OpeningAvailable => 7,
+            _ => u8::MAX,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -175,7 +460,7 @@ pub struct AccountDetailsResponse {
     pub account: Option<Account>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Account {
     /// The account id of the given account in the financial institution
@@ -209,29 +494,96 @@ pub struct Account {
     pub linked_accounts: Option<String>,
     /// Specifies the usage of the account
     pub usage: Option<AccountUsage>,
+    /// Any fields returned by the API that are not modelled above
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum AccountStatus {
+        #[default]
+        /// Account is available
+        Enabled = "enabled",
+        /// Account is terminated
+        Deleted = "deleted",
+        /// Account is blocked e.g. for legal reasons
+        Blocked = "blocked",
+    }
+}
+
+impl AccountStatus {
+    /// Returns `true` if the account is available in the sense of the specification
+    pub fn is_enabled(&self) -> bool {
+        matches!(self, AccountStatus::Enabled)
+    }
+}
+
+string_enum! {
+    #[derive(Default)]
+    pub enum AccountUsage {
+        #[default]
+        /// Private personal account
+        Private = "PRIV",
+        /// Professional account
+        Professional = "ORGA",
+    }
+}
+
+/// Fields which the API returns either as a single object or as an array of objects
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+fn deserialize_option_one_or_many<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match Option::<OneOrMany<T>>::deserialize(deserializer)? {
+        None => None,
+        Some(OneOrMany::One(value)) => Some(vec![value]),
+        Some(OneOrMany::Many(values)) => Some(values),
+    })
+}
+
+/// Numeric fields which the API returns either as a JSON number or as a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString<T> {
+    Number(T),
+    String(String),
+}
+
+fn deserialize_number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: fmt::Display,
+{
+    match NumberOrString::<T>::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Ok(number),
+        NumberOrString::String(string) => string.trim().parse().map_err(serde::de::Error::custom),
+    }
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
-pub enum AccountStatus {
-    #[default]
-    #[serde(rename = "enabled")]
-    /// Account is available
-    Enabled,
-    #[serde(rename = "deleted")]
-    /// Account is terminated
-    Deleted,
-    #[serde(rename = "blocked")]
-    /// Account is blocked e.g. for legal reasons
-    Blocked,
-}
-
-#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
-pub enum AccountUsage {
-    #[default]
-    #[serde(rename = "PRIV")]
-    /// Private personal account
-    Private,
-    #[serde(rename = "ORGA")]
-    /// Professional account
-    Professional,
+fn deserialize_option_number_from_string<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: fmt::Display,
+{
+    match Option::<NumberOrString<T>>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrString::Number(number)) => Ok(Some(number)),
+        Some(NumberOrString::String(string)) if string.trim().is_empty() => Ok(None),
+        Some(NumberOrString::String(string)) => string
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
 }
@@ -0,0 +1,346 @@
+//! Runtime agnostic request construction and response parsing.
+//!
+//! This module is the sans-IO core of the crate: every function builds an `http::Request` for one API endpoint, and `parse_response` turns the matching `http::Response` into a model or an `Error`.
+//! Requests can therefore be sent with any HTTP stack or async runtime, with `Client` and `blocking::Client` being adapters on top of it.
+//!
+//! ```rs
+//! use gocardless_unofficial::{protocol, CreateTokenResponse, ListBalancesResponse};
+//!
+//! let request = protocol::create_token(protocol::DEFAULT_BASE_URL, &secret_id, &secret_key)?;
+//! let response = my_http_client.send(request).await?;
+//! let token: CreateTokenResponse = protocol::parse_response(&response)?;
+//!
+//! let request = protocol::list_balances(protocol::DEFAULT_BASE_URL, &token.access, "account_id")?;
+//! let response = my_http_client.send(request).await?;
+//! let balances: ListBalancesResponse = protocol::parse_response(&response)?;
+//! ```
+
+use secrecy::zeroize::Zeroize;
+use secrecy::{ExposeSecret, SecretString};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::error::Error;
+use crate::rate_limit::{retry_after_header, RateLimits};
+
+/// The base URL of the GoCardless Bank Account Data API
+pub const DEFAULT_BASE_URL: &str = "https://bankaccountdata.gocardless.com";
+
+const PATH_CREATE_TOKEN: &str = "/api/v2/token/new/";
+const PATH_GET_INSTITUTIONS: &str = "/api/v2/institutions/";
+const PATH_CREATE_END_USER_AGREEMENT: &str = "/api/v2/agreements/enduser/";
+const PATH_REQUISITIONS: &str = "/api/v2/requisitions/";
+const PATH_ACCOUNTS: &str = "/api/v2/accounts/";
+
+/// `create_token` builds the request creating a new access token from the user secrets.
+pub fn create_token(
+    base_url: &str,
+    secret_id: &SecretString,
+    secret_key: &SecretString,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let body = json!({
+        "secret_id": secret_id.expose_secret(),
+        "secret_key": secret_key.expose_secret(),
+    });
+
+    request(
+        http::Method::POST,
+        format!("{base_url}{PATH_CREATE_TOKEN}"),
+        None,
+        Some(&body),
+    )
+}
+
+/// `get_institutions` builds the request listing the institutions available in a country.
+pub fn get_institutions(
+    base_url: &str,
+    access_token: &SecretString,
+    country: &str,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let country = percent_encode(country);
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_GET_INSTITUTIONS}?country={country}"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `create_end_user_agreement` builds the request creating an end user agreement with an institution.
+pub fn create_end_user_agreement(
+    base_url: &str,
+    access_token: &SecretString,
+    institution_id: &str,
+    max_historical_days: i32,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    create_end_user_agreement_with_access(
+        base_url,
+        access_token,
+        institution_id,
+        max_historical_days,
+        30,
+        &["balances", "details", "transactions"],
+    )
+}
+
+/// `create_end_user_agreement_with_access` builds the request creating an end user agreement valid for `access_valid_for_days` days and limited to the given scopes.
+pub fn create_end_user_agreement_with_access(
+    base_url: &str,
+    access_token: &SecretString,
+    institution_id: &str,
+    max_historical_days: i32,
+    access_valid_for_days: i32,
+    access_scope: &[&str],
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let body = json!({
+        "institution_id": institution_id,
+        "max_historical_days": max_historical_days,
+        "access_valid_for_days": access_valid_for_days.to_string(),
+        "access_scope": access_scope,
+    });
+
+    request(
+        http::Method::POST,
+        format!("{base_url}{PATH_CREATE_END_USER_AGREEMENT}"),
+        Some(access_token),
+        Some(&body),
+    )
+}
+
+/// `get_end_user_agreement` builds the request retrieving an end user agreement, e.g. to know when it was accepted.
+pub fn get_end_user_agreement(
+    base_url: &str,
+    access_token: &SecretString,
+    agreement_id: &str,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let agreement_id = path_segment(agreement_id)?;
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_CREATE_END_USER_AGREEMENT}{agreement_id}/"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `list_requisitions` builds the request listing all requisitions.
+pub fn list_requisitions(
+    base_url: &str,
+    access_token: &SecretString,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_REQUISITIONS}"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `create_requisition` builds the request creating a requisition, which links the accounts of an end user.
+pub fn create_requisition(
+    base_url: &str,
+    access_token: &SecretString,
+    redirect: &str,
+    institution_id: &str,
+    agreement_id: Option<&str>,
+    reference: Option<&str>,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut body = json!({
+        "redirect": redirect,
+        "institution_id": institution_id,
+        "user_language": "EN" // TODO: configurable
+    });
+    if let Some(reference) = reference {
+        body["reference"] = json!(reference);
+    }
+    if let Some(agreement_id) = agreement_id {
+        body["agreement"] = json!(agreement_id);
+    }
+
+    request(
+        http::Method::POST,
+        format!("{base_url}{PATH_REQUISITIONS}"),
+        Some(access_token),
+        Some(&body),
+    )
+}
+
+/// `get_requisition` builds the request retrieving a requisition, e.g. to poll its status.
+pub fn get_requisition(
+    base_url: &str,
+    access_token: &SecretString,
+    requisition_id: &str,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let requisition_id = path_segment(requisition_id)?;
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_REQUISITIONS}{requisition_id}/"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `list_transactions` builds the request listing the transactions of an account.
+pub fn list_transactions(
+    base_url: &str,
+    access_token: &SecretString,
+    account_id: &str,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let account_id = path_segment(account_id)?;
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_ACCOUNTS}{account_id}/transactions"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `list_transactions_between` builds the request listing the transactions of an account, limited to those dated between `date_from` and `date_to`, both `YYYY-MM-DD` and inclusive.
+pub fn list_transactions_between(
+    base_url: &str,
+    access_token: &SecretString,
+    account_id: &str,
+    date_from: Option<&str>,
+    date_to: Option<&str>,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let account_id = path_segment(account_id)?;
+    let query: Vec<String> = [("date_from", date_from), ("date_to", date_to)]
+        .iter()
+        .filter_map(|(key, value)| value.map(|value| format!("{key}={}", percent_encode(value))))
+        .collect();
+    let query = if query.is_empty() {
+        String::new()
+    } else {
+        let query = query.join("&");
+        format!("?{query}")
+    };
+
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_ACCOUNTS}{account_id}/transactions{query}"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `list_balances` builds the request listing the balances of an account.
+pub fn list_balances(
+    base_url: &str,
+    access_token: &SecretString,
+    account_id: &str,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let account_id = path_segment(account_id)?;
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_ACCOUNTS}{account_id}/balances"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `get_account_details` builds the request retrieving the details of an account.
+pub fn get_account_details(
+    base_url: &str,
+    access_token: &SecretString,
+    account_id: &str,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let account_id = path_segment(account_id)?;
+    request(
+        http::Method::GET,
+        format!("{base_url}{PATH_ACCOUNTS}{account_id}/details"),
+        Some(access_token),
+        None,
+    )
+}
+
+/// `parse_response` turns a response of the API into the expected model.
+///
+/// Unsuccessful responses are turned into an `Error`: `Error::RateLimited` for `429 Too Many Requests` and `Error::Api` otherwise.
+pub fn parse_response<T: DeserializeOwned>(
+    response: &http::Response<Vec<u8>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let status = response.status();
+
+    if status == http::StatusCode::TOO_MANY_REQUESTS {
+        let rate_limits = RateLimits::from_headers(response.headers());
+        let retry_after =
+            retry_after_header(response.headers()).or_else(|| rate_limits.retry_after());
+        return Err(Box::new(Error::RateLimited {
+            retry_after,
+            rate_limits,
+        }));
+    }
+
+    if !status.is_success() {
+        return Err(Box::new(Error::from_response(
+            status.as_u16(),
+            response.body(),
+        )));
+    }
+
+    Ok(serde_json::from_slice(response.body())?)
+}
+
+/// `request` builds a request to the API, with a JSON `body` if given, authorized with `access_token` if given.
+fn request(
+    method: http::Method,
+    url: String,
+    access_token: Option<&SecretString>,
+    body: Option<&serde_json::Value>,
+) -> Result<http::Request<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut builder = http::Request::builder()
+        .method(method)
+        .uri(url)
+        .header("Accept", "application/json");
+
+    if let Some(access_token) = access_token {
+        // The token is only exposed here, and the header is marked as sensitive so that it is
+        // left out of the `Debug` output of the request.
+        let mut authorization = format!("Bearer {}", access_token.expose_secret());
+        let header = http::HeaderValue::from_str(&authorization);
+        authorization.zeroize();
+
+        let mut header = header?;
+        header.set_sensitive(true);
+        builder = builder.header(http::header::AUTHORIZATION, header);
+    }
+
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(body.to_string().into_bytes())?,
+        None => builder.body(Vec::new())?,
+    };
+
+    Ok(request)
+}
+
+/// `path_segment` percent-encodes an ID for use as a path segment, refusing those which would change the path, such as `..`.
+fn path_segment(id: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if id.is_empty() || id == "." || id == ".." {
+        return Err(format!("invalid ID {id:?}: IDs can't be empty, `.` or `..`").into());
+    }
+    Ok(percent_encode(id))
+}
+
+/// `percent_encode` escapes every byte of a path segment or query value but the unreserved characters of RFC 3986, so that IDs and dates can't change the structure of the URL.
+pub(crate) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// `clone_request` copies a request so that it can be sent again, as `http::Request` is not `Clone`.
+pub(crate) fn clone_request(request: &http::Request<Vec<u8>>) -> http::Request<Vec<u8>> {
+    let mut clone = http::Request::new(request.body().clone());
+    *clone.method_mut() = request.method().clone();
+    *clone.uri_mut() = request.uri().clone();
+    *clone.version_mut() = request.version();
+    *clone.headers_mut() = request.headers().clone();
+    clone
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+use crate::rate_limit::RateLimit;
+
+/// The number of successful calls GoCardless allows per account, per endpoint, per day.
+pub const DEFAULT_DAILY_ACCOUNT_LIMIT: u32 = 4;
+
+const WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// `QuotaTracker` counts successful calls to the account data endpoints, per account and endpoint, in a rolling 24 hour window.
+///
+/// When configured on a `Client` via `ClientBuilder::quota_tracker`, calls which would exceed the daily limit are refused locally with `Error::QuotaExceeded` instead of being sent to the API. Every call reserves its slot before being sent, so concurrent calls can't exceed the limit together.
+///
+/// Whenever a response carries the `HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_*` headers, the state reported by GoCardless takes precedence over the local count until the reported reset time.
+///
+/// The state can be persisted with `save`/`load` or `to_json`/`from_json`, so that quota used by previous runs is taken into account.
+#[derive(Debug)]
+pub struct QuotaTracker {
+    daily_limit: u32,
+    usage: Mutex<HashMap<(String, Endpoint), Usage>>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    /// Unix timestamps of successful calls within the window
+    calls: Vec<u64>,
+    /// Calls allowed by `check` which have not been recorded or released yet
+    reserved: u32,
+    /// Remaining calls as reported by the API, until `reset_at`
+    reported: Option<(u32, u64)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedUsage {
+    account_id: String,
+    endpoint: Endpoint,
+    calls: Vec<u64>,
+    #[serde(default)]
+    reported_remaining: Option<u32>,
+    #[serde(default)]
+    reported_reset_at: Option<u64>,
+}
+
+impl Default for QuotaTracker {
+    fn default() -> Self {
+        QuotaTracker::new(DEFAULT_DAILY_ACCOUNT_LIMIT)
+    }
+}
+
+impl QuotaTracker {
+    /// `new` creates an empty `QuotaTracker` allowing `daily_limit` successful calls per account and endpoint in any 24 hour window.
+    pub fn new(daily_limit: u32) -> Self {
+        QuotaTracker {
+            daily_limit,
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `daily_limit` returns the number of calls allowed per account and endpoint in any 24 hour window.
+    pub fn daily_limit(&self) -> u32 {
+        self.daily_limit
+    }
+
+    /// `remaining` returns the number of calls that can currently be made to `endpoint` for the given account.
+    pub fn remaining(&self, account_id: &str, endpoint: Endpoint) -> u32 {
+        let now = unix_now();
+        let mut usage = self.usage.lock().unwrap();
+
+        match usage.get_mut(&(account_id.to_string(), endpoint)) {
+            Some(usage) => {
+                usage.prune(now);
+                usage.remaining(self.daily_limit, now)
+            }
+            None => self.daily_limit,
+        }
+    }
+
+    /// `check` reserves a call to `endpoint` for the given account, or returns `Error::QuotaExceeded` if it would exceed the daily limit.
+    ///
+    /// The reserved call counts against the limit until it is recorded with `record_success` or `record_rate_limited`, or given back with `release` if it failed without reaching the API.
+    pub fn check(&self, account_id: &str, endpoint: Endpoint) -> Result<(), Error> {
+        let now = unix_now();
+        let mut usage = self.usage.lock().unwrap();
+
+        let usage = usage.entry((account_id.to_string(), endpoint)).or_default();
+        usage.prune(now);
+
+        if usage.remaining(self.daily_limit, now) > 0 {
+            usage.reserved = usage.reserved.saturating_add(1);
+            return Ok(());
+        }
+
+        Err(Error::QuotaExceeded {
+            account_id: account_id.to_string(),
+            endpoint,
+            retry_after: usage.retry_after(now),
+        })
+    }
+
+    /// `release` gives back a call reserved by `check` which was not recorded, e.g. because it failed before reaching the API.
+    pub fn release(&self, account_id: &str, endpoint: Endpoint) {
+        let mut usage = self.usage.lock().unwrap();
+
+        if let Some(usage) = usage.get_mut(&(account_id.to_string(), endpoint)) {
+            usage.reserved = usage.reserved.saturating_sub(1);
+        }
+    }
+
+    /// `exceeded` returns the `Error::QuotaExceeded` reporting that no more calls to `endpoint` should be made for the given account, with when a call is possible again if the quota is exhausted.
+    pub(crate) fn exceeded(&self, account_id: &str, endpoint: Endpoint) -> Error {
+        let now = unix_now();
+        let mut usage = self.usage.lock().unwrap();
+
+        let retry_after = usage
+            .get_mut(&(account_id.to_string(), endpoint))
+            .and_then(|usage| {
+                usage.prune(now);
+                if usage.remaining(self.daily_limit, now) == 0 {
+                    usage.retry_after(now)
+                } else {
+                    None
+                }
+            });
+
+        Error::QuotaExceeded {
+            account_id: account_id.to_string(),
+            endpoint,
+            retry_after,
+        }
+    }
+
+    /// `record_success` records a successful call to `endpoint` for the given account, along with the account rate limit reported by the response, if any. The call takes the place of one reserved by `check`, if any.
+    pub fn record_success(
+        &self,
+        account_id: &str,
+        endpoint: Endpoint,
+        account_success: Option<&RateLimit>,
+    ) {
+        let now = unix_now();
+        let mut usage = self.usage.lock().unwrap();
+
+        let usage = usage.entry((account_id.to_string(), endpoint)).or_default();
+        usage.prune(now);
+        usage.reserved = usage.reserved.saturating_sub(1);
+        usage.calls.push(now);
+        if let Some(rate_limit) = account_success {
+            usage.report(rate_limit);
+        }
+    }
+
+    /// `record_rate_limited` records that the API refused a call to `endpoint` for the given account, along with the account rate limit reported by the response, if any. The call reserved by `check`, if any, is released.
+    pub fn record_rate_limited(
+        &self,
+        account_id: &str,
+        endpoint: Endpoint,
+        account_success: Option<&RateLimit>,
+        retry_after: Option<Duration>,
+    ) {
+        let mut usage = self.usage.lock().unwrap();
+
+        let usage = usage.entry((account_id.to_string(), endpoint)).or_default();
+        usage.reserved = usage.reserved.saturating_sub(1);
+        match account_success {
+            Some(rate_limit) if rate_limit.reset.is_some() => usage.report(rate_limit),
+            _ => {
+                if let Some(retry_after) = retry_after {
+                    usage.reported = Some((0, unix_now().saturating_add(retry_after.as_secs())));
+                }
+            }
+        }
+    }
+
+    /// `to_json` serializes the state of the tracker, to be restored with `from_json`.
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let now = unix_now();
+        let mut usage = self.usage.lock().unwrap();
+
+        let mut persisted: Vec<PersistedUsage> = usage
+            .iter_mut()
+            .map(|((account_id, endpoint), usage)| {
+                usage.prune(now);
+                PersistedUsage {
+                    account_id: account_id.clone(),
+                    endpoint: *endpoint,
+                    calls: usage.calls.clone(),
+                    reported_remaining: usage.reported.map(|(remaining, _)| remaining),
+                    reported_reset_at: usage.reported.map(|(_, reset_at)| reset_at),
+                }
+            })
+            .collect();
+        persisted.sort_by(|a, b| (&a.account_id, a.endpoint).cmp(&(&b.account_id, b.endpoint)));
+
+        Ok(serde_json::to_string(&persisted)?)
+    }
+
+    /// `from_json` restores a tracker from the state serialized by `to_json`.
+    pub fn from_json(daily_limit: u32, json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let persisted: Vec<PersistedUsage> = serde_json::from_str(json)?;
+
+        let usage = persisted
+            .into_iter()
+            .map(|persisted| {
+                let reported = persisted
+                    .reported_remaining
+                    .zip(persisted.reported_reset_at);
+                (
+                    (persisted.account_id, persisted.endpoint),
+                    Usage {
+                        calls: persisted.calls,
+                        reserved: 0,
+                        reported,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(QuotaTracker {
+            daily_limit,
+            usage: Mutex::new(usage),
+        })
+    }
+
+    /// `save` writes the state of the tracker to a file, to be restored with `load`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// `load` restores a tracker from a file written by `save`, or creates an empty one if the file does not exist.
+    pub fn load(
+        daily_limit: u32,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => QuotaTracker::from_json(daily_limit, &json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(QuotaTracker::new(daily_limit))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Usage {
+    fn prune(&mut self, now: u64) {
+        self.calls
+            .retain(|&call| call.saturating_add(WINDOW.as_secs()) > now && call <= now);
+        if matches!(self.reported, Some((_, reset_at)) if reset_at <= now) {
+            self.reported = None;
+        }
+    }
+
+    fn remaining(&self, daily_limit: u32, now: u64) -> u32 {
+        let remaining = match self.reported {
+            Some((remaining, reset_at)) if reset_at > now => remaining,
+            _ => daily_limit.saturating_sub(u32::try_from(self.calls.len()).unwrap_or(u32::MAX)),
+        };
+        remaining.saturating_sub(self.reserved)
+    }
+
+    /// Returns how long until a call is possible again, once the quota is exhausted.
+    fn retry_after(&self, now: u64) -> Option<Duration> {
+        let retry_after = match self.reported {
+            Some((_, reset_at)) if reset_at > now => Some(reset_at - now),
+            _ => self
+                .calls
+                .iter()
+                .min()
+                .map(|oldest| oldest.saturating_add(WINDOW.as_secs()).saturating_sub(now)),
+        };
+        retry_after.map(Duration::from_secs)
+    }
+
+    fn report(&mut self, rate_limit: &RateLimit) {
+        if let (Some(remaining), Some(reset_at)) = (rate_limit.remaining, rate_limit.reset_at()) {
+            self.reported = Some((remaining, unix_seconds(reset_at)));
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    unix_seconds(SystemTime::now())
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime};
+
+use http::HeaderMap;
+
+/// The state of a single rate limit, as reported by the headers of an API response.
+///
+/// Every field is optional as GoCardless does not always send every header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Number of requests allowed in the current window
+    pub limit: Option<u32>,
+    /// Number of requests remaining in the current window
+    pub remaining: Option<u32>,
+    /// Time until the current window resets, relative to `observed_at`
+    pub reset: Option<Duration>,
+    /// When the response carrying this rate limit was received
+    pub observed_at: SystemTime,
+}
+
+impl RateLimit {
+    /// Returns the point in time at which the current window resets, if known and representable
+    pub fn reset_at(&self) -> Option<SystemTime> {
+        self.reset
+            .and_then(|reset| self.observed_at.checked_add(reset))
+    }
+
+    /// Returns `true` if the API reported that no requests remain in the current window
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    fn from_headers(headers: &HeaderMap, prefix: &str, observed_at: SystemTime) -> Option<Self> {
+        let limit = header_number(headers, prefix, "limit");
+        let remaining = header_number(headers, prefix, "remaining");
+        let reset = header_number(headers, prefix, "reset").map(Duration::from_secs);
+
+        if limit.is_none() && remaining.is_none() && reset.is_none() {
+            return None;
+        }
+
+        Some(RateLimit {
+            limit: limit.and_then(|limit| u32::try_from(limit).ok()),
+            remaining: remaining.and_then(|remaining| u32::try_from(remaining).ok()),
+            reset,
+            observed_at,
+        })
+    }
+}
+
+/// The rate limits reported by the headers of an API response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// The general rate limit, from the `HTTP_X_RATELIMIT_*` headers
+    pub general: Option<RateLimit>,
+    /// The per account limit on successful calls to the account data endpoints, from the
+    /// `HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_*` headers
+    pub account_success: Option<RateLimit>,
+}
+
+impl RateLimits {
+    /// `from_headers` reads the rate limits from the headers of an API response.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let observed_at = SystemTime::now();
+
+        RateLimits {
+            general: RateLimit::from_headers(headers, "ratelimit", observed_at),
+            account_success: RateLimit::from_headers(
+                headers,
+                "ratelimit_account_success",
+                observed_at,
+            ),
+        }
+    }
+
+    /// Returns the time until the exhausted rate limit resets, preferring the account limit
+    pub(crate) fn retry_after(&self) -> Option<Duration> {
+        self.account_success
+            .iter()
+            .chain(self.general.iter())
+            .find(|rate_limit| rate_limit.is_exhausted())
+            .and_then(|rate_limit| rate_limit.reset)
+    }
+}
+
+/// Reads a numeric rate limit header, accepting both the `HTTP_X_RATELIMIT_LIMIT` spelling used by
+/// GoCardless and the conventional `X-RateLimit-Limit` spelling.
+fn header_number(headers: &HeaderMap, prefix: &str, name: &str) -> Option<u64> {
+    let gocardless = format!("http_x_{prefix}_{name}");
+    let conventional = format!("x-{}-{name}", prefix.replace('_', "-"));
+
+    headers
+        .get(gocardless.as_str())
+        .or_else(|| headers.get(conventional.as_str()))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Reads the `Retry-After` header, which GoCardless sends as a number of seconds
+pub(crate) fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(http::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
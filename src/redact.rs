@@ -0,0 +1,316 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::{Map, Value};
+
+use crate::model::{Account, CreateTokenResponse, CreditorAccount, Transaction};
+
+static DEBUG_REDACTION: AtomicBool = AtomicBool::new(false);
+
+const REDACTED: &str = "[REDACTED]";
+
+/// `set_debug_redaction` enables or disables the masking of personal data in the `Debug` output of `Account`, `Transaction` and `CreditorAccount`, for the whole process.
+///
+/// Redaction is disabled by default. When enabled, IBANs and account numbers only show their last four characters (e.g. `GB** **** 1234`), while names, addresses, phone numbers, remittance information and unmodelled fields are replaced by `[REDACTED]`.
+///
+/// The tokens of `CreateTokenResponse` are never printed, whether redaction is enabled or not.
+///
+/// # Examples
+///
+/// ```
+/// gocardless_unofficial::set_debug_redaction(true);
+///
+/// let details = client.get_account_details("account_id").await?;
+/// dbg!(&details); // iban: Some("GB** **** 5555"), owner_name: Some("[REDACTED]"), ...
+/// ```
+pub fn set_debug_redaction(enabled: bool) {
+    DEBUG_REDACTION.store(enabled, Ordering::Relaxed);
+}
+
+/// `is_debug_redaction_enabled` returns `true` if personal data is masked in `Debug` output, see `set_debug_redaction`.
+pub fn is_debug_redaction_enabled() -> bool {
+    DEBUG_REDACTION.load(Ordering::Relaxed)
+}
+
+/// `mask_iban` masks an IBAN down to its country code and last four characters, e.g. `GB** **** 1234`.
+pub fn mask_iban(iban: &str) -> String {
+    let iban: String = iban.chars().filter(|c| !c.is_whitespace()).collect();
+    if iban.chars().count() < 8 {
+        return "****".to_string();
+    }
+
+    let country: String = iban.chars().take(2).collect();
+    format!("{country}** **** {}", last_four(&iban))
+}
+
+/// `mask_account_number` masks an account number down to its last four characters, e.g. `**** 1234`.
+pub fn mask_account_number(account_number: &str) -> String {
+    let account_number: String = account_number
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if account_number.chars().count() < 8 {
+        return "****".to_string();
+    }
+
+    format!("**** {}", last_four(&account_number))
+}
+
+fn last_four(value: &str) -> String {
+    let skip = value.chars().count().saturating_sub(4);
+    value.chars().skip(skip).collect()
+}
+
+/// Prints an optional field as is, or masked with `mask` when redaction is enabled.
+struct Sensitive<'a, T> {
+    value: &'a Option<T>,
+    mask: fn(&T) -> String,
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Sensitive<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(value) if is_debug_redaction_enabled() => {
+                f.debug_tuple("Some").field(&(self.mask)(value)).finish()
+            }
+            value => value.fmt(f),
+        }
+    }
+}
+
+fn sensitive<T>(value: &Option<T>, mask: fn(&T) -> String) -> Sensitive<'_, T> {
+    Sensitive { value, mask }
+}
+
+fn redact<T>(_: &T) -> String {
+    REDACTED.to_string()
+}
+
+/// Prints unmodelled fields as is, or only their keys when redaction is enabled.
+struct Extra<'a>(&'a Map<String, Value>);
+
+impl<'a> fmt::Debug for Extra<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !is_debug_redaction_enabled() {
+            return self.0.fmt(f);
+        }
+
+        f.debug_map()
+            .entries(self.0.keys().map(|key| (key, REDACTED)))
+            .finish()
+    }
+}
+
+impl fmt::Debug for CreateTokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreateTokenResponse")
+            .field("access", &REDACTED)
+            .field("access_expires", &self.access_expires)
+            .field("refresh", &REDACTED)
+            .field("refresh_expires", &self.refresh_expires)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("resource_id", &self.resource_id)
+            .field("iban", &sensitive(&self.iban, |iban| mask_iban(iban)))
+            .field(
+                "bban",
+                &sensitive(&self.bban, |bban| mask_account_number(bban)),
+            )
+            .field("bic", &self.bic)
+            .field("msisdn", &sensitive(&self.msisdn, redact))
+            .field("currency", &self.currency)
+            .field("owner_name", &sensitive(&self.owner_name, redact))
+            .field(
+                "owner_address_unstructured",
+                &sensitive(&self.owner_address_unstructured, redact),
+            )
+            .field("name", &sensitive(&self.name, redact))
+            .field("display_name", &sensitive(&self.display_name, redact))
+            .field("details", &self.details)
+            .field("product", &self.product)
+            .field("cash_account_type", &self.cash_account_type)
+            .field("status", &self.status)
+            .field("linked_accounts", &self.linked_accounts)
+            .field("usage", &self.usage)
+            .field("extra", &Extra(&self.extra))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("transaction_id", &self.transaction_id)
+            .field("booking_date", &self.booking_date)
+            .field("value_date", &self.value_date)
+            .field("booking_date_time", &self.booking_date_time)
+            .field("value_date_time", &self.value_date_time)
+            .field("transaction_amount", &self.transaction_amount)
+            .field("creditor_name", &sensitive(&self.creditor_name, redact))
+            .field(
+                "remittance_information_unstructured",
+                &sensitive(&self.remittance_information_unstructured, redact),
+            )
+            .field(
+                "remittance_information_unstructured_array",
+                &sensitive(&self.remittance_information_unstructured_array, redact),
+            )
+            .field(
+                "remittance_information_structured",
+                &sensitive(&self.remittance_information_structured, redact),
+            )
+            .field(
+                "remittance_information_structured_array",
+                &sensitive(&self.remittance_information_structured_array, redact),
+            )
+            .field(
+                "proprietary_bank_transaction_code",
+                &self.proprietary_bank_transaction_code,
+            )
+            .field("internal_transaction_id", &self.internal_transaction_id)
+            .field("debtor_name", &sensitive(&self.debtor_name, redact))
+            .field("creditor_account", &self.creditor_account)
+            .field("debtor_account", &self.debtor_account)
+            .field("currency_exchange", &self.currency_exchange)
+            .field("extra", &Extra(&self.extra))
+            .finish()
+    }
+}
+
+impl fmt::Debug for CreditorAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreditorAccount")
+            .field("iban", &sensitive(&self.iban, |iban| mask_iban(iban)))
+            .field(
+                "bban",
+                &sensitive(&self.bban, |bban| mask_account_number(bban)),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use secrecy::SecretString;
+
+    use super::*;
+
+    /// Serialises the tests depending on the process wide redaction setting.
+    static REDACTION: Mutex<()> = Mutex::new(());
+
+    /// Runs `f` with redaction set to `enabled`, restoring the default afterwards.
+    fn with_redaction<T>(enabled: bool, f: impl FnOnce() -> T) -> T {
+        let _guard = REDACTION.lock().unwrap();
+        set_debug_redaction(enabled);
+        let result = f();
+        set_debug_redaction(false);
+        result
+    }
+
+    #[test]
+    fn masks_ibans() {
+        assert_eq!(mask_iban("GB29NWBK60161331926819"), "GB** **** 6819");
+        assert_eq!(mask_iban("GB29 NWBK 6016 1331 9268 19"), "GB** **** 6819");
+        assert_eq!(mask_iban(" BE68\t5390 0754 7034 "), "BE** **** 7034");
+        assert_eq!(mask_iban("DE89 3704 0044 0532 0130 ÄÖ"), "DE** **** 30ÄÖ");
+        assert_eq!(mask_iban("ÄÖ12 3456 7890"), "ÄÖ** **** 7890");
+        assert_eq!(mask_iban("GB12345"), "****");
+        assert_eq!(mask_iban("   "), "****");
+        assert_eq!(mask_iban(""), "****");
+    }
+
+    #[test]
+    fn masks_account_numbers() {
+        assert_eq!(mask_account_number("12345678"), "**** 5678");
+        assert_eq!(mask_account_number("1234 5678 9012"), "**** 9012");
+        assert_eq!(mask_account_number("1234567é"), "**** 567é");
+        assert_eq!(mask_account_number("1234567"), "****");
+        assert_eq!(mask_account_number(""), "****");
+    }
+
+    fn account() -> Account {
+        Account {
+            resource_id: "resource".to_string(),
+            iban: Some("GB29NWBK60161331926819".to_string()),
+            bban: Some("NWBK60161331926819".to_string()),
+            currency: "GBP".to_string(),
+            owner_name: Some("Jane Doe".to_string()),
+            extra: [("ownerAddressStructured".to_string(), "Ghent".into())]
+                .into_iter()
+                .collect(),
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn redacts_debug_output_when_enabled() {
+        let (redacted, plain) = (
+            with_redaction(true, || format!("{:?}", account())),
+            with_redaction(false, || format!("{:?}", account())),
+        );
+
+        assert!(redacted.contains(r#"iban: Some("GB** **** 6819")"#));
+        assert!(redacted.contains(r#"bban: Some("**** 6819")"#));
+        assert!(redacted.contains(r#"owner_name: Some("[REDACTED]")"#));
+        assert!(redacted.contains(r#""ownerAddressStructured": "[REDACTED]""#));
+        assert!(redacted.contains(r#"resource_id: "resource""#));
+        assert!(!redacted.contains("Jane") && !redacted.contains("Ghent"));
+        assert!(!redacted.contains("60161331926819"));
+
+        assert!(plain.contains(r#"iban: Some("GB29NWBK60161331926819")"#));
+        assert!(plain.contains(r#"owner_name: Some("Jane Doe")"#));
+        assert!(plain.contains("Ghent"));
+        // Missing values are printed as they are
+        assert!(plain.contains("msisdn: None"));
+    }
+
+    #[test]
+    fn redacts_transactions_and_creditor_accounts() {
+        let transaction = Transaction {
+            creditor_name: Some("Coffee Shop".to_string()),
+            remittance_information_unstructured_array: Some(vec!["Card 1234".to_string()]),
+            creditor_account: Some(CreditorAccount {
+                iban: Some("BE68539007547034".to_string()),
+                bban: None,
+            }),
+            debtor_account: Some(CreditorAccount {
+                iban: None,
+                bban: Some("539007547034".to_string()),
+            }),
+            ..Transaction::default()
+        };
+
+        let redacted = with_redaction(true, || format!("{transaction:?}"));
+        assert!(!redacted.contains("Coffee") && !redacted.contains("Card 1234"));
+        assert!(redacted.contains(r#"iban: Some("BE** **** 7034")"#));
+        assert!(redacted.contains("bban: None"));
+        assert!(!redacted.contains("539007547034"));
+
+        let plain = with_redaction(false, || format!("{transaction:?}"));
+        assert!(plain.contains("Coffee Shop") && plain.contains("BE68539007547034"));
+        assert!(plain.contains(r#"bban: Some("539007547034")"#));
+    }
+
+    #[test]
+    fn never_prints_tokens() {
+        let response = CreateTokenResponse {
+            access: SecretString::new("access-token".to_string()),
+            access_expires: 86400,
+            refresh: SecretString::new("refresh-token".to_string()),
+            refresh_expires: 2592000,
+        };
+
+        for enabled in [true, false] {
+            let debug = with_redaction(enabled, || format!("{response:?}"));
+            assert!(!debug.contains("access-token") && !debug.contains("refresh-token"));
+            assert!(debug.contains("access_expires: 86400"));
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::fmt;
+
+use crate::model::Transaction;
+
+/// Words which, when directly preceding a number, mark it as a payment reference.
+///
+/// Bare numbers are only recognised as references after one of these words, or when they make up the whole remittance field, as otherwise too many unrelated numbers pass the checksums by chance.
+const GENERIC_KEYWORDS: &[&str] = &["ref", "reference", "referanse", "referens", "viesti"];
+const FINNISH_KEYWORDS: &[&str] = &["viite", "viitenro", "viitenumero", "viitenumerot"];
+const NORWEGIAN_KEYWORDS: &[&str] = &["kid", "kidnr", "kid-nr", "kidnummer"];
+
+/// A structured payment reference found in the remittance information of a transaction.
+///
+/// Every variant holds the normalised reference, i.e. without any spaces, separators or delimiters. The `parse_*` constructors and `parse_payment_references` only return references whose checksum is valid, while variants built directly are not validated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PaymentReference {
+    /// ISO 11649 creditor reference, e.g. `RF18539007547034`
+    Creditor(String),
+    /// Belgian structured communication (OGM/VCS), e.g. `123456789002` for `+++123/4567/89002+++`
+    BelgianStructured(String),
+    /// Finnish national reference number (viitenumero)
+    Finnish(String),
+    /// Norwegian customer identification number (KID), validated with either MOD10 or MOD11
+    NorwegianKid(String),
+}
+
+impl PaymentReference {
+    /// Parses and validates an ISO 11649 creditor reference, ignoring spaces and case
+    pub fn parse_creditor(reference: &str) -> Option<Self> {
+        let normalised: String = reference
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        if is_valid_creditor_reference(&normalised) {
+            Some(PaymentReference::Creditor(normalised))
+        } else {
+            None
+        }
+    }
+
+    /// Parses and validates a Belgian structured communication.
+    ///
+    /// Accepts the printed `+++123/4567/89002+++` and `***123/4567/89002***` forms, with or without the delimiters, as well as the bare 12 digits.
+    pub fn parse_belgian_structured(reference: &str) -> Option<Self> {
+        let inner = reference.trim().trim_matches(|c| c == '+' || c == '*');
+
+        let digits: String = if inner.contains('/') {
+            let groups: Vec<&str> = inner.split('/').collect();
+            let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+            if lengths != [3, 4, 5] {
+                return None;
+            }
+            groups.concat()
+        } else {
+            inner.to_string()
+        };
+
+        if is_valid_belgian_structured(&digits) {
+            Some(PaymentReference::BelgianStructured(digits))
+        } else {
+            None
+        }
+    }
+
+    /// Parses and validates a Finnish reference number, ignoring spaces
+    pub fn parse_finnish(reference: &str) -> Option<Self> {
+        let normalised: String = reference.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if is_valid_finnish_reference(&normalised) {
+            Some(PaymentReference::Finnish(normalised))
+        } else {
+            None
+        }
+    }
+
+    /// Parses and validates a Norwegian KID number, ignoring spaces
+    pub fn parse_norwegian_kid(reference: &str) -> Option<Self> {
+        let normalised: String = reference.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if is_valid_norwegian_kid(&normalised) {
+            Some(PaymentReference::NorwegianKid(normalised))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the normalised reference
+    pub fn as_str(&self) -> &str {
+        match self {
+            PaymentReference::Creditor(reference)
+            | PaymentReference::BelgianStructured(reference)
+            | PaymentReference::Finnish(reference)
+            | PaymentReference::NorwegianKid(reference) => reference,
+        }
+    }
+}
+
+impl fmt::Display for PaymentReference {
+    /// Formats the reference the way it is usually printed on invoices
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentReference::BelgianStructured(digits)
+                if digits.len() == 12 && is_digits(digits) =>
+            {
+                write!(
+                    f,
+                    "+++{}/{}/{}+++",
+                    &digits[..3],
+                    &digits[3..7],
+                    &digits[7..]
+                )
+            }
+            other => f.write_str(other.as_str()),
+        }
+    }
+}
+
+/// Extracts every valid payment reference from a piece of remittance information.
+///
+/// Creditor references and Belgian structured communications are recognised anywhere in the text. Bare numbers are only considered when they make up the whole text or directly follow a word such as `ref`, `viite` or `KID`; whether they are validated as Finnish references or Norwegian KIDs depends on that word, or otherwise on the transaction `currency` (`EUR` or `NOK`). References are returned in the order they appear, without duplicates.
+pub fn parse_payment_references(text: &str, currency: Option<&str>) -> Vec<PaymentReference> {
+    let tokens: Vec<&str> = text
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '*' | '/' | '-')))
+        .map(|token| token.trim_start_matches('-'))
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    let mut references = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+
+        if token.contains('/') {
+            if let Some(reference) = PaymentReference::parse_belgian_structured(token) {
+                push_unique(&mut references, reference);
+            }
+            i += 1;
+            continue;
+        }
+
+        if starts_creditor_reference(token) {
+            if let Some((reference, end)) = parse_creditor_tokens(&tokens, i) {
+                push_unique(&mut references, reference);
+                i = end;
+                continue;
+            }
+        }
+
+        if is_digits(token.trim_end_matches('-')) {
+            // references are often printed in groups of five digits, so the longest run of short
+            // digit groups which forms a valid reference is used
+            let mut end = i + 1;
+            while end < tokens.len()
+                && is_digits(tokens[end - 1])
+                && tokens[end - 1].len() <= 5
+                && is_digits(tokens[end].trim_end_matches('-'))
+                && tokens[end].len() <= 5
+            {
+                end += 1;
+            }
+
+            let keyword = i
+                .checked_sub(1)
+                .map(|previous| tokens[previous].to_ascii_lowercase());
+
+            let found = (i + 1..=end).rev().find_map(|end| {
+                let digits = tokens[i..end].concat();
+                let reference = match keyword.as_deref() {
+                    Some(keyword) if FINNISH_KEYWORDS.contains(&keyword) => {
+                        PaymentReference::parse_finnish(&digits)
+                    }
+                    Some(keyword) if NORWEGIAN_KEYWORDS.contains(&keyword) => {
+                        PaymentReference::parse_norwegian_kid(&digits)
+                    }
+                    Some(keyword) if GENERIC_KEYWORDS.contains(&keyword) => {
+                        parse_by_currency(&digits, currency)
+                    }
+                    _ if i == 0 && end == tokens.len() => parse_by_currency(&digits, currency),
+                    _ => None,
+                };
+                reference.map(|reference| (reference, end))
+            });
+
+            if let Some((reference, end)) = found {
+                push_unique(&mut references, reference);
+                i = end;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    references
+}
+
+impl Transaction {
+    /// Extracts every valid payment reference from the structured and unstructured remittance information of the transaction, using its currency to disambiguate bare numbers.
+    ///
+    /// See [`parse_payment_references`] for the rules used.
+    pub fn payment_references(&self) -> Vec<PaymentReference> {
+        let currency = Some(self.transaction_amount.currency.as_str());
+
+        let fields = self
+            .remittance_information_structured
+            .iter()
+            .chain(
+                self.remittance_information_structured_array
+                    .iter()
+                    .flatten(),
+            )
+            .chain(self.remittance_information_unstructured.iter())
+            .chain(
+                self.remittance_information_unstructured_array
+                    .iter()
+                    .flatten(),
+            );
+
+        let mut references = Vec::new();
+        for field in fields {
+            for reference in parse_payment_references(field, currency) {
+                push_unique(&mut references, reference);
+            }
+        }
+
+        references
+    }
+}
+
+fn push_unique(references: &mut Vec<PaymentReference>, reference: PaymentReference) {
+    if !references.contains(&reference) {
+        references.push(reference);
+    }
+}
+
+fn parse_by_currency(digits: &str, currency: Option<&str>) -> Option<PaymentReference> {
+    match currency
+        .map(|currency| currency.to_ascii_uppercase())
+        .as_deref()
+    {
+        Some("EUR") => PaymentReference::parse_finnish(digits),
+        Some("NOK") => PaymentReference::parse_norwegian_kid(digits),
+        _ => None,
+    }
+}
+
+fn is_digits(token: &str) -> bool {
+    !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn starts_creditor_reference(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    bytes.len() >= 4
+        && bytes[..2].eq_ignore_ascii_case(b"RF")
+        && bytes[2].is_ascii_digit()
+        && bytes[3].is_ascii_digit()
+}
+
+/// Creditor references are often printed in groups of four characters, so the longest run of tokens starting at `start` which forms a valid reference is used.
+fn parse_creditor_tokens(tokens: &[&str], start: usize) -> Option<(PaymentReference, usize)> {
+    let mut end = start + 1;
+    let mut length = tokens[start].len();
+    while end < tokens.len()
+        && tokens[end].len() <= 4
+        && tokens[end].bytes().all(|b| b.is_ascii_alphanumeric())
+        && length + tokens[end].len() <= 25
+    {
+        length += tokens[end].len();
+        end += 1;
+    }
+
+    (start + 1..=end).rev().find_map(|end| {
+        PaymentReference::parse_creditor(&tokens[start..end].concat())
+            .map(|reference| (reference, end))
+    })
+}
+
+/// ISO 11649: `RF`, two check digits and up to 21 alphanumeric characters, validated with ISO 7064 MOD 97-10 after moving the first four characters to the end
+fn is_valid_creditor_reference(reference: &str) -> bool {
+    let bytes = reference.as_bytes();
+    if !(5..=25).contains(&bytes.len())
+        || !bytes.starts_with(b"RF")
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+        || !bytes[4..].iter().all(u8::is_ascii_alphanumeric)
+    {
+        return false;
+    }
+
+    let mut remainder = 0u32;
+    for &b in bytes[4..].iter().chain(&bytes[..4]) {
+        let value = match b {
+            b'0'..=b'9' => u32::from(b - b'0'),
+            _ => u32::from(b.to_ascii_uppercase() - b'A') + 10,
+        };
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+
+    remainder == 1
+}
+
+/// Belgian OGM/VCS: 12 digits, where the last two are the first ten modulo 97 (or 97 if zero)
+fn is_valid_belgian_structured(digits: &str) -> bool {
+    if digits.len() != 12 || !is_digits(digits) {
+        return false;
+    }
+
+    let base: u64 = digits[..10].parse().unwrap_or(0);
+    let check: u64 = digits[10..].parse().unwrap_or(0);
+    let expected = match base % 97 {
+        0 => 97,
+        remainder => remainder,
+    };
+
+    check == expected
+}
+
+/// Finnish reference: 4 to 20 digits, where the last digit is a check digit computed with the repeating weights 7, 3, 1 from right to left
+fn is_valid_finnish_reference(digits: &str) -> bool {
+    if !(4..=20).contains(&digits.len()) || !is_digits(digits) {
+        return false;
+    }
+
+    let bytes = digits.as_bytes();
+    let (body, check) = bytes.split_at(bytes.len() - 1);
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .zip([7, 3, 1].iter().cycle())
+        .map(|(b, weight)| u32::from(b - b'0') * weight)
+        .sum();
+
+    (10 - sum % 10) % 10 == u32::from(check[0] - b'0')
+}
+
+/// Norwegian KID: 2 to 25 digits, where the last character is either a MOD10 (Luhn) or a MOD11 check digit. A MOD11 remainder of 10 is written as `-`.
+fn is_valid_norwegian_kid(kid: &str) -> bool {
+    if !(2..=25).contains(&kid.len()) {
+        return false;
+    }
+
+    let bytes = kid.as_bytes();
+    let (body, check) = bytes.split_at(bytes.len() - 1);
+    if !body.iter().all(u8::is_ascii_digit) {
+        return false;
+    }
+
+    let mod11 = {
+        let sum: u32 = body
+            .iter()
+            .rev()
+            .zip([2, 3, 4, 5, 6, 7].iter().cycle())
+            .map(|(b, weight)| u32::from(b - b'0') * weight)
+            .sum();
+        match 11 - sum % 11 {
+            11 => b'0',
+            10 => b'-',
+            digit => b'0' + digit as u8,
+        }
+    };
+    if check[0] == mod11 {
+        return true;
+    }
+
+    if !check[0].is_ascii_digit() {
+        return false;
+    }
+
+    let luhn: u32 = bytes
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let digit = u32::from(b - b'0');
+            if i % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                digit
+            }
+        })
+        .sum();
+
+    luhn % 10 == 0
+}
@@ -0,0 +1,328 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::{Duration, SystemTime};
+
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+
+/// `RetryPolicy` controls how `Client` retries requests that failed with a transient error.
+///
+/// Retries are delayed with exponential backoff: the `n`th retry waits `initial_backoff * multiplier^(n - 1)`, capped at `max_backoff`, with up to `jitter` of that delay randomly removed so that concurrent clients don't retry in lockstep.
+///
+/// Calls to non-idempotent endpoints, such as `create_requisition`, are never retried unless `retry_non_idempotent` is set, as a request may have succeeded even though its response was lost.
+///
+/// # Examples
+///
+/// ```
+/// let policy = RetryPolicy {
+///     max_attempts: 5,
+///     ..RetryPolicy::default()
+/// };
+/// let client = Client::builder(secret_id, secret_key)
+///     .retry_policy(policy)
+///     .build()
+///     .await?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after every retry
+    pub multiplier: f64,
+    /// Fraction of the delay, between `0.0` and `1.0`, which is randomly removed
+    pub jitter: f64,
+    /// HTTP status codes which are retried
+    pub retryable_statuses: Vec<u16>,
+    /// Whether connection errors and timeouts are retried
+    pub retry_connection_errors: bool,
+    /// Whether the `Retry-After` delay of rate limited responses is waited for, rather than the backoff
+    pub honor_retry_after: bool,
+    /// Rate limited responses asking to wait longer than this are not retried
+    pub max_retry_after: Duration,
+    /// Whether calls to non-idempotent endpoints are retried
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retryable_statuses: vec![429, 500, 502, 503, 504],
+            retry_connection_errors: true,
+            honor_retry_after: true,
+            max_retry_after: Duration::from_secs(60),
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// `none` returns a policy which never retries, the behaviour of a `Client` without a configured policy.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// `backoff` returns the delay before retrying after the given failed attempt, starting at `1`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        let delay = delay.min(self.max_backoff.as_secs_f64());
+
+        let jitter = if self.jitter.is_nan() {
+            0.0
+        } else {
+            self.jitter.clamp(0.0, 1.0) * random_fraction(attempt)
+        };
+        saturating_duration(delay * (1.0 - jitter))
+    }
+
+    /// `retry_delay` returns how long to wait before retrying a call to `endpoint` which failed on the given attempt, or `None` if it should not be retried.
+    pub(crate) fn retry_delay(
+        &self,
+        endpoint: Endpoint,
+        attempt: u32,
+        error: &(dyn std::error::Error + 'static),
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || (!endpoint.is_idempotent() && !self.retry_non_idempotent)
+        {
+            return None;
+        }
+
+        if let Some(error) = error.downcast_ref::<Error>() {
+            return match error {
+                Error::RateLimited { retry_after, .. }
+                    if self.retryable_statuses.contains(&429) =>
+                {
+                    match retry_after {
+                        Some(retry_after) if *retry_after > self.max_retry_after => None,
+                        Some(retry_after) if self.honor_retry_after => Some(*retry_after),
+                        _ => Some(self.backoff(attempt)),
+                    }
+                }
+                Error::Api { status, .. } if self.retryable_statuses.contains(status) => {
+                    Some(self.backoff(attempt))
+                }
+                _ => None,
+            };
+        }
+
+        #[cfg(feature = "reqwest")]
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            if self.retry_connection_errors
+                && (error.is_connect() || error.is_timeout() || error.is_request())
+            {
+                return Some(self.backoff(attempt));
+            }
+        }
+
+        // custom transports are likely to report connection failures as I/O errors
+        if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            if self.retry_connection_errors
+                && matches!(
+                    error.kind(),
+                    std::io::ErrorKind::ConnectionRefused
+                        | std::io::ErrorKind::ConnectionReset
+                        | std::io::ErrorKind::ConnectionAborted
+                        | std::io::ErrorKind::NotConnected
+                        | std::io::ErrorKind::BrokenPipe
+                        | std::io::ErrorKind::TimedOut
+                        | std::io::ErrorKind::UnexpectedEof
+                )
+            {
+                return Some(self.backoff(attempt));
+            }
+        }
+
+        None
+    }
+}
+
+/// Converts seconds to a `Duration`, saturating at `Duration::MAX` and treating negative or NaN values as zero, where `Duration::from_secs_f64` would panic
+pub(crate) fn saturating_duration(seconds: f64) -> Duration {
+    if seconds.is_nan() || seconds <= 0.0 {
+        Duration::ZERO
+    } else if seconds >= Duration::MAX.as_secs_f64() {
+        Duration::MAX
+    } else {
+        Duration::from_secs_f64(seconds)
+    }
+}
+
+/// Returns a pseudo random number between `0.0` and `1.0`, good enough for jitter
+fn random_fraction(attempt: u32) -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    attempt.hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limit::RateLimits;
+
+    fn without_jitter() -> RetryPolicy {
+        RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn api_error(status: u16) -> Error {
+        Error::Api {
+            status,
+            summary: None,
+            detail: None,
+        }
+    }
+
+    fn rate_limited(retry_after: Option<Duration>) -> Error {
+        Error::RateLimited {
+            retry_after,
+            rate_limits: RateLimits::default(),
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        let policy = without_jitter();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), Duration::from_secs(30));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(30));
+
+        let policy = RetryPolicy::default();
+        for attempt in 1..10 {
+            let backoff = policy.backoff(attempt);
+            let max = without_jitter().backoff(attempt);
+            assert!(backoff <= max && backoff >= max / 2, "{backoff:?}");
+        }
+    }
+
+    #[test]
+    fn backoff_never_panics() {
+        let policy = RetryPolicy {
+            jitter: f64::NAN,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+
+        let policy = RetryPolicy {
+            max_backoff: Duration::MAX,
+            multiplier: 1e300,
+            ..without_jitter()
+        };
+        assert_eq!(policy.backoff(3), Duration::MAX);
+
+        let policy = RetryPolicy {
+            multiplier: f64::NAN,
+            ..without_jitter()
+        };
+        assert_eq!(policy.backoff(3), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn retries_retryable_statuses() {
+        let policy = without_jitter();
+        let endpoint = Endpoint::ListTransactions;
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &api_error(503)),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            policy.retry_delay(endpoint, 2, &api_error(500)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(policy.retry_delay(endpoint, 1, &api_error(400)), None);
+        assert_eq!(policy.retry_delay(endpoint, 1, &api_error(404)), None);
+        // The last attempt is not retried
+        assert_eq!(policy.retry_delay(endpoint, 3, &api_error(503)), None);
+    }
+
+    #[test]
+    fn honors_retry_after() {
+        let policy = without_jitter();
+        let endpoint = Endpoint::ListTransactions;
+        let retry_after = Duration::from_secs(7);
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &rate_limited(Some(retry_after))),
+            Some(retry_after)
+        );
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &rate_limited(None)),
+            Some(Duration::from_millis(500))
+        );
+        // Waiting longer than `max_retry_after` is not worth it
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &rate_limited(Some(Duration::from_secs(3600)))),
+            None
+        );
+
+        let policy = RetryPolicy {
+            honor_retry_after: false,
+            ..without_jitter()
+        };
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &rate_limited(Some(retry_after))),
+            Some(Duration::from_millis(500))
+        );
+
+        let policy = RetryPolicy {
+            retryable_statuses: vec![503],
+            ..without_jitter()
+        };
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &rate_limited(Some(retry_after))),
+            None
+        );
+    }
+
+    #[test]
+    fn retries_non_idempotent_calls_only_when_allowed() {
+        let endpoint = Endpoint::CreateRequisition;
+        assert_eq!(
+            without_jitter().retry_delay(endpoint, 1, &api_error(503)),
+            None
+        );
+
+        let policy = RetryPolicy {
+            retry_non_idempotent: true,
+            ..without_jitter()
+        };
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &api_error(503)),
+            Some(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn retries_connection_errors() {
+        let policy = without_jitter();
+        let endpoint = Endpoint::ListTransactions;
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert_eq!(
+            policy.retry_delay(endpoint, 1, &reset),
+            Some(Duration::from_millis(500))
+        );
+        let invalid = std::io::Error::from(std::io::ErrorKind::InvalidData);
+        assert_eq!(policy.retry_delay(endpoint, 1, &invalid), None);
+
+        let policy = RetryPolicy {
+            retry_connection_errors: false,
+            ..without_jitter()
+        };
+        assert_eq!(policy.retry_delay(endpoint, 1, &reset), None);
+    }
+}
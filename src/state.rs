@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use secrecy::SecretString;
+use serde::de::DeserializeOwned;
+
+use crate::endpoint::Endpoint;
+use crate::error::Error;
+use crate::metrics::{ErrorKind, MetricsRecorder, RequestMetrics, StatusClass};
+use crate::model::CreateTokenResponse;
+use crate::protocol;
+use crate::quota::QuotaTracker;
+use crate::rate_limit::{RateLimit, RateLimits};
+use crate::retry::RetryPolicy;
+use crate::telemetry::RequestSpan;
+use crate::transport::TransportError;
+
+/// `Call` identifies a single API call, for quota tracking and instrumentation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Call<'a> {
+    pub(crate) endpoint: Endpoint,
+    pub(crate) account_id: Option<&'a str>,
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    pub(crate) institution_id: Option<&'a str>,
+}
+
+impl<'a> Call<'a> {
+    pub(crate) fn new(endpoint: Endpoint) -> Self {
+        Call {
+            endpoint,
+            account_id: None,
+            institution_id: None,
+        }
+    }
+
+    pub(crate) fn account(endpoint: Endpoint, account_id: &'a str) -> Self {
+        Call {
+            account_id: Some(account_id),
+            ..Call::new(endpoint)
+        }
+    }
+
+    pub(crate) fn institution(endpoint: Endpoint, institution_id: &'a str) -> Self {
+        Call {
+            institution_id: Some(institution_id),
+            ..Call::new(endpoint)
+        }
+    }
+}
+
+/// `ClientState` holds everything the async and blocking clients share apart from their transport: credentials, the created token, the observed rate limits and the optional quota tracking, retry behaviour and metrics recorder.
+pub(crate) struct ClientState {
+    pub(crate) base_url: String,
+    pub(crate) secret_id: SecretString,
+    pub(crate) secret_key: SecretString,
+    pub(crate) created_token: Option<CreateTokenResponse>,
+    pub(crate) quota_tracker: Option<QuotaTracker>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) metrics_recorder: Option<Box<dyn MetricsRecorder>>,
+    rate_limits: Mutex<RateLimitState>,
+}
+
+#[derive(Default)]
+struct RateLimitState {
+    general: Option<RateLimit>,
+    accounts: HashMap<String, RateLimit>,
+}
+
+impl ClientState {
+    pub(crate) fn new(
+        base_url: String,
+        secret_id: SecretString,
+        secret_key: SecretString,
+        quota_tracker: Option<QuotaTracker>,
+        retry_policy: RetryPolicy,
+        metrics_recorder: Option<Box<dyn MetricsRecorder>>,
+    ) -> Self {
+        ClientState {
+            base_url,
+            secret_id,
+            secret_key,
+            created_token: None,
+            quota_tracker,
+            retry_policy,
+            metrics_recorder,
+            rate_limits: Mutex::new(RateLimitState::default()),
+        }
+    }
+
+    /// `access_token` returns the access token of the created token.
+    ///
+    /// Panics if no token has been created yet, which cannot happen once a client has been built.
+    pub(crate) fn access_token(&self) -> &SecretString {
+        &self.created_token.as_ref().unwrap().access
+    }
+
+    pub(crate) fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limits.lock().unwrap().general
+    }
+
+    pub(crate) fn account_rate_limit(&self, account_id: &str) -> Option<RateLimit> {
+        self.rate_limits
+            .lock()
+            .unwrap()
+            .accounts
+            .get(account_id)
+            .copied()
+    }
+
+    /// `before_send` reserves account data calls in the `QuotaTracker`, if one is configured, refusing those which would exceed its daily limit.
+    ///
+    /// The reservation is released when dropped, unless the call is recorded by `handle_response`.
+    fn before_send<'a>(&'a self, call: &Call<'a>) -> Result<QuotaReservation<'a>, Error> {
+        let reserved = match self.quota(call) {
+            Some((quota_tracker, account_id)) => {
+                quota_tracker.check(account_id, call.endpoint)?;
+                Some((quota_tracker, account_id, call.endpoint))
+            }
+            None => None,
+        };
+        Ok(QuotaReservation { reserved })
+    }
+
+    /// `handle_response` records the rate limits reported by a response, records the call reserved by `before_send` in the `QuotaTracker` and parses the response.
+    fn handle_response<T: DeserializeOwned>(
+        &self,
+        call: &Call,
+        reservation: &mut QuotaReservation,
+        response: &http::Response<Vec<u8>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let rate_limits = RateLimits::from_headers(response.headers());
+        {
+            let mut state = self.rate_limits.lock().unwrap();
+            if rate_limits.general.is_some() {
+                state.general = rate_limits.general;
+            }
+            if let (Some(account_id), Some(account_success)) =
+                (call.account_id, rate_limits.account_success)
+            {
+                state
+                    .accounts
+                    .insert(account_id.to_string(), account_success);
+            }
+        }
+
+        let result = protocol::parse_response(response);
+
+        if let Some((quota_tracker, account_id)) = self.quota(call) {
+            if response.status().is_success() {
+                reservation.reserved = None;
+                quota_tracker.record_success(
+                    account_id,
+                    call.endpoint,
+                    rate_limits.account_success.as_ref(),
+                );
+            } else if let Err(error) = &result {
+                if let Some(Error::RateLimited { retry_after, .. }) = error.downcast_ref() {
+                    reservation.reserved = None;
+                    quota_tracker.record_rate_limited(
+                        account_id,
+                        call.endpoint,
+                        rate_limits.account_success.as_ref(),
+                        *retry_after,
+                    );
+                }
+            }
+        }
+
+        result
+    }
+
+    /// `request_started` notifies the `MetricsRecorder`, if one is configured, that a request is about to be sent.
+    fn request_started(&self, call: &Call) {
+        if let Some(metrics_recorder) = &self.metrics_recorder {
+            metrics_recorder.request_started(call.endpoint);
+        }
+    }
+
+    /// `request_finished` notifies the `MetricsRecorder`, if one is configured, of the outcome of an attempt, and of the new token if one was created.
+    fn request_finished(
+        &self,
+        call: &Call,
+        attempt: u32,
+        status: Option<http::StatusCode>,
+        error_kind: Option<ErrorKind>,
+        latency: Duration,
+    ) {
+        if let Some(metrics_recorder) = &self.metrics_recorder {
+            metrics_recorder.request_finished(&RequestMetrics {
+                endpoint: call.endpoint,
+                attempt,
+                status_class: status.map(StatusClass::from_status),
+                error_kind,
+                latency,
+            });
+
+            if call.endpoint == Endpoint::CreateToken && error_kind.is_none() {
+                metrics_recorder.token_created();
+            }
+        }
+    }
+
+    /// `request_refused` notifies the `MetricsRecorder`, if one is configured, of a call refused by `before_send`.
+    fn request_refused(&self, call: &Call) {
+        if let Some(metrics_recorder) = &self.metrics_recorder {
+            metrics_recorder.request_refused(call.endpoint);
+        }
+    }
+
+    fn quota<'a>(&'a self, call: &Call<'a>) -> Option<(&'a QuotaTracker, &'a str)> {
+        match (&self.quota_tracker, call.account_id) {
+            (Some(quota_tracker), Some(account_id)) if call.endpoint.is_account_data() => {
+                Some((quota_tracker, account_id))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A call reserved in the `QuotaTracker` by `ClientState::before_send`, released when dropped unless it was recorded, e.g. when the request fails or its future is dropped.
+pub(crate) struct QuotaReservation<'a> {
+    reserved: Option<(&'a QuotaTracker, &'a str, Endpoint)>,
+}
+
+impl Drop for QuotaReservation<'_> {
+    fn drop(&mut self) {
+        if let Some((quota_tracker, account_id, endpoint)) = self.reserved.take() {
+            quota_tracker.release(account_id, endpoint);
+        }
+    }
+}
+
+/// `RequestDriver` runs the attempts of a single call, leaving only the IO to the client: it reserves quota, notifies the `MetricsRecorder` and the span of every attempt, parses responses and decides whether to retry.
+///
+/// For every attempt the client calls `start_attempt`, sends the request, passes the outcome to `finish_attempt`, and either returns the result of the call or sleeps before the next attempt.
+pub(crate) struct RequestDriver<'a> {
+    state: &'a ClientState,
+    call: Call<'a>,
+    span: &'a RequestSpan,
+    attempt: u32,
+}
+
+/// An attempt started by `RequestDriver::start_attempt`, holding the quota it reserved.
+///
+/// An attempt dropped before being passed to `finish_attempt`, because the future of the call was dropped, is reported to the `MetricsRecorder` as cancelled.
+pub(crate) struct Attempt<'a> {
+    state: &'a ClientState,
+    call: Call<'a>,
+    number: u32,
+    reservation: QuotaReservation<'a>,
+    started_at: Instant,
+    finished: bool,
+}
+
+impl Attempt<'_> {
+    fn finish(&mut self, status: Option<http::StatusCode>, error_kind: Option<ErrorKind>) {
+        self.finished = true;
+        self.state.request_finished(
+            &self.call,
+            self.number,
+            status,
+            error_kind,
+            self.started_at.elapsed(),
+        );
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(None, Some(ErrorKind::Cancelled));
+        }
+    }
+}
+
+/// What a client does after an attempt, as decided by `RequestDriver::finish_attempt`.
+pub(crate) enum Step<T> {
+    /// The call is over, with this result
+    Done(Result<T, Box<dyn std::error::Error>>),
+    /// The call is attempted again after this delay
+    Retry(Duration),
+}
+
+impl<'a> RequestDriver<'a> {
+    pub(crate) fn new(state: &'a ClientState, call: Call<'a>, span: &'a RequestSpan) -> Self {
+        RequestDriver {
+            state,
+            call,
+            span,
+            attempt: 1,
+        }
+    }
+
+    /// `start_attempt` reserves quota for the next attempt and notifies the `MetricsRecorder` that it is about to be sent, or returns the error refusing the call.
+    pub(crate) fn start_attempt(&self) -> Result<Attempt<'a>, Box<dyn std::error::Error>> {
+        let reservation = match self.state.before_send(&self.call) {
+            Ok(reservation) => reservation,
+            Err(error) => {
+                self.state.request_refused(&self.call);
+                self.span.record_outcome(self.attempt, Some(&error));
+                return Err(error.into());
+            }
+        };
+
+        self.state.request_started(&self.call);
+        Ok(Attempt {
+            state: self.state,
+            call: self.call,
+            number: self.attempt,
+            reservation,
+            started_at: Instant::now(),
+            finished: false,
+        })
+    }
+
+    /// `finish_attempt` handles the response to an attempt, or the error of the transport if none was received, and decides whether the call is over.
+    pub(crate) fn finish_attempt<T: DeserializeOwned>(
+        &mut self,
+        mut attempt: Attempt<'a>,
+        response: Result<http::Response<Vec<u8>>, TransportError>,
+    ) -> Step<T> {
+        let (status, result) = match response {
+            Ok(response) => {
+                self.span
+                    .record_response(self.attempt, &response, attempt.started_at.elapsed());
+                let result =
+                    self.state
+                        .handle_response(&self.call, &mut attempt.reservation, &response);
+                (Some(response.status()), result)
+            }
+            Err(error) => (None, Err(error as Box<dyn std::error::Error>)),
+        };
+        attempt.finish(
+            status,
+            result
+                .as_ref()
+                .err()
+                .map(|error| ErrorKind::from_error(error.as_ref())),
+        );
+
+        let error = match result {
+            Ok(response) => {
+                self.span.record_outcome(self.attempt, None);
+                return Step::Done(Ok(response));
+            }
+            Err(error) => error,
+        };
+
+        match self
+            .state
+            .retry_policy
+            .retry_delay(self.call.endpoint, self.attempt, error.as_ref())
+        {
+            Some(delay) => {
+                self.span.record_retry(self.attempt, delay, error.as_ref());
+                self.attempt += 1;
+                Step::Retry(delay)
+            }
+            None => {
+                self.span.record_outcome(self.attempt, Some(error.as_ref()));
+                Step::Done(Err(error))
+            }
+        }
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::date::Date;
+use crate::endpoint::Endpoint;
+use crate::identity::Deduplicator;
+use crate::model::Transaction;
+
+/// `TransactionSync` downloads the transactions of accounts incrementally, reporting only what changed since the previous sync.
+///
+/// For every account, it remembers the date of the most recent booked transaction and the transactions seen since then. The next sync only requests transactions from `overlap_days` before that date, as banks may still add, amend or remove recent transactions, and compares them with the remembered ones to emit `TransactionChange`s. Pending transactions which reappear as booked are reported as `TransactionChange::Booked`, even when the bank gives them a new ID or booking date, and transactions whose ID changed as `TransactionChange::Changed`.
+///
+/// Syncing an account costs one call to the transactions endpoint, which GoCardless limits per account per day. If the `Client` has a `QuotaTracker`, accounts whose remaining quota is at or below `reserve_quota` are not synced, and fail with `Error::QuotaExceeded` without changing their state.
+///
+/// The state can be persisted with `save`/`load` or `to_json`/`from_json`, so that syncs resume where the previous run stopped.
+///
+/// # Examples
+///
+/// ```
+/// let sync = TransactionSync::load("sync.json")?.overlap_days(10);
+///
+/// let report = sync.sync(&client, &account_id).await?;
+/// for change in report.changes {
+///     match change {
+///         TransactionChange::Added { transaction, .. } => insert(&transaction)?,
+///         TransactionChange::Changed { current, .. } => update(&current)?,
+///         TransactionChange::Booked { pending, booked } => replace(&pending, &booked)?,
+///         TransactionChange::Removed { transaction, .. } => delete(&transaction)?,
+///     }
+/// }
+/// sync.save("sync.json")?;
+/// ```
+#[derive(Debug)]
+pub struct TransactionSync {
+    overlap_days: u32,
+    initial_history_days: Option<u32>,
+    reserve_quota: u32,
+    accounts: Mutex<BTreeMap<String, AccountSync>>,
+    syncing: Mutex<HashSet<String>>,
+}
+
+/// The state of an account, as persisted by `to_json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AccountSync {
+    /// The booking date of the most recent booked transaction
+    last_booking_date: Option<String>,
+    /// The transactions which may still change, i.e. pending ones and booked ones within the overlap
+    #[serde(default)]
+    transactions: Vec<SyncedTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncedTransaction {
+    pending: bool,
+    transaction: Transaction,
+}
+
+impl Default for TransactionSync {
+    fn default() -> Self {
+        TransactionSync {
+            overlap_days: 7,
+            initial_history_days: None,
+            reserve_quota: 0,
+            accounts: Mutex::new(BTreeMap::new()),
+            syncing: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+impl TransactionSync {
+    /// `new` creates a `TransactionSync` with no synced accounts.
+    pub fn new() -> Self {
+        TransactionSync::default()
+    }
+
+    /// `overlap_days` sets how many days before the most recent booked transaction are requested again, 7 by default.
+    pub fn overlap_days(mut self, days: u32) -> Self {
+        self.overlap_days = days;
+        self
+    }
+
+    /// `initial_history_days` limits the first sync of an account to the given number of days, instead of the whole history the end user agreement gives access to.
+    pub fn initial_history_days(mut self, days: Option<u32>) -> Self {
+        self.initial_history_days = days;
+        self
+    }
+
+    /// `reserve_quota` sets the number of daily calls to the transactions endpoint left for other uses, 0 by default. Accounts are only synced while more calls than that remain.
+    pub fn reserve_quota(mut self, calls: u32) -> Self {
+        self.reserve_quota = calls;
+        self
+    }
+
+    /// `last_booking_date` returns the booking date of the most recent booked transaction synced for an account.
+    pub fn last_booking_date(&self, account_id: &str) -> Option<String> {
+        self.accounts
+            .lock()
+            .unwrap()
+            .get(account_id)
+            .and_then(|account| account.last_booking_date.clone())
+    }
+
+    /// `reset` forgets the state of an account, so that its next sync starts over.
+    pub fn reset(&self, account_id: &str) {
+        self.accounts.lock().unwrap().remove(account_id);
+    }
+
+    /// `sync` requests the transactions of an account since its previous sync and compares them with the ones previously seen.
+    ///
+    /// # Returns
+    ///
+    /// This method returns a `SyncReport` with the changes since the previous sync, every transaction being `Added` on the first one. The state of the account is only updated if the transactions could be retrieved.
+    ///
+    /// Only one sync of an account may run at a time, as concurrent syncs would report the same changes: while one is running, others fail with `SyncError::InProgress`.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn sync(
+        &self,
+        client: &Client,
+        account_id: &str,
+    ) -> Result<SyncReport, Box<dyn std::error::Error>> {
+        let _guard = SyncGuard::acquire(&self.syncing, account_id)?;
+
+        if let Some(tracker) = client.quota_tracker() {
+            if tracker.remaining(account_id, Endpoint::ListTransactions) <= self.reserve_quota {
+                // An exhausted quota is reported along with when a call is possible again
+                return Err(tracker
+                    .exceeded(account_id, Endpoint::ListTransactions)
+                    .into());
+            }
+        }
+
+        let previous = self
+            .accounts
+            .lock()
+            .unwrap()
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default();
+        let date_from = match &previous.last_booking_date {
+            Some(date) => Date::parse(date).map(|date| date.add_days(-(self.overlap_days as i64))),
+            None => self
+                .initial_history_days
+                .map(|days| Date::today().add_days(-(days as i64))),
+        }
+        .map(|date| date.to_string());
+
+        let response = client
+            .list_transactions_between(account_id, date_from.as_deref(), None)
+            .await?;
+        let current: Vec<SyncedTransaction> = response
+            .transactions
+            .booked
+            .into_iter()
+            .map(|transaction| SyncedTransaction {
+                pending: false,
+                transaction,
+            })
+            .chain(
+                response
+                    .transactions
+                    .pending
+                    .into_iter()
+                    .map(|transaction| SyncedTransaction {
+                        pending: true,
+                        transaction,
+                    }),
+            )
+            .collect();
+
+        // Previously seen transactions outside of the requested window are not expected again
+        let in_window = |synced: &SyncedTransaction| {
+            synced.pending
+                || match (&date_from, booking_date(&synced.transaction)) {
+                    (Some(date_from), Some(date)) => date >= date_from.as_str(),
+                    _ => true,
+                }
+        };
+        let mut previous_by_key: HashMap<String, SyncedTransaction> = keyed(
+            previous
+                .transactions
+                .into_iter()
+                .filter(in_window)
+                .collect(),
+        )
+        .collect();
+
+        let mut changes = Vec::new();
+        for (key, synced) in keyed(current.iter().collect()) {
+            let change = match previous_by_key.remove(&key) {
+                None => TransactionChange::Added {
+                    transaction: synced.transaction.clone(),
+                    pending: synced.pending,
+                },
+                Some(previous) if previous.pending && !synced.pending => {
+                    TransactionChange::Booked {
+                        pending: previous.transaction,
+                        booked: synced.transaction.clone(),
+                    }
+                }
+                Some(previous)
+                    if previous.pending != synced.pending
+                        || previous.transaction != synced.transaction =>
+                {
+                    TransactionChange::Changed {
+                        previous: previous.transaction,
+                        current: synced.transaction.clone(),
+                        pending: synced.pending,
+                    }
+                }
+                Some(_) => continue,
+            };
+            changes.push(change);
+        }
+
+        let mut removed = match_reidentified(&mut changes, previous_by_key.into_values().collect());
+        removed.sort_by(|a, b| booking_date(&b.transaction).cmp(&booking_date(&a.transaction)));
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|synced| TransactionChange::Removed {
+                    transaction: synced.transaction,
+                    pending: synced.pending,
+                }),
+        );
+
+        let last_booking_date = current
+            .iter()
+            .filter(|synced| !synced.pending)
+            .filter_map(|synced| booking_date(&synced.transaction))
+            .max()
+            .map(str::to_string)
+            .max(previous.last_booking_date);
+
+        // Only the transactions the next sync requests again need to be remembered
+        let next_date_from = last_booking_date
+            .as_deref()
+            .and_then(Date::parse)
+            .map(|date| date.add_days(-(self.overlap_days as i64)).to_string());
+        let transactions = current
+            .into_iter()
+            .filter(|synced| {
+                synced.pending
+                    || match (&next_date_from, booking_date(&synced.transaction)) {
+                        (Some(next_date_from), Some(date)) => date >= next_date_from.as_str(),
+                        _ => true,
+                    }
+            })
+            .collect();
+
+        self.accounts.lock().unwrap().insert(
+            account_id.to_string(),
+            AccountSync {
+                last_booking_date,
+                transactions,
+            },
+        );
+
+        Ok(SyncReport {
+            account_id: account_id.to_string(),
+            date_from,
+            changes,
+        })
+    }
+
+    /// `to_json` serializes the state of every account, to be restored with `from_json`.
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string(&*self.accounts.lock().unwrap())?)
+    }
+
+    /// `from_json` restores the state serialized by `to_json`, with the default settings.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let accounts: BTreeMap<String, AccountSync> = serde_json::from_str(json)?;
+
+        Ok(TransactionSync {
+            accounts: Mutex::new(accounts),
+            ..TransactionSync::default()
+        })
+    }
+
+    /// `save` writes the state of every account to a file, to be restored with `load`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// `load` restores the state from a file written by `save`, or creates an empty `TransactionSync` if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => TransactionSync::from_json(&json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TransactionSync::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// The outcome of syncing an account with `TransactionSync::sync`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncReport {
+    /// The ID of the account
+    pub account_id: String,
+    /// The `YYYY-MM-DD` date transactions were requested from, or `None` if the whole history was requested
+    pub date_from: Option<String>,
+    /// The changes since the previous sync, in the order the API returned the transactions, booked ones before pending ones, followed by the removed transactions, newest first
+    pub changes: Vec<TransactionChange>,
+}
+
+/// A change to the transactions of an account, reported by `TransactionSync`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionChange {
+    /// A transaction which wasn't seen before
+    Added {
+        /// The transaction
+        transaction: Transaction,
+        /// Whether the transaction is pending
+        pending: bool,
+    },
+    /// A transaction whose details changed
+    Changed {
+        /// The transaction as previously seen
+        previous: Transaction,
+        /// The transaction as now returned
+        current: Transaction,
+        /// Whether the transaction is pending
+        pending: bool,
+    },
+    /// A pending transaction which has been booked
+    Booked {
+        /// The transaction as previously seen while pending
+        pending: Transaction,
+        /// The booked transaction
+        booked: Transaction,
+    },
+    /// A transaction which is no longer returned, e.g. a cancelled pending transaction
+    Removed {
+        /// The transaction as previously seen
+        transaction: Transaction,
+        /// Whether the transaction was pending
+        pending: bool,
+    },
+}
+
+/// Pairs transactions with their `transaction_key`, numbering transactions sharing the same key in order, e.g. identical card payments made on the same day.
+fn keyed<T: std::borrow::Borrow<SyncedTransaction>>(
+    transactions: Vec<T>,
+) -> impl Iterator<Item = (String, T)> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    transactions.into_iter().map(move |synced| {
+        let key = transaction_key(&synced.borrow().transaction);
+        let occurrence = occurrences.entry(key.clone()).or_insert(0);
+        *occurrence += 1;
+        (format!("{key}#{occurrence}"), synced)
+    })
+}
+
+/// Errors returned by `TransactionSync::sync`.
+///
+/// They can be recovered from the returned `Box<dyn std::error::Error>` with `downcast_ref::<SyncError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncError {
+    /// Another sync of the account is running
+    InProgress {
+        /// The ID of the account
+        account_id: String,
+    },
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::InProgress { account_id } => {
+                write!(f, "account {account_id} is already being synced")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// `SyncGuard` marks an account as being synced until it is dropped, including when the sync is cancelled.
+struct SyncGuard<'a> {
+    syncing: &'a Mutex<HashSet<String>>,
+    account_id: String,
+}
+
+impl<'a> SyncGuard<'a> {
+    fn acquire(syncing: &'a Mutex<HashSet<String>>, account_id: &str) -> Result<Self, SyncError> {
+        if !syncing.lock().unwrap().insert(account_id.to_string()) {
+            return Err(SyncError::InProgress {
+                account_id: account_id.to_string(),
+            });
+        }
+
+        Ok(SyncGuard {
+            syncing,
+            account_id: account_id.to_string(),
+        })
+    }
+}
+
+impl Drop for SyncGuard<'_> {
+    fn drop(&mut self) {
+        self.syncing.lock().unwrap().remove(&self.account_id);
+    }
+}
+
+/// Pairs transactions which are no longer returned with added ones the bank gave a new ID, replacing the `Added` changes with `Changed` ones, or with `Booked` ones for pending transactions which got a new ID once booked, as matched by `Deduplicator`.
+///
+/// It returns the transactions which were really removed.
+fn match_reidentified(
+    changes: &mut [TransactionChange],
+    removed: Vec<SyncedTransaction>,
+) -> Vec<SyncedTransaction> {
+    let mut added: Vec<(usize, Transaction, bool)> = changes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, change)| match change {
+            TransactionChange::Added {
+                transaction,
+                pending,
+            } => Some((index, transaction.clone(), *pending)),
+            _ => None,
+        })
+        .collect();
+    let mut matched = vec![false; removed.len()];
+
+    for (synced, matched) in removed.iter().zip(matched.iter_mut()) {
+        let fingerprint = synced.transaction.content_fingerprint();
+        let position = added.iter().position(|(_, transaction, pending)| {
+            *pending == synced.pending && transaction.content_fingerprint() == fingerprint
+        });
+        if let Some(position) = position {
+            let (index, current, pending) = added.remove(position);
+            changes[index] = TransactionChange::Changed {
+                previous: synced.transaction.clone(),
+                current,
+                pending,
+            };
+            *matched = true;
+        }
+    }
+
+    let (pending_indices, pending): (Vec<usize>, Vec<Transaction>) = removed
+        .iter()
+        .enumerate()
+        .filter(|(index, synced)| synced.pending && !matched[*index])
+        .map(|(index, synced)| (index, synced.transaction.clone()))
+        .unzip();
+    added.retain(|(_, _, pending)| !pending);
+    let booked: Vec<Transaction> = added
+        .iter()
+        .map(|(_, transaction, _)| transaction.clone())
+        .collect();
+    for m in Deduplicator::new().match_pending(&pending, &booked) {
+        let (index, booked, _) = &added[m.booked];
+        changes[*index] = TransactionChange::Booked {
+            pending: pending[m.pending].clone(),
+            booked: booked.clone(),
+        };
+        matched[pending_indices[m.pending]] = true;
+    }
+
+    removed
+        .into_iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(synced, _)| synced)
+        .collect()
+}
+
+/// Returns the `YYYY-MM-DD` date a transaction was booked on, or its value date if it has none.
+fn booking_date(transaction: &Transaction) -> Option<&str> {
+    transaction
+        .booking_date
+        .as_deref()
+        .or(transaction.booking_date_time.as_deref())
+        .or(transaction.value_date.as_deref())
+        .and_then(|date| date.get(..10))
+}
+
+/// Returns the key identifying a transaction across syncs: its ID if the bank provides one, or its `Transaction::content_fingerprint` otherwise.
+fn transaction_key(transaction: &Transaction) -> String {
+    if let Some(id) = &transaction.transaction_id {
+        return format!("id:{id}");
+    }
+    if let Some(id) = &transaction.internal_transaction_id {
+        return format!("internal:{id}");
+    }
+
+    format!("content:{}", transaction.content_fingerprint())
+}
@@ -0,0 +1,157 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::state::Call;
+
+/// `RequestSpan` traces a single API call and all of its attempts, when the `tracing` feature is enabled.
+///
+/// Only the endpoint template, the account and institution IDs, status codes, latencies, retries and rate limits are recorded.
+/// Tokens, secrets, request and response bodies and full URLs are never recorded, as they may contain personal data.
+pub(crate) struct RequestSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    started_at: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl RequestSpan {
+    pub(crate) fn new(call: &Call) -> Self {
+        use tracing::field::Empty;
+
+        let span = tracing::info_span!(
+            "gocardless.request",
+            method = call.endpoint.method(),
+            endpoint = call.endpoint.path(),
+            endpoint.name = call.endpoint.name(),
+            account_id = call.account_id,
+            institution_id = call.institution_id,
+            status = Empty,
+            latency_ms = Empty,
+            attempts = Empty,
+            error.kind = Empty,
+            rate_limit.remaining = Empty,
+            account_rate_limit.remaining = Empty,
+        );
+
+        RequestSpan {
+            span,
+            started_at: std::time::Instant::now(),
+        }
+    }
+
+    /// `instrument` runs the future of the call inside the span.
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        tracing::Instrument::instrument(future, self.span.clone())
+    }
+
+    /// `enter` enters the span for the duration of a blocking call.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn enter(&self) -> tracing::span::Entered<'_> {
+        self.span.enter()
+    }
+
+    /// `record_response` records the status code and rate limits of the response to one attempt.
+    pub(crate) fn record_response(
+        &self,
+        attempt: u32,
+        response: &http::Response<Vec<u8>>,
+        latency: Duration,
+    ) {
+        let rate_limits = crate::rate_limit::RateLimits::from_headers(response.headers());
+        let status = response.status().as_u16();
+
+        self.span.record("status", status);
+        if let Some(remaining) = rate_limits.general.and_then(|r| r.remaining) {
+            self.span.record("rate_limit.remaining", remaining);
+        }
+        if let Some(remaining) = rate_limits.account_success.and_then(|r| r.remaining) {
+            self.span.record("account_rate_limit.remaining", remaining);
+        }
+
+        tracing::debug!(
+            attempt,
+            status,
+            latency_ms = latency.as_millis() as u64,
+            "response received"
+        );
+    }
+
+    /// `record_retry` records that an attempt failed and is retried after `delay`.
+    pub(crate) fn record_retry(
+        &self,
+        attempt: u32,
+        delay: Duration,
+        error: &(dyn std::error::Error + 'static),
+    ) {
+        tracing::info!(
+            attempt,
+            error.kind = crate::metrics::ErrorKind::from_error(error).as_str(),
+            delay_ms = delay.as_millis() as u64,
+            "retrying request"
+        );
+    }
+
+    /// `record_outcome` records the number of attempts, the total latency and the kind of error, if the call failed.
+    pub(crate) fn record_outcome(
+        &self,
+        attempts: u32,
+        error: Option<&(dyn std::error::Error + 'static)>,
+    ) {
+        let latency_ms = self.started_at.elapsed().as_millis() as u64;
+        self.span.record("attempts", attempts);
+        self.span.record("latency_ms", latency_ms);
+
+        match error {
+            Some(error) => {
+                let kind = crate::metrics::ErrorKind::from_error(error).as_str();
+                self.span.record("error.kind", kind);
+                tracing::warn!(attempts, latency_ms, error.kind = kind, "request failed");
+            }
+            None => tracing::debug!(attempts, latency_ms, "request succeeded"),
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl RequestSpan {
+    pub(crate) fn new(_call: &Call) -> Self {
+        RequestSpan {}
+    }
+
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        future
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn enter(&self) -> Entered {
+        Entered
+    }
+
+    pub(crate) fn record_response(
+        &self,
+        _attempt: u32,
+        _response: &http::Response<Vec<u8>>,
+        _latency: Duration,
+    ) {
+    }
+
+    pub(crate) fn record_retry(
+        &self,
+        _attempt: u32,
+        _delay: Duration,
+        _error: &(dyn std::error::Error + 'static),
+    ) {
+    }
+
+    pub(crate) fn record_outcome(
+        &self,
+        _attempts: u32,
+        _error: Option<&(dyn std::error::Error + 'static)>,
+    ) {
+    }
+}
+
+/// The guard returned by `RequestSpan::enter` when the `tracing` feature is disabled.
+#[cfg(all(feature = "blocking", not(feature = "tracing")))]
+pub(crate) struct Entered;
@@ -0,0 +1,498 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::transport::{HttpTransport, SleepFuture, TransportError, TransportFuture};
+
+/// A `Cassette` is a list of recorded API interactions, saved as JSON by `RecordingTransport` and served back by `ReplayTransport`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// The interactions, in the order they were recorded
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// `load` reads a cassette from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read(path)?;
+        serde_json::from_slice(&json)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// `save` writes the cassette to a JSON file, creating its parent directories if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, json)
+    }
+}
+
+/// A request and the response it received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request sent by the client
+    pub request: RecordedRequest,
+    /// The response received from the API
+    pub response: RecordedResponse,
+}
+
+/// A request recorded in a `Cassette`.
+///
+/// Headers are not recorded, so that the access token never ends up in a cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The method of the request
+    pub method: String,
+    /// The path and query of the request, without the base URL
+    pub uri: String,
+    /// The JSON body of the request, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// A response recorded in a `Cassette`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The status code of the response
+    pub status: u16,
+    /// The headers of the response, apart from cookies and framing headers
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The body of the response, if it is JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+    /// The body of the response, if it is not JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_text: Option<String>,
+}
+
+/// Headers which are not recorded, as they are either sensitive or no longer valid once the body is scrubbed
+const SKIPPED_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "set-cookie",
+    "transfer-encoding",
+];
+
+/// Keys of the secrets and tokens sent to and returned by the token endpoints
+const TOKEN_KEYS: &[&str] = &["access", "refresh", "secret_id", "secret_key"];
+
+/// Keys of account identifiers
+const IBAN_KEYS: &[&str] = &["iban", "bban"];
+
+/// Keys of the names of account owners and counterparties
+const NAME_KEYS: &[&str] = &[
+    "ownerName",
+    "creditorName",
+    "debtorName",
+    "ultimateCreditor",
+    "ultimateDebtor",
+    "displayName",
+    "name",
+];
+
+/// Keys of the addresses of account owners, whose strings are all scrubbed, keeping the shape of structured addresses
+const ADDRESS_KEYS: &[&str] = &["ownerAddressUnstructured", "ownerAddressStructured"];
+
+const SCRUBBED: &str = "[SCRUBBED]";
+
+/// `RecordingTransport` is an `HttpTransport` recording the interactions of `Client` with the API to a cassette file, to be replayed by `ReplayTransport`.
+///
+/// Requests are sent with the wrapped transport, and the cassette is saved after every response. Before being recorded, interactions are scrubbed of:
+/// * request headers, which hold the access token,
+/// * the secrets and tokens sent to and returned by the token endpoints,
+/// * IBANs and BBANs, replaced by placeholders such as `GB00SCRUBBED0001`,
+/// * names of account owners and counterparties, replaced by placeholders such as `Name 1`,
+/// * addresses of account owners,
+/// * any other field added with `RecordingTransport::scrub_key`.
+///
+/// IBANs and names are replaced by the same placeholder wherever they appear in the cassette, including within other text such as remittance information and request paths, so that recorded data stays consistent. The whole cassette is scrubbed again before every save, so that values first seen in a later response are also replaced in earlier interactions.
+///
+/// # Examples
+///
+/// ```
+/// let client = Client::builder(secret_id, secret_key)
+///     .transport(RecordingTransport::new(ReqwestTransport::new(), "tests/cassettes/transactions.json"))
+///     .build()
+///     .await?;
+///
+/// client.list_transactions(&account_id).await?;
+/// ```
+pub struct RecordingTransport<T> {
+    inner: T,
+    path: PathBuf,
+    state: Mutex<Recording>,
+}
+
+struct Recording {
+    /// The interactions recorded so far, with sensitive keys scrubbed but not yet the IBANs and names found elsewhere
+    interactions: Vec<Interaction>,
+    cassette: Cassette,
+    scrubber: Scrubber,
+}
+
+impl Recording {
+    /// Scrubs every interaction with the IBANs and names found so far, rebuilding the cassette
+    fn scrub(&mut self) {
+        let scrubber = &self.scrubber;
+        self.cassette.interactions = self
+            .interactions
+            .iter()
+            .cloned()
+            .map(|mut interaction| {
+                interaction.request.uri = scrubber.scrub_text(&interaction.request.uri);
+                if let Some(body) = &mut interaction.request.body {
+                    scrubber.scrub_strings(body);
+                }
+                if let Some(body) = &mut interaction.response.body {
+                    scrubber.scrub_strings(body);
+                }
+                if let Some(text) = &mut interaction.response.body_text {
+                    *text = scrubber.scrub_text(text);
+                }
+                interaction
+            })
+            .collect();
+    }
+}
+
+impl<T: HttpTransport> RecordingTransport<T> {
+    /// `new` creates a `RecordingTransport` sending requests with `inner` and recording them to the cassette at `path`, replacing any existing cassette.
+    pub fn new(inner: T, path: impl Into<PathBuf>) -> Self {
+        RecordingTransport {
+            inner,
+            path: path.into(),
+            state: Mutex::new(Recording {
+                interactions: Vec::new(),
+                cassette: Cassette::default(),
+                scrubber: Scrubber::default(),
+            }),
+        }
+    }
+
+    /// `scrub_key` scrubs every JSON field named `key` in recorded bodies, in addition to the tokens, IBANs and names scrubbed by default.
+    pub fn scrub_key(self, key: &str) -> Self {
+        self.recording().scrubber.keys.push(key.to_string());
+        self
+    }
+
+    /// `cassette` returns the interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.recording().cassette.clone()
+    }
+
+    fn recording(&self) -> std::sync::MutexGuard<'_, Recording> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl<T: HttpTransport> HttpTransport for RecordingTransport<T> {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        let method = request.method().to_string();
+        let uri = path_and_query(request.uri());
+        let request_body = request.body().clone();
+
+        Box::pin(async move {
+            let response = self.inner.send(request).await?;
+
+            let mut headers = BTreeMap::new();
+            for (name, value) in response.headers() {
+                if SKIPPED_HEADERS.contains(&name.as_str()) {
+                    continue;
+                }
+                if let Ok(value) = value.to_str() {
+                    headers.insert(name.to_string(), value.to_string());
+                }
+            }
+
+            let mut recording = self.recording();
+            let scrubber = &mut recording.scrubber;
+            let request = RecordedRequest {
+                method,
+                uri,
+                body: parse_json(&request_body).map(|body| scrubber.scrub_keys(body)),
+            };
+            let (body, body_text) = match parse_json(response.body()) {
+                Some(body) => (Some(scrubber.scrub_keys(body)), None),
+                None if response.body().is_empty() => (None, None),
+                None => (
+                    None,
+                    Some(String::from_utf8_lossy(response.body()).into_owned()),
+                ),
+            };
+
+            recording.interactions.push(Interaction {
+                request,
+                response: RecordedResponse {
+                    status: response.status().as_u16(),
+                    headers,
+                    body,
+                    body_text,
+                },
+            });
+            recording.scrub();
+            recording.cassette.save(&self.path)?;
+
+            Ok(response)
+        })
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        self.inner.sleep(duration)
+    }
+}
+
+/// Replaces sensitive values in recorded bodies, giving the same placeholder to the same value.
+#[derive(Default)]
+struct Scrubber {
+    keys: Vec<String>,
+    ibans: HashMap<String, String>,
+    names: HashMap<String, String>,
+}
+
+impl Scrubber {
+    fn scrub_keys(&mut self, mut body: Value) -> Value {
+        self.scrub_key_values(&mut body);
+        body
+    }
+
+    /// Replaces the values of sensitive keys, remembering the placeholders of IBANs and names
+    fn scrub_key_values(&mut self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    match value {
+                        Value::String(string) if IBAN_KEYS.contains(&key.as_str()) => {
+                            *string = self.iban(string);
+                        }
+                        Value::String(string) if NAME_KEYS.contains(&key.as_str()) => {
+                            *string = self.name(string);
+                        }
+                        Value::Null => {}
+                        _ if ADDRESS_KEYS.contains(&key.as_str()) => scrub_all(value),
+                        _ if TOKEN_KEYS.contains(&key.as_str())
+                            || self.keys.iter().any(|scrubbed| scrubbed == key) =>
+                        {
+                            *value = Value::String(SCRUBBED.to_string());
+                        }
+                        _ => self.scrub_key_values(value),
+                    }
+                }
+            }
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.scrub_key_values(value)),
+            _ => {}
+        }
+    }
+
+    /// Replaces IBANs and names found by `scrub_key_values` wherever else they appear
+    fn scrub_strings(&self, value: &mut Value) {
+        match value {
+            Value::String(string) => *string = self.scrub_text(string),
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.scrub_strings(value)),
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.scrub_strings(value)),
+            _ => {}
+        }
+    }
+
+    fn scrub_text(&self, text: &str) -> String {
+        // Longer values first, so that a name such as `Anna` doesn't partially replace `Anna Smith`
+        let mut replacements: Vec<(&String, &String)> =
+            self.ibans.iter().chain(&self.names).collect();
+        replacements.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+        let mut text = text.to_string();
+        for (original, placeholder) in replacements {
+            // Very short values would also replace unrelated text
+            if original.chars().count() > 2 && text.contains(original.as_str()) {
+                text = text.replace(original.as_str(), placeholder);
+            }
+        }
+        text
+    }
+
+    fn iban(&mut self, iban: &str) -> String {
+        if let Some(placeholder) = self.ibans.get(iban) {
+            return placeholder.clone();
+        }
+
+        let country: String = iban
+            .chars()
+            .take(2)
+            .filter(|c| c.is_ascii_alphabetic())
+            .collect();
+        let placeholder = format!("{country}00SCRUBBED{:04}", self.ibans.len() + 1);
+        self.ibans.insert(iban.to_string(), placeholder.clone());
+        placeholder
+    }
+
+    fn name(&mut self, name: &str) -> String {
+        if let Some(placeholder) = self.names.get(name) {
+            return placeholder.clone();
+        }
+
+        let placeholder = format!("Name {}", self.names.len() + 1);
+        self.names.insert(name.to_string(), placeholder.clone());
+        placeholder
+    }
+}
+
+/// Replaces every string within a value, such as the fields of a structured address
+fn scrub_all(value: &mut Value) {
+    match value {
+        Value::String(string) => *string = SCRUBBED.to_string(),
+        Value::Object(object) => object.values_mut().for_each(scrub_all),
+        Value::Array(values) => values.iter_mut().for_each(scrub_all),
+        _ => {}
+    }
+}
+
+/// `ReplayTransport` is an `HttpTransport` serving the interactions of a cassette recorded by `RecordingTransport`, without network access.
+///
+/// A request is answered with the first interaction not yet served that has the same method, path and query; bodies are not compared. Requests matching no interaction fail with `UnmatchedRequest`.
+///
+/// # Examples
+///
+/// ```
+/// let client = Client::builder("secret_id".to_string(), "secret_key".to_string())
+///     .transport(ReplayTransport::load("tests/cassettes/transactions.json")?)
+///     .build()
+///     .await?;
+///
+/// let transactions = client.list_transactions(&account_id).await?;
+/// ```
+#[derive(Debug)]
+pub struct ReplayTransport {
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl ReplayTransport {
+    /// `new` creates a `ReplayTransport` serving the interactions of `cassette`.
+    pub fn new(cassette: Cassette) -> Self {
+        ReplayTransport {
+            interactions: Mutex::new(cassette.interactions.into_iter().map(Some).collect()),
+        }
+    }
+
+    /// `load` creates a `ReplayTransport` serving the interactions of the cassette file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Cassette::load(path).map(ReplayTransport::new)
+    }
+
+    /// `remaining` returns the number of interactions not served yet, e.g. to assert that a test made every recorded call.
+    pub fn remaining(&self) -> usize {
+        self.interactions().iter().flatten().count()
+    }
+
+    fn interactions(&self) -> std::sync::MutexGuard<'_, Vec<Option<Interaction>>> {
+        self.interactions.lock().unwrap()
+    }
+
+    fn replay(&self, method: &str, uri: &str) -> Result<http::Response<Vec<u8>>, TransportError> {
+        let mut interactions = self.interactions();
+        let interaction = interactions
+            .iter_mut()
+            .find(|interaction| match interaction {
+                Some(interaction) => {
+                    interaction.request.method == method && interaction.request.uri == uri
+                }
+                None => false,
+            })
+            .and_then(Option::take);
+
+        let interaction = match interaction {
+            Some(interaction) => interaction,
+            None => {
+                return Err(Box::new(UnmatchedRequest {
+                    method: method.to_string(),
+                    uri: uri.to_string(),
+                    remaining: interactions
+                        .iter()
+                        .flatten()
+                        .map(|interaction| {
+                            format!("{} {}", interaction.request.method, interaction.request.uri)
+                        })
+                        .collect(),
+                }))
+            }
+        };
+
+        let response = interaction.response;
+        let body = match (response.body, response.body_text) {
+            (Some(body), _) => serde_json::to_vec(&body)?,
+            (None, Some(text)) => text.into_bytes(),
+            (None, None) => Vec::new(),
+        };
+
+        let mut builder = http::Response::builder().status(response.status);
+        for (name, value) in &response.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        Ok(builder.body(body)?)
+    }
+}
+
+impl HttpTransport for ReplayTransport {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        let result = self.replay(request.method().as_str(), &path_and_query(request.uri()));
+        Box::pin(async move { result })
+    }
+}
+
+/// The error returned by `ReplayTransport` for a request that matches none of the remaining interactions of its cassette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmatchedRequest {
+    /// The method of the request
+    pub method: String,
+    /// The path and query of the request
+    pub uri: String,
+    /// The method and URI of the interactions not served yet
+    pub remaining: Vec<String>,
+}
+
+impl fmt::Display for UnmatchedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no recorded interaction matches {} {}",
+            self.method, self.uri
+        )?;
+        if self.remaining.is_empty() {
+            write!(f, ", every interaction of the cassette has been served")
+        } else {
+            write!(f, ", remaining interactions: {}", self.remaining.join(", "))
+        }
+    }
+}
+
+impl std::error::Error for UnmatchedRequest {}
+
+fn path_and_query(uri: &http::Uri) -> String {
+    uri.path_and_query()
+        .map(|path_and_query| path_and_query.to_string())
+        .unwrap_or_else(|| uri.path().to_string())
+}
+
+fn parse_json(body: &[u8]) -> Option<Value> {
+    if body.is_empty() {
+        return None;
+    }
+    serde_json::from_slice(body).ok()
+}
@@ -0,0 +1,40 @@
+//! Utilities for testing code built on this crate without access to the real API.
+//!
+//! Enable the `testing` feature to use them:
+//! ```toml
+//! [dev-dependencies]
+//! gocardless-unofficial = { version = "0.1", features = ["testing"] }
+//! ```
+//!
+//! `MockServer` is a local HTTP server implementing the API, for exercising `Client` end to end:
+//! ```rs
+//! use gocardless_unofficial::testing::MockServer;
+//! use gocardless_unofficial::Client;
+//!
+//! #[tokio::test]
+//! async fn lists_institutions() -> Result<(), Box<dyn std::error::Error>> {
+//!     let server = MockServer::start()?;
+//!     let client = Client::builder("secret_id".to_string(), "secret_key".to_string())
+//!         .base_url(server.url())
+//!         .build()
+//!         .await?;
+//!
+//!     let institutions = client.get_institutions("XX").await?;
+//!     assert_eq!(institutions[0].id, "SANDBOXFINANCE_SFIN0000");
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! `SandboxGenerator` generates the accounts served by `MockServer`, in the image of the `SANDBOXFINANCE_SFIN0000` sandbox institution, and can be used on its own to get realistic account data.
+//!
+//! `RecordingTransport` records the interactions of `Client` with the real API to a cassette file, scrubbed of tokens, IBANs and names, and `ReplayTransport` serves them back offline in regression tests.
+
+mod cassette;
+pub use cassette::*;
+
+mod sandbox;
+pub use sandbox::*;
+
+mod server;
+pub use server::*;
@@ -0,0 +1,521 @@
+use serde_json::{json, Value};
+
+use crate::date::{days_in_month, Date};
+use crate::testing::MockAccount;
+
+/// `SandboxGenerator` generates fake accounts in the image of the `SANDBOXFINANCE_SFIN0000` sandbox institution, for testing without access to the API.
+///
+/// Every account has an owner, a valid `GL` IBAN, `expected` and `interimAvailable` balances, and a transaction history made of:
+/// * a monthly salary, paid on the 25th or the last weekday before it,
+/// * a monthly rent on the 1st, and monthly subscription and energy bill direct debits,
+/// * a few card purchases per day, some of them abroad in `USD`, `GBP` or `CHF` with their `currencyExchange`,
+/// * pending card purchases over the last two days.
+///
+/// The data is derived from the seed, the index of the account and the end date only, so the same seed and index always generate the same account unless the end date is set to today with `end_date_today`. The JSON is in the shape returned by the API, and parses into `AccountDetailsResponse`, `ListBalancesResponse` and `ListTransactionsResponse`.
+///
+/// # Examples
+///
+/// ```
+/// let sandbox = SandboxGenerator::new(42);
+///
+/// let transactions: ListTransactionsResponse = serde_json::from_value(sandbox.transactions(0))?;
+/// let balances: ListBalancesResponse = serde_json::from_value(sandbox.balances(0))?;
+///
+/// // Or as served by `MockServer`
+/// let account = sandbox.account(0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxGenerator {
+    seed: u64,
+    /// The date of the last transactions, today if `None`
+    end_date: Option<Date>,
+    history_days: u32,
+}
+
+impl Default for SandboxGenerator {
+    fn default() -> Self {
+        SandboxGenerator::new(0)
+    }
+}
+
+impl SandboxGenerator {
+    /// `new` creates a `SandboxGenerator` generating accounts from `seed`, with 90 days of history up to 30 April 2024.
+    pub fn new(seed: u64) -> Self {
+        SandboxGenerator {
+            seed,
+            end_date: Some(Date::from_ymd(2024, 4, 30).expect("30 April 2024 exists")),
+            history_days: 90,
+        }
+    }
+
+    /// `end_date` sets the date of the last generated transactions, 30 April 2024 by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the date does not exist.
+    pub fn end_date(mut self, year: i64, month: u32, day: u32) -> Self {
+        let date = Date::from_ymd(year, month, day)
+            .unwrap_or_else(|| panic!("invalid date {year:04}-{month:02}-{day:02}"));
+        self.end_date = Some(date);
+        self
+    }
+
+    /// `end_date_today` makes the last generated transactions dated today in UTC, e.g. to serve recent data to code filtering transactions by date.
+    ///
+    /// The generated data then changes every day.
+    pub fn end_date_today(mut self) -> Self {
+        self.end_date = None;
+        self
+    }
+
+    /// `history_days` sets the number of days of transaction history, 90 by default like the sandbox institution.
+    pub fn history_days(mut self, days: u32) -> Self {
+        self.history_days = days.max(1);
+        self
+    }
+
+    /// `account_details` returns the details of the account numbered `index`, in the shape of `AccountDetailsResponse`.
+    pub fn account_details(&self, index: usize) -> Value {
+        self.generate(index).details
+    }
+
+    /// `balances` returns the balances of the account numbered `index`, in the shape of `ListBalancesResponse`.
+    pub fn balances(&self, index: usize) -> Value {
+        self.generate(index).balances
+    }
+
+    /// `transactions` returns the transactions of the account numbered `index`, newest first, in the shape of `ListTransactionsResponse`.
+    pub fn transactions(&self, index: usize) -> Value {
+        self.generate(index).transactions
+    }
+
+    /// `account` returns the account numbered `index` as served by `MockServer`.
+    pub fn account(&self, index: usize) -> MockAccount {
+        let generated = self.generate(index);
+        let details: crate::AccountDetailsResponse =
+            serde_json::from_value(generated.details).expect("generated account details parse");
+        let balances: crate::ListBalancesResponse =
+            serde_json::from_value(generated.balances).expect("generated balances parse");
+        let transactions: crate::ListTransactionsResponse =
+            serde_json::from_value(generated.transactions).expect("generated transactions parse");
+
+        MockAccount {
+            details: details.account.unwrap_or_default(),
+            balances: balances.balances,
+            transactions: transactions.transactions,
+        }
+    }
+
+    fn generate(&self, index: usize) -> Generated {
+        let mut rng = Rng::new(self.seed, index as u64);
+        let end = self.end_date.unwrap_or_else(Date::today);
+        let start = end.add_days(-(self.history_days as i64 - 1));
+
+        let owner = rng.pick(OWNERS);
+        let employer = rng.pick(EMPLOYERS);
+        let employer_iban = rng.iban();
+        let landlord = rng.pick(LANDLORDS);
+        let landlord_iban = rng.iban();
+        let salary = rng.range(2_400, 4_200) * 100 + rng.range(0, 99);
+        let rent = rng.range(65, 140) * 1_000;
+        let opening_balance = rng.range(50_000, 300_000);
+
+        let details = json!({
+            "account": {
+                "resourceId": rng.resource_id(),
+                "iban": rng.iban(),
+                "currency": "EUR",
+                "ownerName": owner,
+                "name": ACCOUNT_NAMES[index % ACCOUNT_NAMES.len()],
+                "product": "Checkings",
+                "cashAccountType": "CACC",
+            }
+        });
+
+        let mut booked = Vec::new();
+        let mut pending = Vec::new();
+        let mut date = start;
+        while date <= end {
+            let (year, month, day) = date.ymd();
+            let is_pending = date >= end.add_days(-1);
+
+            if day == 1 {
+                booked.push(rng.transfer(
+                    date,
+                    -rent,
+                    Counterparty::Creditor(landlord, &landlord_iban),
+                    format!("Rent {}", month_name(month, year)),
+                ));
+            }
+            if date == salary_date(year, month) {
+                booked.push(rng.transfer(
+                    date,
+                    salary,
+                    Counterparty::Debtor(employer, &employer_iban),
+                    format!("Salary {}", month_name(month, year)),
+                ));
+            }
+            if day == 10 {
+                booked.push(rng.direct_debit(date, -1_299, "StreamFlix", "Monthly subscription"));
+            }
+            if day == 15 {
+                let bill = -rng.range(6_000, 12_000);
+                booked.push(rng.direct_debit(date, bill, "City Energy", "Electricity bill"));
+            }
+
+            for _ in 0..rng.range(0, 2) {
+                let purchase = if rng.chance(10) {
+                    rng.foreign_card_purchase(date, is_pending)
+                } else {
+                    rng.card_purchase(date, is_pending)
+                };
+                if is_pending {
+                    pending.push(purchase);
+                } else {
+                    booked.push(purchase);
+                }
+            }
+
+            date = date.add_days(1);
+        }
+
+        let booked_balance = opening_balance + booked.iter().map(|entry| entry.cents).sum::<i64>();
+        let expected_balance =
+            booked_balance + pending.iter().map(|entry| entry.cents).sum::<i64>();
+        let balances = json!({
+            "balances": [
+                {
+                    "balanceAmount": { "amount": amount(expected_balance), "currency": "EUR" },
+                    "balanceType": "expected",
+                    "referenceDate": end.to_string(),
+                },
+                {
+                    "balanceAmount": { "amount": amount(expected_balance), "currency": "EUR" },
+                    "balanceType": "interimAvailable",
+                    "referenceDate": end.to_string(),
+                },
+            ]
+        });
+
+        let transactions = json!({
+            "transactions": {
+                "booked": newest_first(booked),
+                "pending": newest_first(pending),
+            }
+        });
+
+        Generated {
+            details,
+            balances,
+            transactions,
+        }
+    }
+}
+
+struct Generated {
+    details: Value,
+    balances: Value,
+    transactions: Value,
+}
+
+/// A generated transaction, with its amount in cents for computing balances.
+struct Entry {
+    date: Date,
+    seconds: u32,
+    cents: i64,
+    json: Value,
+}
+
+enum Counterparty<'a> {
+    Creditor(&'a str, &'a str),
+    Debtor(&'a str, &'a str),
+}
+
+const OWNERS: &[&str] = &[
+    "John Doe",
+    "Jane Doe",
+    "Max Mustermann",
+    "Erika Mustermann",
+    "Mario Rossi",
+    "Jan Kowalski",
+];
+
+const EMPLOYERS: &[&str] = &["Acme Corporation", "Globex Ltd", "Initech GmbH"];
+
+const LANDLORDS: &[&str] = &["Elm Street Apartments", "Property Management Co."];
+
+const ACCOUNT_NAMES: &[&str] = &["Main Account", "Joint Account", "Travel Account"];
+
+/// Merchants of domestic card purchases, with the range of their amounts in cents
+const MERCHANTS: &[(&str, &str, i64, i64)] = &[
+    ("Freshto Ltd", "Fresh Food Delivery", 1_500, 6_500),
+    ("Emperor's Burgers", "Burger meal", 800, 2_500),
+    ("Corner Bakery", "Bakery", 250, 1_200),
+    ("Greenleaf Market", "Groceries", 1_200, 9_500),
+    ("Bean There Coffee", "Coffee", 300, 750),
+    ("Metro Transit", "Transit ticket", 250, 450),
+    ("Page Turner Books", "Books", 900, 3_500),
+    ("Pharmacy Plus", "Pharmacy", 500, 4_000),
+    ("Cinema Paradiso", "Movie tickets", 1_100, 2_800),
+    ("Fuel Stop", "Fuel", 3_000, 8_000),
+];
+
+/// Merchants of card purchases abroad, with their currency and its rate to EUR in millionths
+const FOREIGN_MERCHANTS: &[(&str, &str, i64)] = &[
+    ("Golden Gate Diner", "USD", 921_500),
+    ("Big Apple Deli", "USD", 921_500),
+    ("Thames Cafe", "GBP", 1_168_300),
+    ("Alpine Outfitters", "CHF", 1_041_200),
+];
+
+/// A SplitMix64 generator, small and stable across releases unlike the generators of other crates.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Rng(seed ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03));
+        rng.next();
+        rng
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// Returns a number in `min..=max`
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        min + self.below((max - min + 1) as u64) as i64
+    }
+
+    /// Returns `true` with a probability of `percent` percent
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+impl Rng {
+    fn transfer(
+        &mut self,
+        date: Date,
+        cents: i64,
+        counterparty: Counterparty<'_>,
+        remittance: String,
+    ) -> Entry {
+        let seconds = self.range(6 * 3600, 9 * 3600) as u32;
+        let mut json = self.booked_json(date, seconds, cents, "TRANSFER");
+        match counterparty {
+            Counterparty::Creditor(name, iban) => {
+                json["creditorName"] = json!(name);
+                json["creditorAccount"] = json!({ "iban": iban });
+            }
+            Counterparty::Debtor(name, iban) => {
+                json["debtorName"] = json!(name);
+                json["debtorAccount"] = json!({ "iban": iban });
+            }
+        }
+        json["remittanceInformationUnstructured"] = json!(remittance);
+
+        Entry {
+            date,
+            seconds,
+            cents,
+            json,
+        }
+    }
+
+    fn direct_debit(&mut self, date: Date, cents: i64, creditor: &str, remittance: &str) -> Entry {
+        let seconds = self.range(3 * 3600, 6 * 3600) as u32;
+        let mut json = self.booked_json(date, seconds, cents, "DIRECT_DEBIT");
+        json["creditorName"] = json!(creditor);
+        json["remittanceInformationUnstructured"] = json!(format!("{creditor} {remittance}"));
+
+        Entry {
+            date,
+            seconds,
+            cents,
+            json,
+        }
+    }
+
+    fn card_purchase(&mut self, date: Date, pending: bool) -> Entry {
+        let (merchant, description, min, max) = *self.pick(MERCHANTS);
+        let cents = -self.range(min, max);
+        let seconds = self.range(8 * 3600, 22 * 3600 - 1) as u32;
+
+        let mut json = if pending {
+            self.pending_json(date, cents)
+        } else {
+            self.booked_json(date, seconds, cents, "CARD_PAYMENT")
+        };
+        json["creditorName"] = json!(merchant);
+        json["remittanceInformationUnstructured"] = if pending {
+            json!(format!("Reserved PAYMENT {merchant}"))
+        } else {
+            json!(format!("{merchant} {description}"))
+        };
+
+        Entry {
+            date,
+            seconds,
+            cents,
+            json,
+        }
+    }
+
+    fn foreign_card_purchase(&mut self, date: Date, pending: bool) -> Entry {
+        let (merchant, currency, rate) = *self.pick(FOREIGN_MERCHANTS);
+        // Rates move by up to 1% from their reference
+        let rate = rate + rate * self.range(-100, 100) / 10_000;
+        let foreign_cents = self.range(500, 15_000);
+        let cents = -((foreign_cents * rate + 500_000) / 1_000_000);
+        let seconds = self.range(8 * 3600, 22 * 3600 - 1) as u32;
+
+        let mut json = if pending {
+            self.pending_json(date, cents)
+        } else {
+            self.booked_json(date, seconds, cents, "CARD_PAYMENT")
+        };
+        json["creditorName"] = json!(merchant);
+        json["remittanceInformationUnstructured"] =
+            json!(format!("{merchant} {currency} {}", amount(foreign_cents)));
+        json["currencyExchange"] = json!([{
+            "sourceCurrency": currency,
+            "exchangeRate": format!("{}.{:06}", rate / 1_000_000, rate % 1_000_000),
+            "unitCurrency": currency,
+            "targetCurrency": "EUR",
+            "quotationDate": date.to_string(),
+        }]);
+
+        Entry {
+            date,
+            seconds,
+            cents,
+            json,
+        }
+    }
+
+    fn booked_json(&mut self, date: Date, seconds: u32, cents: i64, code: &str) -> Value {
+        json!({
+            "transactionId": self.transaction_id(date),
+            "bookingDate": date.to_string(),
+            "valueDate": date.to_string(),
+            "bookingDateTime": date_time(date, seconds),
+            "valueDateTime": date_time(date, seconds),
+            "transactionAmount": { "amount": amount(cents), "currency": "EUR" },
+            "bankTransactionCode": "PMNT",
+            "proprietaryBankTransactionCode": code,
+            "internalTransactionId": self.hex(32),
+        })
+    }
+
+    /// Pending transactions of the sandbox institution have no ID nor booking date
+    fn pending_json(&mut self, date: Date, cents: i64) -> Value {
+        json!({
+            "valueDate": date.to_string(),
+            "transactionAmount": { "amount": amount(cents), "currency": "EUR" },
+        })
+    }
+
+    /// Returns a transaction ID in the format of the sandbox institution, e.g. `2024041201927907-1`
+    fn transaction_id(&mut self, date: Date) -> String {
+        let (year, month, day) = date.ymd();
+        format!(
+            "{year:04}{month:02}{day:02}{:08}-1",
+            self.below(100_000_000)
+        )
+    }
+
+    /// Returns a resource ID in the format of the sandbox institution, e.g. `01F3NS4YV94RA29YCH8R0F6BMF`
+    fn resource_id(&mut self) -> String {
+        const ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+        (0..26)
+            .map(|_| ALPHABET[self.below(ALPHABET.len() as u64) as usize] as char)
+            .collect()
+    }
+
+    /// Returns a valid IBAN of the fictional `GL` country of the sandbox institution
+    fn iban(&mut self) -> String {
+        let bban = format!("1023{:010}", self.below(10_000_000_000));
+
+        // Move the country code and check digits to the end, letters being numbered from 10
+        let digits = format!(
+            "{bban}{}{}00",
+            'G' as u32 - 'A' as u32 + 10,
+            'L' as u32 - 'A' as u32 + 10
+        );
+        let remainder = digits.bytes().fold(0, |remainder, digit| {
+            (remainder * 10 + (digit - b'0') as u64) % 97
+        });
+
+        format!("GL{:02}{bban}", 98 - remainder)
+    }
+
+    fn hex(&mut self, length: usize) -> String {
+        (0..length)
+            .map(|_| char::from_digit(self.below(16) as u32, 16).unwrap_or('0'))
+            .collect()
+    }
+}
+
+/// Returns the 25th of the month, or the last weekday before it
+fn salary_date(year: i64, month: u32) -> Date {
+    let date = Date::from_ymd(year, month, 25.min(days_in_month(year, month)))
+        .expect("the 25th or the last day of a month exists");
+    match date.weekday() {
+        5 => date.add_days(-1),
+        6 => date.add_days(-2),
+        _ => date,
+    }
+}
+
+fn month_name(month: u32, year: i64) -> String {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    format!("{} {year}", MONTHS[month as usize - 1])
+}
+
+fn date_time(date: Date, seconds: u32) -> String {
+    format!(
+        "{date}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Formats an amount in cents like the API, e.g. `-12.50`
+fn amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.abs();
+    format!("{sign}{}.{:02}", cents / 100, cents % 100)
+}
+
+fn newest_first(mut entries: Vec<Entry>) -> Vec<Value> {
+    entries.sort_by_key(|entry| std::cmp::Reverse((entry.date, entry.seconds)));
+    entries.into_iter().map(|entry| entry.json).collect()
+}
@@ -0,0 +1,1066 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use serde_json::{json, Map, Value};
+
+use crate::callback::parse_query;
+use crate::client::{Client, ClientBuilder};
+use crate::date::timestamp;
+use crate::endpoint::Endpoint;
+use crate::model::*;
+use crate::protocol::percent_encode;
+use crate::testing::SandboxGenerator;
+
+/// `MockServer` is a local HTTP server implementing the GoCardless Bank Account Data API, for testing `Client` end to end without network access.
+///
+/// The server implements the token, institution, agreement, requisition and account endpoints, keeping their state in memory:
+/// * any credentials are accepted unless others are configured with `MockServerBuilder::credentials`, and every other endpoint requires a token created by the server,
+/// * requisitions are created with status `CR`, and become `LN` when their `link` is visited, when `MockServer::link_requisition` is called, or step by step on every poll with `MockServerBuilder::auto_progress`,
+/// * linking a requisition creates its accounts, whose details, balances and transactions are served by the account endpoints.
+///
+/// Failures are injected per endpoint with `MockServer::fail_next`, and rate limits are enforced with `MockServerBuilder::rate_limit` and `MockServerBuilder::account_rate_limit`.
+///
+/// The server is shut down when dropped.
+///
+/// # Examples
+///
+/// ```
+/// let server = MockServer::start()?;
+/// let client = server.client().await?;
+///
+/// let requisition = client.create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None).await?;
+/// server.link_requisition(&requisition.id);
+///
+/// let requisitions = client.list_requisitions().await?;
+/// assert!(requisitions.results[0].status.is_linked());
+/// ```
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// `start` starts a `MockServer` with the default configuration on a random local port.
+    pub fn start() -> io::Result<Self> {
+        MockServer::builder().start()
+    }
+
+    /// `builder` creates a `MockServerBuilder`, used to configure the server before starting it.
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// `url` returns the base URL of the server, to be passed to `ClientBuilder::base_url`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// `client_builder` returns a `ClientBuilder` pointed at the server, with the credentials configured with `MockServerBuilder::credentials` or placeholder ones.
+    pub fn client_builder(&self) -> ClientBuilder {
+        let (secret_id, secret_key) = self
+            .state()
+            .config
+            .credentials
+            .clone()
+            .unwrap_or_else(|| ("secret_id".to_string(), "secret_key".to_string()));
+        Client::builder(secret_id, secret_key).base_url(self.url())
+    }
+
+    /// `client` builds a `Client` pointed at the server, as configured by `client_builder`.
+    ///
+    /// # Async
+    ///
+    /// This method is async and should be awaited.
+    pub async fn client(&self) -> Result<Client, Box<dyn std::error::Error>> {
+        self.client_builder().build().await
+    }
+
+    /// `address` returns the local address the server listens on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// `link_requisition` links a requisition as if the end user completed the authorization at its `link`, creating its accounts.
+    ///
+    /// Returns `false` if the requisition does not exist or has already reached a terminal status.
+    pub fn link_requisition(&self, requisition_id: &str) -> bool {
+        self.state().link_requisition(requisition_id)
+    }
+
+    /// `set_requisition_status` sets the status of a requisition, e.g. to `RequisitionStatus::Rejected` or `RequisitionStatus::Expired`.
+    ///
+    /// Setting `RequisitionStatus::Linked` links the requisition like `link_requisition`. Returns `false` if the requisition does not exist.
+    pub fn set_requisition_status(&self, requisition_id: &str, status: RequisitionStatus) -> bool {
+        let mut state = self.state();
+        if status.is_linked() {
+            return state.link_requisition(requisition_id);
+        }
+
+        match state.requisition_mut(requisition_id) {
+            Some(requisition) => {
+                requisition.status = status;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `requisition` returns the current state of a requisition.
+    pub fn requisition(&self, requisition_id: &str) -> Option<Requisition> {
+        self.state()
+            .requisitions
+            .iter()
+            .find(|requisition| requisition.id == requisition_id)
+            .cloned()
+    }
+
+    /// `account` returns the data served for an account.
+    pub fn account(&self, account_id: &str) -> Option<MockAccount> {
+        self.state().accounts.get(account_id).cloned()
+    }
+
+    /// `set_account` sets the data served for an account, adding the account if it does not exist.
+    pub fn set_account(&self, account_id: &str, account: MockAccount) {
+        self.state()
+            .accounts
+            .insert(account_id.to_string(), account);
+    }
+
+    /// `fail_next` makes the next call to `endpoint` fail with `failure`.
+    ///
+    /// Failures are queued, so calling `fail_next` several times makes as many calls fail.
+    pub fn fail_next(&self, endpoint: Endpoint, failure: MockFailure) {
+        self.state()
+            .failures
+            .entry(endpoint)
+            .or_default()
+            .push_back(failure);
+    }
+
+    /// `expire_tokens` invalidates every access token created so far, so that further calls fail with `401 Unauthorized`.
+    pub fn expire_tokens(&self) {
+        self.state().access_tokens.clear();
+    }
+
+    /// `reset_rate_limits` starts a new rate limit window, as if the general and per account limits had reset.
+    pub fn reset_rate_limits(&self) {
+        let mut state = self.state();
+        state.general_calls = 0;
+        state.account_calls.clear();
+    }
+
+    /// `requests` returns every request received by the server so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state().requests.clone()
+    }
+
+    /// `request_count` returns the number of requests received by the server for `endpoint`.
+    pub fn request_count(&self, endpoint: Endpoint) -> usize {
+        self.state()
+            .requests
+            .iter()
+            .filter(|request| request.endpoint == Some(endpoint))
+            .count()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the accept loop so that it notices the shutdown
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// `MockServerBuilder` is used to configure a `MockServer` before it is started.
+#[derive(Debug, Clone)]
+pub struct MockServerBuilder {
+    credentials: Option<(String, String)>,
+    institutions: Vec<Institution>,
+    accounts_per_requisition: usize,
+    sandbox: SandboxGenerator,
+    auto_progress: bool,
+    rate_limit: Option<u32>,
+    account_rate_limit: Option<u32>,
+}
+
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        MockServerBuilder {
+            credentials: None,
+            institutions: vec![sandbox_institution()],
+            accounts_per_requisition: 1,
+            sandbox: SandboxGenerator::default().end_date_today(),
+            auto_progress: false,
+            rate_limit: None,
+            account_rate_limit: None,
+        }
+    }
+}
+
+impl MockServerBuilder {
+    /// `credentials` restricts the secret ID and secret key accepted by the token endpoint. By default any credentials are accepted.
+    pub fn credentials(mut self, secret_id: &str, secret_key: &str) -> Self {
+        self.credentials = Some((secret_id.to_string(), secret_key.to_string()));
+        self
+    }
+
+    /// `institution` adds an institution to the ones served by the server, which include `SANDBOXFINANCE_SFIN0000` by default.
+    pub fn institution(mut self, institution: Institution) -> Self {
+        self.institutions.push(institution);
+        self
+    }
+
+    /// `accounts_per_requisition` sets the number of accounts created when a requisition is linked, 1 by default.
+    pub fn accounts_per_requisition(mut self, accounts: usize) -> Self {
+        self.accounts_per_requisition = accounts;
+        self
+    }
+
+    /// `sandbox` sets the generator of the accounts created when a requisition is linked, `SandboxGenerator::new(0).end_date_today()` by default, so that the transactions are recent like those of the API.
+    ///
+    /// The accounts of the server are numbered in the order they are created, starting at 0.
+    pub fn sandbox(mut self, sandbox: SandboxGenerator) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// `auto_progress` makes requisitions advance by one status every time they are polled, going through `CR`, `GC`, `UA`, `GA` and finally `LN`.
+    pub fn auto_progress(mut self, auto_progress: bool) -> Self {
+        self.auto_progress = auto_progress;
+        self
+    }
+
+    /// `rate_limit` limits the number of API calls in the current window, apart from token calls, answering with `429 Too Many Requests` once exhausted.
+    ///
+    /// The `HTTP_X_RATELIMIT_*` headers are sent with every response. The window is reset with `MockServer::reset_rate_limits`.
+    pub fn rate_limit(mut self, limit: u32) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// `account_rate_limit` limits the number of successful calls per account and account data endpoint, like the daily limit of the real API.
+    ///
+    /// The `HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_*` headers are sent with every account data response.
+    pub fn account_rate_limit(mut self, limit: u32) -> Self {
+        self.account_rate_limit = Some(limit);
+        self
+    }
+
+    /// `start` starts the server on a random local port.
+    pub fn start(self) -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(State {
+            base_url: format!("http://{address}"),
+            config: self,
+            next_id: 0,
+            access_tokens: HashSet::new(),
+            refresh_tokens: HashSet::new(),
+            agreements: Vec::new(),
+            requisitions: Vec::new(),
+            accounts: HashMap::new(),
+            failures: HashMap::new(),
+            requests: Vec::new(),
+            general_calls: 0,
+            account_calls: HashMap::new(),
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let state = state.clone();
+                        std::thread::spawn(move || {
+                            let _ = serve(stream, &state);
+                        });
+                    }
+                }
+            })
+        };
+
+        Ok(MockServer {
+            address,
+            state,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// A failure injected with `MockServer::fail_next`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockFailure {
+    /// Respond with the given status code and an error body like the ones of the API
+    Status(u16),
+    /// Respond with `429 Too Many Requests` and a `Retry-After` header of the given number of seconds
+    RateLimited {
+        /// The value of the `Retry-After` header, in seconds
+        retry_after: u64,
+    },
+    /// Close the connection without responding
+    Disconnect,
+}
+
+/// A request received by a `MockServer`.
+///
+/// The headers are not recorded, so that the access token is not kept around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    /// The method of the request
+    pub method: String,
+    /// The path of the request, without the query
+    pub path: String,
+    /// The query of the request, if any
+    pub query: Option<String>,
+    /// The endpoint the request was made to, if it is one called by `Client`
+    pub endpoint: Option<Endpoint>,
+    /// The body of the request
+    pub body: Vec<u8>,
+}
+
+/// The data served by a `MockServer` for an account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockAccount {
+    /// Served by the account details endpoint
+    pub details: Account,
+    /// Served by the balances endpoint
+    pub balances: Vec<Balance>,
+    /// Served by the transactions endpoint, filtered by the `date_from` and `date_to` query parameters
+    pub transactions: Transactions,
+}
+
+struct State {
+    base_url: String,
+    config: MockServerBuilder,
+    next_id: u64,
+    access_tokens: HashSet<String>,
+    refresh_tokens: HashSet<String>,
+    agreements: Vec<EndUserAgreement>,
+    requisitions: Vec<Requisition>,
+    accounts: HashMap<String, MockAccount>,
+    failures: HashMap<Endpoint, VecDeque<MockFailure>>,
+    requests: Vec<MockRequest>,
+    general_calls: u32,
+    account_calls: HashMap<(String, Endpoint), u32>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: &Value) -> Self {
+        Response {
+            status,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, summary: &str, detail: &str) -> Self {
+        Response::json(
+            status,
+            &json!({ "summary": summary, "detail": detail, "status_code": status }),
+        )
+    }
+
+    fn not_found() -> Self {
+        Response::error(404, "Not found.", "Not found.")
+    }
+}
+
+impl State {
+    /// Handles a request, returning `None` if the connection should be closed without a response.
+    fn handle(&mut self, request: Request) -> Option<Response> {
+        let endpoint = endpoint_of(&request.method, &request.path);
+        self.requests.push(MockRequest {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            query: if request.query.is_empty() {
+                None
+            } else {
+                Some(
+                    request
+                        .query
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect::<Vec<_>>()
+                        .join("&"),
+                )
+            },
+            endpoint,
+            body: request.body.clone(),
+        });
+
+        if request.method == "GET" && request.path.starts_with("/link/") {
+            return Some(self.visit_link(&request.path["/link/".len()..]));
+        }
+
+        if let Some(failure) = endpoint.and_then(|endpoint| {
+            self.failures
+                .get_mut(&endpoint)
+                .and_then(|failures| failures.pop_front())
+        }) {
+            return match failure {
+                MockFailure::Status(status) => Some(Response::error(
+                    status,
+                    "Injected failure",
+                    "This failure was injected with MockServer::fail_next",
+                )),
+                MockFailure::RateLimited { retry_after } => {
+                    let mut response =
+                        Response::error(429, "Rate limit exceeded", "Request was throttled.");
+                    response
+                        .headers
+                        .push(("Retry-After", retry_after.to_string()));
+                    Some(response)
+                }
+                MockFailure::Disconnect => None,
+            };
+        }
+
+        let is_token_request = request.path.starts_with("/api/v2/token/");
+        if !is_token_request && !self.is_authorized(&request) {
+            return Some(Response::error(
+                401,
+                "Invalid token",
+                "Token is invalid or expired",
+            ));
+        }
+
+        let mut headers = Vec::new();
+        if let (Some(limit), false) = (self.config.rate_limit, is_token_request) {
+            self.general_calls += 1;
+            let remaining = limit.saturating_sub(self.general_calls.min(limit));
+            headers.push(("HTTP_X_RATELIMIT_LIMIT", limit.to_string()));
+            headers.push(("HTTP_X_RATELIMIT_REMAINING", remaining.to_string()));
+            headers.push(("HTTP_X_RATELIMIT_RESET", "60".to_string()));
+
+            if self.general_calls > limit {
+                let mut response =
+                    Response::error(429, "Rate limit exceeded", "Request was throttled.");
+                response.headers.extend(headers);
+                return Some(response);
+            }
+        }
+
+        let mut response = self.route(endpoint, &request);
+        response.headers.extend(headers);
+        Some(response)
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        match &request.authorization {
+            Some(authorization) => match authorization.strip_prefix("Bearer ") {
+                Some(token) => self.access_tokens.contains(token),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    fn route(&mut self, endpoint: Option<Endpoint>, request: &Request) -> Response {
+        let segments: Vec<&str> = request
+            .path
+            .trim_start_matches("/api/v2/")
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["token", "new"]) => self.create_token(request),
+            ("POST", ["token", "refresh"]) => self.refresh_token(request),
+            ("GET", ["institutions"]) => self.get_institutions(request),
+            ("GET", ["institutions", id]) => {
+                match self.config.institutions.iter().find(|i| i.id == *id) {
+                    Some(institution) => Response::json(200, &to_value(institution)),
+                    None => Response::not_found(),
+                }
+            }
+            ("POST", ["agreements", "enduser"]) => self.create_agreement(request),
+            ("GET", ["agreements", "enduser"]) => {
+                let results: Vec<Value> = self.agreements.iter().map(to_value).collect();
+                Response::json(200, &paginated(results))
+            }
+            ("GET", ["agreements", "enduser", id]) => {
+                match self.agreements.iter().find(|agreement| agreement.id == *id) {
+                    Some(agreement) => Response::json(200, &to_value(agreement)),
+                    None => Response::not_found(),
+                }
+            }
+            ("GET", ["requisitions"]) => {
+                let ids: Vec<String> = self.requisitions.iter().map(|r| r.id.clone()).collect();
+                for id in ids {
+                    self.poll_requisition(&id);
+                }
+                let results: Vec<Value> = self.requisitions.iter().map(to_value).collect();
+                Response::json(200, &paginated(results))
+            }
+            ("POST", ["requisitions"]) => self.create_requisition(request),
+            ("GET", ["requisitions", id]) => {
+                self.poll_requisition(id);
+                match self.requisition_mut(id) {
+                    Some(requisition) => Response::json(200, &to_value(&*requisition)),
+                    None => Response::not_found(),
+                }
+            }
+            ("DELETE", ["requisitions", id]) => {
+                let before = self.requisitions.len();
+                self.requisitions
+                    .retain(|requisition| requisition.id != *id);
+                if self.requisitions.len() < before {
+                    Response::json(
+                        200,
+                        &json!({ "summary": "Requisition deleted", "detail": "" }),
+                    )
+                } else {
+                    Response::not_found()
+                }
+            }
+            ("GET", ["accounts", id, _]) => match endpoint {
+                Some(endpoint) => self.account_data(endpoint, id, request),
+                None => Response::not_found(),
+            },
+            _ => Response::not_found(),
+        }
+    }
+
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:08x}-0000-4000-8000-{:012x}", self.next_id, self.next_id)
+    }
+
+    fn create_token(&mut self, request: &Request) -> Response {
+        let body = json_body(request);
+        let secret_id = body.get("secret_id").and_then(Value::as_str);
+        let secret_key = body.get("secret_key").and_then(Value::as_str);
+
+        let authenticated = match (&self.config.credentials, secret_id, secret_key) {
+            (Some((id, key)), Some(secret_id), Some(secret_key)) => {
+                id == secret_id && key == secret_key
+            }
+            (None, Some(secret_id), Some(secret_key)) => {
+                !secret_id.is_empty() && !secret_key.is_empty()
+            }
+            _ => false,
+        };
+        if !authenticated {
+            return Response::error(
+                401,
+                "Authentication failed",
+                "No active account found with the given credentials",
+            );
+        }
+
+        let access = format!("mock-access-{}", self.next_id());
+        let refresh = format!("mock-refresh-{}", self.next_id());
+        self.access_tokens.insert(access.clone());
+        self.refresh_tokens.insert(refresh.clone());
+
+        Response::json(
+            200,
+            &json!({
+                "access": access,
+                "access_expires": 86400,
+                "refresh": refresh,
+                "refresh_expires": 2592000,
+            }),
+        )
+    }
+
+    fn refresh_token(&mut self, request: &Request) -> Response {
+        let body = json_body(request);
+        let refresh = body.get("refresh").and_then(Value::as_str).unwrap_or("");
+        if !self.refresh_tokens.contains(refresh) {
+            return Response::error(401, "Invalid token", "Token is invalid or expired");
+        }
+
+        let access = format!("mock-access-{}", self.next_id());
+        self.access_tokens.insert(access.clone());
+
+        Response::json(200, &json!({ "access": access, "access_expires": 86400 }))
+    }
+
+    fn get_institutions(&self, request: &Request) -> Response {
+        let country = query(request, "country").map(|country| country.to_uppercase());
+
+        let institutions: Vec<Value> = self
+            .config
+            .institutions
+            .iter()
+            .filter(|institution| match &country {
+                Some(country) => institution.countries.iter().any(|c| c == country),
+                None => true,
+            })
+            .map(to_value)
+            .collect();
+
+        Response::json(200, &Value::Array(institutions))
+    }
+
+    fn create_agreement(&mut self, request: &Request) -> Response {
+        let body = json_body(request);
+        let institution_id = body
+            .get("institution_id")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        if !self.has_institution(&institution_id) {
+            return Response::error(
+                400,
+                "Unknown Institution ID",
+                "Get Institution IDs from /institutions/?country={$COUNTRY_CODE}",
+            );
+        }
+
+        let agreement = EndUserAgreement {
+            id: self.next_id(),
+            created: timestamp(SystemTime::now()),
+            institution_id,
+            max_historical_days: number(&body, "max_historical_days").unwrap_or(90),
+            access_valid_for_days: number(&body, "access_valid_for_days").unwrap_or(90),
+            access_scope: match body.get("access_scope").and_then(Value::as_array) {
+                Some(scopes) => scopes
+                    .iter()
+                    .filter_map(|scope| scope.as_str().map(str::to_string))
+                    .collect(),
+                None => vec![
+                    "balances".to_string(),
+                    "details".to_string(),
+                    "transactions".to_string(),
+                ],
+            },
+            accepted: None,
+        };
+        let response = Response::json(201, &to_value(&agreement));
+        self.agreements.push(agreement);
+
+        response
+    }
+
+    fn create_requisition(&mut self, request: &Request) -> Response {
+        let body = json_body(request);
+        let string = |key: &str| body.get(key).and_then(Value::as_str).map(str::to_string);
+
+        let institution_id = string("institution_id").unwrap_or_default();
+        if !self.has_institution(&institution_id) {
+            return Response::error(
+                400,
+                "Unknown Institution ID",
+                "Get Institution IDs from /institutions/?country={$COUNTRY_CODE}",
+            );
+        }
+
+        let redirect = match string("redirect") {
+            Some(redirect) => redirect,
+            None => return Response::error(400, "redirect", "This field is required."),
+        };
+
+        let id = self.next_id();
+        let reference = string("reference").unwrap_or_else(|| id.clone());
+        if self
+            .requisitions
+            .iter()
+            .any(|requisition| requisition.reference == reference)
+        {
+            return Response::error(400, "reference", "Client reference must be unique");
+        }
+
+        let agreement = match string("agreement") {
+            Some(agreement) => agreement,
+            None => self.next_id(),
+        };
+
+        let requisition = Requisition {
+            link: format!("{}/link/{id}", self.base_url),
+            id,
+            created: timestamp(SystemTime::now()),
+            redirect,
+            status: RequisitionStatus::Created,
+            institution_id,
+            agreement,
+            reference,
+            accounts: Vec::new(),
+            user_language: string("user_language").unwrap_or_else(|| "EN".to_string()),
+            account_selection: false,
+            redirect_immediate: false,
+            extra: Map::new(),
+        };
+        let response = Response::json(201, &to_value(&requisition));
+        self.requisitions.push(requisition);
+
+        response
+    }
+
+    fn has_institution(&self, institution_id: &str) -> bool {
+        self.config
+            .institutions
+            .iter()
+            .any(|institution| institution.id == institution_id)
+    }
+
+    fn requisition_mut(&mut self, requisition_id: &str) -> Option<&mut Requisition> {
+        self.requisitions
+            .iter_mut()
+            .find(|requisition| requisition.id == requisition_id)
+    }
+
+    /// Advances a requisition by one status when `auto_progress` is enabled.
+    fn poll_requisition(&mut self, requisition_id: &str) {
+        if !self.config.auto_progress {
+            return;
+        }
+
+        let next = match self.requisition_mut(requisition_id) {
+            Some(requisition) => match requisition.status {
+                RequisitionStatus::Created => RequisitionStatus::GivingConsent,
+                RequisitionStatus::GivingConsent => RequisitionStatus::UndergoingAuthentication,
+                RequisitionStatus::UndergoingAuthentication => RequisitionStatus::GrantingAccess,
+                RequisitionStatus::SelectingAccounts | RequisitionStatus::GrantingAccess => {
+                    RequisitionStatus::Linked
+                }
+                _ => return,
+            },
+            None => return,
+        };
+
+        if next.is_linked() {
+            self.link_requisition(requisition_id);
+        } else if let Some(requisition) = self.requisition_mut(requisition_id) {
+            requisition.status = next;
+        }
+    }
+
+    fn link_requisition(&mut self, requisition_id: &str) -> bool {
+        let accounts_per_requisition = self.config.accounts_per_requisition;
+        match self.requisition_mut(requisition_id) {
+            Some(requisition) if !requisition.status.is_terminal() => {}
+            _ => return false,
+        }
+
+        let mut account_ids = Vec::new();
+        for _ in 0..accounts_per_requisition {
+            let account_id = self.next_id();
+            let account = self.config.sandbox.account(self.accounts.len());
+            self.accounts.insert(account_id.clone(), account);
+            account_ids.push(account_id);
+        }
+
+        let mut agreement_id = None;
+        if let Some(requisition) = self.requisition_mut(requisition_id) {
+            requisition.status = RequisitionStatus::Linked;
+            requisition.accounts = account_ids;
+            agreement_id = Some(requisition.agreement.clone());
+        }
+
+        // The end user accepts the agreement while linking their accounts
+        if let Some(agreement) = self
+            .agreements
+            .iter_mut()
+            .find(|agreement| Some(&agreement.id) == agreement_id.as_ref())
+        {
+            agreement
+                .accepted
+                .get_or_insert_with(|| timestamp(SystemTime::now()));
+        }
+
+        true
+    }
+
+    /// Links the requisition like the end user would, then redirects to its `redirect` URL with its reference.
+    fn visit_link(&mut self, requisition_id: &str) -> Response {
+        self.link_requisition(requisition_id);
+
+        let requisition = match self.requisition_mut(requisition_id) {
+            Some(requisition) => requisition.clone(),
+            None => return Response::not_found(),
+        };
+
+        let separator = if requisition.redirect.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let mut location = format!(
+            "{}{separator}ref={}",
+            requisition.redirect,
+            percent_encode(&requisition.reference)
+        );
+        if !requisition.status.is_linked() {
+            location.push_str("&error=UserCancelledSession&details=User+cancelled+the+session");
+        }
+
+        Response {
+            status: 302,
+            headers: vec![("Location", location)],
+            body: Vec::new(),
+        }
+    }
+
+    fn account_data(
+        &mut self,
+        endpoint: Endpoint,
+        account_id: &str,
+        request: &Request,
+    ) -> Response {
+        let account = match self.accounts.get(account_id) {
+            Some(account) => account,
+            None => {
+                return Response::error(
+                    404,
+                    "Account ID not found",
+                    &format!("Account ID {account_id} not found"),
+                )
+            }
+        };
+
+        let body = match endpoint {
+            Endpoint::GetAccountDetails => json!({ "account": to_value(&account.details) }),
+            Endpoint::ListBalances => json!({ "balances": to_value(&account.balances) }),
+            _ => {
+                let date_from = query(request, "date_from");
+                let date_to = query(request, "date_to");
+                let in_range = |transaction: &&Transaction| {
+                    let date = transaction_date(transaction);
+                    date_from.as_deref().map_or(true, |from| date >= from)
+                        && date_to.as_deref().map_or(true, |to| date <= to)
+                };
+                let transactions = Transactions {
+                    booked: account
+                        .transactions
+                        .booked
+                        .iter()
+                        .filter(in_range)
+                        .cloned()
+                        .collect(),
+                    pending: account
+                        .transactions
+                        .pending
+                        .iter()
+                        .filter(in_range)
+                        .cloned()
+                        .collect(),
+                };
+                json!({ "transactions": to_value(&transactions) })
+            }
+        };
+
+        let limit = match self.config.account_rate_limit {
+            Some(limit) => limit,
+            None => return Response::json(200, &body),
+        };
+
+        let calls = self
+            .account_calls
+            .entry((account_id.to_string(), endpoint))
+            .or_default();
+        let mut response = if *calls >= limit {
+            Response::error(
+                429,
+                "Rate limit exceeded",
+                &format!(
+                    "The daily request limit set by the Institution has been exceeded. Limit: {limit}"
+                ),
+            )
+        } else {
+            *calls += 1;
+            Response::json(200, &body)
+        };
+
+        response.headers.extend([
+            ("HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_LIMIT", limit.to_string()),
+            (
+                "HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_REMAINING",
+                (limit - *calls).to_string(),
+            ),
+            (
+                "HTTP_X_RATELIMIT_ACCOUNT_SUCCESS_RESET",
+                "86400".to_string(),
+            ),
+        ]);
+        response
+    }
+}
+
+/// The sandbox institution of the API, whose end user authorization always succeeds.
+fn sandbox_institution() -> Institution {
+    Institution {
+        id: "SANDBOXFINANCE_SFIN0000".to_string(),
+        name: "Sandbox Finance".to_string(),
+        bic: "SFIN0000".to_string(),
+        transaction_total_days: 90,
+        max_access_valid_for_days: Some(90),
+        countries: vec!["XX".to_string()],
+        logo: "https://cdn-logos.gocardless.com/ais/SANDBOXFINANCE_SFIN0000.png".to_string(),
+        ..Institution::default()
+    }
+}
+
+fn endpoint_of(method: &str, path: &str) -> Option<Endpoint> {
+    let segments: Vec<&str> = path
+        .trim_start_matches("/api/v2/")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match (method, segments.as_slice()) {
+        ("POST", ["token", "new"]) => Some(Endpoint::CreateToken),
+        ("GET", ["institutions"]) => Some(Endpoint::GetInstitutions),
+        ("POST", ["agreements", "enduser"]) => Some(Endpoint::CreateEndUserAgreement),
+        ("GET", ["agreements", "enduser", _]) => Some(Endpoint::GetEndUserAgreement),
+        ("GET", ["requisitions"]) => Some(Endpoint::ListRequisitions),
+        ("POST", ["requisitions"]) => Some(Endpoint::CreateRequisition),
+        ("GET", ["requisitions", _]) => Some(Endpoint::GetRequisition),
+        ("GET", ["accounts", _, "transactions"]) => Some(Endpoint::ListTransactions),
+        ("GET", ["accounts", _, "balances"]) => Some(Endpoint::ListBalances),
+        ("GET", ["accounts", _, "details"]) => Some(Endpoint::GetAccountDetails),
+        _ => None,
+    }
+}
+
+/// The date a transaction is filtered on by `date_from` and `date_to`.
+fn transaction_date(transaction: &Transaction) -> &str {
+    transaction
+        .booking_date
+        .as_deref()
+        .or(transaction.value_date.as_deref())
+        .unwrap_or_default()
+}
+
+fn to_value<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn paginated(results: Vec<Value>) -> Value {
+    json!({ "count": results.len(), "next": null, "previous": null, "results": results })
+}
+
+fn json_body(request: &Request) -> Value {
+    serde_json::from_slice(&request.body).unwrap_or(Value::Null)
+}
+
+/// Reads a number from a JSON body, accepting numbers sent as strings.
+fn number(body: &Value, key: &str) -> Option<i64> {
+    match body.get(key)? {
+        Value::Number(number) => number.as_i64(),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+fn query(request: &Request, key: &str) -> Option<String> {
+    request
+        .query
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.clone())
+}
+
+/// Reads a single HTTP/1.1 request from the connection and answers it, closing the connection afterwards.
+fn serve(stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+    if method.is_empty() {
+        return Ok(());
+    }
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query),
+        None => (target.clone(), ""),
+    };
+    let query = parse_query(query);
+
+    let response = state.lock().unwrap().handle(Request {
+        method,
+        path,
+        query,
+        authorization,
+        body,
+    });
+
+    let response = match response {
+        Some(response) => response,
+        None => return Ok(()),
+    };
+
+    let mut stream = stream;
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        http::StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or(""),
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
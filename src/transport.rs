@@ -0,0 +1,258 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// The error type returned by an `HttpTransport`.
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The future returned by `HttpTransport::sleep`.
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// The future returned by `HttpTransport::send`.
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<http::Response<Vec<u8>>, TransportError>> + Send + 'a>>;
+
+/// `HttpTransport` sends the HTTP requests made by `Client`.
+///
+/// Every API call made by the client goes through its transport, which makes it the place to add cross cutting behaviour such as proxies, request signing or circuit breakers, or to replace the network with a test double.
+///
+/// The default transport is `ReqwestTransport`, which requires the `reqwest` feature and a tokio runtime. A different transport is configured with `ClientBuilder::transport`, which allows the client to be driven by any HTTP stack or async runtime.
+///
+/// # Examples
+///
+/// ```
+/// struct LoggingTransport<T>(T);
+///
+/// impl<T: HttpTransport> HttpTransport for LoggingTransport<T> {
+///     fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+///         println!("{} {}", request.method(), request.uri());
+///         self.0.send(request)
+///     }
+/// }
+///
+/// let client = Client::builder(secret_id, secret_key)
+///     .transport(LoggingTransport(ReqwestTransport::new()))
+///     .build()
+///     .await?;
+/// ```
+pub trait HttpTransport: Send + Sync {
+    /// `send` sends a request and returns its response, whatever its status code.
+    ///
+    /// Errors are only returned when no response was received at all, e.g. because the connection failed.
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_>;
+
+    /// `sleep` waits for the given duration, used to delay retries.
+    ///
+    /// The default implementation is runtime agnostic: the sleeps of every client are woken by a single timer thread, started on the first sleep. Transports tied to a runtime should use its timer instead.
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        Box::pin(TimerSleep {
+            duration,
+            state: None,
+        })
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Box<T> {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        (**self).send(request)
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        (**self).sleep(duration)
+    }
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        (**self).send(request)
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        (**self).sleep(duration)
+    }
+}
+
+/// A future completing after `duration`, woken by the shared `TIMER` thread.
+struct TimerSleep {
+    duration: Duration,
+    state: Option<Arc<Mutex<SleepState>>>,
+}
+
+struct SleepState {
+    done: bool,
+    waker: Waker,
+}
+
+impl Future for TimerSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match &self.state {
+            Some(state) => {
+                let mut state = state.lock().unwrap();
+                if state.done {
+                    return Poll::Ready(());
+                }
+                state.waker = cx.waker().clone();
+            }
+            None => {
+                let deadline = match Instant::now().checked_add(self.duration) {
+                    Some(deadline) => deadline,
+                    // Sleeps too long to be represented never complete
+                    None => return Poll::Pending,
+                };
+                let state = Arc::new(Mutex::new(SleepState {
+                    done: false,
+                    waker: cx.waker().clone(),
+                }));
+                TIMER.schedule(deadline, state.clone());
+                self.state = Some(state);
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+/// The timer waking every `TimerSleep`, whose thread is started on the first sleep and runs for the rest of the process.
+static TIMER: Timer = Timer {
+    sleeps: Mutex::new(TimerSleeps {
+        started: false,
+        sleeps: Vec::new(),
+    }),
+    changed: Condvar::new(),
+};
+
+struct Timer {
+    sleeps: Mutex<TimerSleeps>,
+    /// Notified whenever a sleep is scheduled, as it may end before the one being waited for
+    changed: Condvar,
+}
+
+struct TimerSleeps {
+    started: bool,
+    sleeps: Vec<(Instant, Arc<Mutex<SleepState>>)>,
+}
+
+impl Timer {
+    fn schedule(&'static self, deadline: Instant, state: Arc<Mutex<SleepState>>) {
+        let mut sleeps = self.sleeps.lock().unwrap();
+        sleeps.sleeps.push((deadline, state));
+        if !sleeps.started {
+            sleeps.started = true;
+            std::thread::Builder::new()
+                .name("gocardless-timer".to_string())
+                .spawn(move || self.run())
+                .expect("failed to spawn the timer thread");
+        }
+        self.changed.notify_one();
+    }
+
+    fn run(&self) {
+        let mut sleeps = self.sleeps.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            sleeps.sleeps.retain(|(deadline, state)| {
+                if *deadline > now {
+                    return true;
+                }
+                let mut state = state.lock().unwrap();
+                state.done = true;
+                state.waker.wake_by_ref();
+                false
+            });
+
+            sleeps = match sleeps.sleeps.iter().map(|(deadline, _)| *deadline).min() {
+                Some(next) => {
+                    self.changed
+                        .wait_timeout(sleeps, next.saturating_duration_since(now))
+                        .unwrap()
+                        .0
+                }
+                None => self.changed.wait(sleeps).unwrap(),
+            };
+        }
+    }
+}
+
+/// `ReqwestTransport` is the default `HttpTransport`, sending requests with a `reqwest::Client` on a tokio runtime.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    /// `new` creates a `ReqwestTransport` with a default `reqwest::Client`.
+    pub fn new() -> Self {
+        ReqwestTransport::default()
+    }
+
+    /// `with_client` creates a `ReqwestTransport` sending requests with the given `reqwest::Client`, e.g. one configured with a proxy or timeouts.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let request = reqwest::Request::try_from(request)?;
+            let response = self.client.execute(request).await?;
+
+            let mut builder = http::Response::builder()
+                .status(response.status())
+                .version(response.version());
+            if let Some(headers) = builder.headers_mut() {
+                *headers = response.headers().clone();
+            }
+
+            let body = response.bytes().await?;
+            Ok(builder.body(body.to_vec())?)
+        })
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// `TowerTransport` adapts a `tower::Service` into an `HttpTransport`, so that tower middleware can be used with `Client`.
+///
+/// The service is cloned for every request, as is customary for tower services.
+#[cfg(feature = "tower")]
+#[derive(Debug, Clone)]
+pub struct TowerTransport<S> {
+    service: S,
+}
+
+#[cfg(feature = "tower")]
+impl<S> TowerTransport<S> {
+    /// `new` creates a `TowerTransport` sending requests through the given service.
+    pub fn new(service: S) -> Self {
+        TowerTransport { service }
+    }
+}
+
+#[cfg(feature = "tower")]
+impl<S> HttpTransport for TowerTransport<S>
+where
+    S: tower::Service<http::Request<Vec<u8>>, Response = http::Response<Vec<u8>>>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+    S::Error: Into<TransportError>,
+{
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        use tower::ServiceExt;
+
+        let service = self.service.clone();
+        Box::pin(async move { service.oneshot(request).await.map_err(Into::into) })
+    }
+}
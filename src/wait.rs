@@ -0,0 +1,228 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::model::{Requisition, RequisitionStatus};
+use crate::retry::saturating_duration;
+
+/// `WaitOptions` controls how `Client::wait_for_requisition` polls a requisition.
+///
+/// Polls are delayed with exponential backoff: the `n`th poll after a status change waits `interval * multiplier^(n - 1)`, capped at `max_interval`. The delay is reset to `interval` whenever the status changes, as the end user is likely to be moving through the flow.
+///
+/// # Examples
+///
+/// ```
+/// let options = WaitOptions::new()
+///     .interval(Duration::from_secs(2))
+///     .timeout(Some(Duration::from_secs(600)))
+///     .on_transition(|transition| println!("{:?} -> {:?}", transition.from, transition.to));
+/// let requisition = client.wait_for_requisition(&requisition_id, &options).await?;
+/// ```
+#[derive(Clone)]
+pub struct WaitOptions {
+    interval: Duration,
+    max_interval: Duration,
+    multiplier: f64,
+    timeout: Option<Duration>,
+    on_transition: Option<TransitionCallback>,
+}
+
+type TransitionCallback = Arc<dyn Fn(&StatusTransition) + Send + Sync>;
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(10),
+            multiplier: 1.5,
+            timeout: Some(Duration::from_secs(15 * 60)),
+            on_transition: None,
+        }
+    }
+}
+
+impl fmt::Debug for WaitOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitOptions")
+            .field("interval", &self.interval)
+            .field("max_interval", &self.max_interval)
+            .field("multiplier", &self.multiplier)
+            .field("timeout", &self.timeout)
+            .field("on_transition", &self.on_transition.is_some())
+            .finish()
+    }
+}
+
+impl WaitOptions {
+    /// `new` creates `WaitOptions` polling every second at first, backing off up to every 10 seconds, for at most 15 minutes.
+    pub fn new() -> Self {
+        WaitOptions::default()
+    }
+
+    /// `interval` sets the delay before the first poll following a status change, 1 second by default.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// `max_interval` sets the upper bound of the delay between two polls, 10 seconds by default.
+    pub fn max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    /// `multiplier` sets the factor applied to the delay after every poll which didn't observe a status change, `1.5` by default. `1.0` polls at a fixed interval.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// `timeout` sets how long to poll before failing with `WaitError::TimedOut`, 15 minutes by default, or indefinitely if `None`.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// `on_transition` sets a callback invoked with every status change observed while polling, including the status returned by the first poll.
+    pub fn on_transition<F>(mut self, on_transition: F) -> Self
+    where
+        F: Fn(&StatusTransition) + Send + Sync + 'static,
+    {
+        self.on_transition = Some(Arc::new(on_transition));
+        self
+    }
+
+    /// `delay` returns the delay before the poll following the given number of polls without a status change, starting at `0`.
+    fn delay(&self, unchanged_polls: u32) -> Duration {
+        let exponent = unchanged_polls.min(i32::MAX as u32) as i32;
+        let delay = self.interval.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        saturating_duration(delay.min(self.max_interval.as_secs_f64()))
+    }
+}
+
+/// A status change observed by `Client::wait_for_requisition`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusTransition {
+    /// The ID of the requisition
+    pub requisition_id: String,
+    /// The previous status, or `None` for the first poll
+    pub from: Option<RequisitionStatus>,
+    /// The new status
+    pub to: RequisitionStatus,
+    /// The time elapsed since waiting started
+    pub elapsed: Duration,
+}
+
+/// Errors returned by `Client::wait_for_requisition`.
+///
+/// They can be recovered from the returned `Box<dyn std::error::Error>` with `downcast_ref::<WaitError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitError {
+    /// The requisition didn't reach a terminal status before the timeout
+    TimedOut {
+        /// The ID of the requisition
+        requisition_id: String,
+        /// The last status of the requisition
+        status: RequisitionStatus,
+    },
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::TimedOut {
+                requisition_id,
+                status,
+            } => write!(
+                f,
+                "timed out waiting for requisition {requisition_id}, last status {status}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
+
+/// `Waiter` tracks the polls of a requisition, shared by the async and blocking clients.
+pub(crate) struct Waiter<'a> {
+    options: &'a WaitOptions,
+    started_at: Instant,
+    status: Option<RequisitionStatus>,
+    unchanged_polls: u32,
+}
+
+impl<'a> Waiter<'a> {
+    pub(crate) fn new(options: &'a WaitOptions) -> Self {
+        Waiter {
+            options,
+            started_at: Instant::now(),
+            status: None,
+            unchanged_polls: 0,
+        }
+    }
+
+    /// `observe` records a polled requisition, reporting any status change.
+    ///
+    /// It returns `None` once the requisition reached a terminal status, or the delay before the next poll.
+    pub(crate) fn observe(
+        &mut self,
+        requisition: &Requisition,
+    ) -> Result<Option<Duration>, WaitError> {
+        let elapsed = self.started_at.elapsed();
+        if self.status.as_ref() == Some(&requisition.status) {
+            self.unchanged_polls = self.unchanged_polls.saturating_add(1);
+        } else {
+            let transition = StatusTransition {
+                requisition_id: requisition.id.clone(),
+                from: self.status.replace(requisition.status.clone()),
+                to: requisition.status.clone(),
+                elapsed,
+            };
+            self.unchanged_polls = 0;
+            if let Some(on_transition) = &self.options.on_transition {
+                on_transition(&transition);
+            }
+        }
+
+        if requisition.status.is_terminal() {
+            return Ok(None);
+        }
+
+        let delay = self.options.delay(self.unchanged_polls);
+        match self.options.timeout {
+            // Poll a last time at the deadline rather than sleeping past it
+            Some(timeout) if elapsed >= timeout => Err(WaitError::TimedOut {
+                requisition_id: requisition.id.clone(),
+                status: requisition.status.clone(),
+            }),
+            Some(timeout) => Ok(Some(delay.min(timeout - elapsed))),
+            None => Ok(Some(delay)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_up_to_the_maximum_interval() {
+        let options = WaitOptions::new();
+        assert_eq!(options.delay(0), Duration::from_secs(1));
+        assert_eq!(options.delay(2), Duration::from_secs_f64(2.25));
+        assert_eq!(options.delay(100), Duration::from_secs(10));
+        assert_eq!(options.delay(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn delay_never_panics() {
+        let options = WaitOptions::new()
+            .max_interval(Duration::MAX)
+            .multiplier(1e300)
+            .timeout(None);
+        assert_eq!(options.delay(3), Duration::MAX);
+
+        let options = WaitOptions::new().multiplier(f64::NAN);
+        assert_eq!(options.delay(3), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use gocardless_unofficial::testing::MockServer;
+use gocardless_unofficial::{
+    Callback, CallbackError, CallbackHandler, CallbackOutcome, Client, LinkError, LinkFlow,
+    LinkState, LinkStore, RequisitionStatus,
+};
+
+async fn start(client: &Client, store: &Arc<LinkStore>) -> LinkState {
+    LinkFlow::new(
+        "XX",
+        "SANDBOXFINANCE_SFIN0000",
+        "https://example.org/callback?user=42",
+    )
+    .store(store.clone())
+    .start(client)
+    .await
+    .unwrap()
+}
+
+/// Visits the link of the requisition like the end user would, returning the URL they are redirected to.
+async fn visit(state: &LinkState) -> String {
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(state.link.as_deref().unwrap())
+        .send()
+        .await
+        .unwrap();
+    response.headers()["location"].to_str().unwrap().to_string()
+}
+
+#[test]
+fn parses_callbacks() {
+    let callback = Callback::parse(
+        "https://example.org/callback?user=42&ref=link%2D1&error=UserCancelledSession&details=User+cancelled+the+session#top",
+    );
+    assert_eq!(
+        callback,
+        Callback {
+            reference: Some("link-1".to_string()),
+            error: Some("UserCancelledSession".to_string()),
+            details: Some("User cancelled the session".to_string()),
+        }
+    );
+
+    assert_eq!(
+        Callback::parse("ref=link-1"),
+        Callback::parse("/callback?ref=link-1")
+    );
+    assert_eq!(
+        Callback::parse("https://example.org/callback"),
+        Callback::default()
+    );
+
+    // Malformed escapes are kept as is, while `+` still stands for a space
+    assert_eq!(
+        Callback::parse("ref=%+41%4%zz").reference.as_deref(),
+        Some("% 41%4%zz")
+    );
+
+    let uri: http::Uri = "/callback?ref=link-2".parse().unwrap();
+    assert_eq!(
+        Callback::from_uri(&uri).reference.as_deref(),
+        Some("link-2")
+    );
+}
+
+#[tokio::test]
+async fn resolves_linked_accounts() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let state = start(&client, &store).await;
+
+    let url = visit(&state).await;
+    let outcome = CallbackHandler::new(store.clone())
+        .handle(&client, &Callback::parse(&url))
+        .await
+        .unwrap();
+
+    let accounts = server
+        .requisition(state.requisition_id.as_deref().unwrap())
+        .unwrap()
+        .accounts;
+    match outcome {
+        CallbackOutcome::Linked {
+            state: linked,
+            accounts: linked_accounts,
+        } => {
+            assert_eq!(linked.reference, state.reference);
+            assert_eq!(linked_accounts, accounts);
+        }
+        outcome => panic!("unexpected outcome {outcome:?}"),
+    }
+
+    let stored = store.get(&state.reference).unwrap();
+    assert_eq!(stored.status, Some(RequisitionStatus::Linked));
+    assert_eq!(stored.accounts, accounts);
+}
+
+#[tokio::test]
+async fn resolves_cancellations_and_institution_errors() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let handler = CallbackHandler::new(store.clone());
+
+    // Rejected requisitions are redirected with an error by the mock server
+    let state = start(&client, &store).await;
+    server.set_requisition_status(
+        state.requisition_id.as_deref().unwrap(),
+        RequisitionStatus::Rejected,
+    );
+    let url = visit(&state).await;
+    let outcome = handler
+        .handle(&client, &Callback::parse(&url))
+        .await
+        .unwrap();
+    assert!(matches!(
+        outcome,
+        CallbackOutcome::Cancelled { details: Some(ref details), .. } if details == "User cancelled the session"
+    ));
+    assert_eq!(
+        store.get(&state.reference).unwrap().status,
+        Some(RequisitionStatus::Rejected)
+    );
+
+    let state = start(&client, &store).await;
+    let callback = Callback {
+        reference: Some(state.reference.clone()),
+        error: Some("InstitutionUnavailable".to_string()),
+        details: None,
+    };
+    let outcome = handler.handle(&client, &callback).await.unwrap();
+    assert_eq!(
+        outcome,
+        CallbackOutcome::InstitutionError {
+            state: store.get(&state.reference).unwrap(),
+            error: "InstitutionUnavailable".to_string(),
+            details: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn reports_pending_requisitions() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let state = start(&client, &store).await;
+
+    let callback = Callback {
+        reference: Some(state.reference.clone()),
+        ..Callback::default()
+    };
+    let outcome = CallbackHandler::new(store.clone())
+        .handle(&client, &callback)
+        .await
+        .unwrap();
+    assert_eq!(outcome, CallbackOutcome::Pending { state });
+}
+
+#[tokio::test]
+async fn rejects_uncorrelated_callbacks() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let handler = CallbackHandler::new(store.clone());
+
+    let error = handler
+        .handle(&client, &Callback::default())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<CallbackError>(),
+        Some(&CallbackError::MissingReference)
+    );
+
+    let error = handler
+        .handle(&client, &Callback::parse("?ref=unknown"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<CallbackError>(),
+        Some(&CallbackError::UnknownReference {
+            reference: "unknown".to_string()
+        })
+    );
+
+    // The requisition was deleted in the meantime
+    let mut state = start(&client, &store).await;
+    let requisition_id = "deleted".to_string();
+    state.requisition_id = Some(requisition_id.clone());
+    store.insert(state.clone());
+    let error = handler
+        .handle(
+            &client,
+            &Callback::parse(&format!("?ref={}", state.reference)),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<LinkError>(),
+        Some(&LinkError::RequisitionNotFound {
+            reference: state.reference,
+            requisition_id,
+        })
+    );
+}
+
+#[cfg(feature = "axum")]
+#[tokio::test]
+async fn extracts_callbacks_in_axum() {
+    use axum::extract::FromRequestParts;
+
+    let (mut parts, _) = http::Request::get("/callback?ref=link-1&error=UserCancelledSession")
+        .body(())
+        .unwrap()
+        .into_parts();
+    let callback = Callback::from_request_parts(&mut parts, &()).await.unwrap();
+    assert_eq!(callback.reference.as_deref(), Some("link-1"));
+    assert_eq!(callback.error.as_deref(), Some("UserCancelledSession"));
+}
+
+#[tokio::test]
+async fn handles_callbacks_in_send_futures() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let handler = CallbackHandler::new(store.clone());
+    let state = start(&client, &store).await;
+    server.link_requisition(state.requisition_id.as_deref().unwrap());
+
+    // Web frameworks such as axum require handlers to return `Send` futures
+    let outcome = tokio::spawn(async move {
+        let callback = Callback::parse(&format!("?ref={}", state.reference));
+        match handler.handle(&client, &callback).await {
+            Ok(CallbackOutcome::Linked { accounts, .. }) => accounts.len(),
+            _ => 0,
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(outcome, 1);
+}
@@ -0,0 +1,269 @@
+use std::path::PathBuf;
+
+use gocardless_unofficial::testing::{
+    Cassette, MockServer, RecordingTransport, ReplayTransport, UnmatchedRequest,
+};
+use gocardless_unofficial::{Client, HttpTransport, ReqwestTransport, TransportFuture};
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("gocardless-cassettes-{}", std::process::id()))
+        .join(format!("{name}.json"))
+}
+
+async fn client(transport: impl HttpTransport + 'static, base_url: &str) -> Client {
+    Client::builder("my-secret-id".to_string(), "my-secret-key".to_string())
+        .base_url(base_url)
+        .transport(transport)
+        .build()
+        .await
+        .unwrap()
+}
+
+/// Records the creation and linking of a requisition and the transactions of its account.
+async fn record(path: &PathBuf) -> (String, Vec<gocardless_unofficial::Transaction>) {
+    let server = MockServer::start().unwrap();
+    let client = client(
+        RecordingTransport::new(ReqwestTransport::new(), path),
+        &server.url(),
+    )
+    .await;
+
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+    server.link_requisition(&requisition.id);
+    let account_id = server.requisition(&requisition.id).unwrap().accounts[0].clone();
+
+    client.get_account_details(&account_id).await.unwrap();
+    let transactions = client.list_transactions(&account_id).await.unwrap();
+
+    (account_id, transactions.transactions.booked)
+}
+
+#[tokio::test]
+async fn records_scrubbed_interactions() {
+    let path = cassette_path("records_scrubbed_interactions");
+    record(&path).await;
+
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(!json.contains("my-secret-id") && !json.contains("my-secret-key"));
+    assert!(!json.contains("John Doe") && !json.contains("Jane Doe"));
+    assert!(!json.contains("Bearer"));
+
+    let cassette = Cassette::load(&path).unwrap();
+    let uris: Vec<_> = cassette
+        .interactions
+        .iter()
+        .map(|interaction| {
+            format!(
+                "{} {}",
+                interaction.request.method,
+                interaction
+                    .request
+                    .uri
+                    .split('/')
+                    .nth(3)
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+    assert_eq!(
+        uris,
+        vec![
+            "POST token",
+            "POST requisitions",
+            "GET accounts",
+            "GET accounts"
+        ]
+    );
+
+    let token = &cassette.interactions[0];
+    assert_eq!(
+        token.request.body.as_ref().unwrap()["secret_id"],
+        "[SCRUBBED]"
+    );
+    assert_eq!(
+        token.response.body.as_ref().unwrap()["access"],
+        "[SCRUBBED]"
+    );
+
+    let details = cassette.interactions[2].response.body.as_ref().unwrap();
+    assert_eq!(details["account"]["iban"], "GL00SCRUBBED0001");
+    assert_eq!(details["account"]["name"], "Name 1");
+    assert_eq!(details["account"]["ownerName"], "Name 2");
+}
+
+#[tokio::test]
+async fn scrubs_overlapping_names_and_addresses() {
+    let path = cassette_path("scrubs_overlapping_names_and_addresses");
+    let server = MockServer::start().unwrap();
+    let client = client(
+        RecordingTransport::new(ReqwestTransport::new(), &path),
+        &server.url(),
+    )
+    .await;
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+    server.link_requisition(&requisition.id);
+    let account_id = server.requisition(&requisition.id).unwrap().accounts[0].clone();
+
+    let mut account = server.account(&account_id).unwrap();
+    account.details.owner_name = Some("Anna Smith".to_string());
+    account.details.owner_address_unstructured = Some("1 Mill Lane, Oxford".to_string());
+    account.details.extra.insert(
+        "ownerAddressStructured".to_string(),
+        serde_json::json!({ "streetName": "Mill Lane", "buildingNumber": "1" }),
+    );
+    let transaction = &mut account.transactions.booked[0];
+    transaction.creditor_name = Some("Anna".to_string());
+    transaction.remittance_information_unstructured = Some("Refund to Anna Smith".to_string());
+    server.set_account(&account_id, account);
+
+    client.get_account_details(&account_id).await.unwrap();
+    client.list_transactions(&account_id).await.unwrap();
+
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(!json.contains("Anna") && !json.contains("Smith"));
+    assert!(!json.contains("Mill Lane") && !json.contains("Oxford"));
+
+    let cassette = Cassette::load(&path).unwrap();
+    let details = &cassette.interactions[2].response.body.as_ref().unwrap()["account"];
+    assert_eq!(details["ownerName"], "Name 2");
+    assert_eq!(details["ownerAddressUnstructured"], "[SCRUBBED]");
+    assert_eq!(
+        details["ownerAddressStructured"],
+        serde_json::json!({ "streetName": "[SCRUBBED]", "buildingNumber": "[SCRUBBED]" })
+    );
+    let transaction =
+        &cassette.interactions[3].response.body.as_ref().unwrap()["transactions"]["booked"][0];
+    assert_eq!(
+        transaction["remittanceInformationUnstructured"],
+        "Refund to Name 2"
+    );
+}
+
+#[tokio::test]
+async fn scrubs_names_found_later_from_earlier_interactions() {
+    let path = cassette_path("scrubs_names_found_later_from_earlier_interactions");
+    let server = MockServer::start().unwrap();
+    let client = client(
+        RecordingTransport::new(ReqwestTransport::new(), &path),
+        &server.url(),
+    )
+    .await;
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+    server.link_requisition(&requisition.id);
+    let account_id = server.requisition(&requisition.id).unwrap().accounts[0].clone();
+
+    let mut account = server.account(&account_id).unwrap();
+    account.details.owner_name = Some("Anna Smith".to_string());
+    account.details.name = Some("Household Pot".to_string());
+    let transaction = &mut account.transactions.booked[0];
+    transaction.remittance_information_unstructured =
+        Some("Anna Smith to Household Pot".to_string());
+    server.set_account(&account_id, account);
+
+    // The names are only learnt from the details, fetched after the transactions
+    client.list_transactions(&account_id).await.unwrap();
+    client.get_account_details(&account_id).await.unwrap();
+
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(!json.contains("Anna Smith") && !json.contains("Household Pot"));
+
+    let cassette = Cassette::load(&path).unwrap();
+    let transaction =
+        &cassette.interactions[2].response.body.as_ref().unwrap()["transactions"]["booked"][0];
+    let details = &cassette.interactions[3].response.body.as_ref().unwrap()["account"];
+    assert_eq!(
+        transaction["remittanceInformationUnstructured"],
+        format!(
+            "{} to {}",
+            details["ownerName"].as_str().unwrap(),
+            details["name"].as_str().unwrap()
+        )
+    );
+}
+
+/// A transport answering every request with the same JSON body.
+struct StaticTransport(&'static str);
+
+impl HttpTransport for StaticTransport {
+    fn send(&self, _: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        let response = http::Response::new(self.0.as_bytes().to_vec());
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[tokio::test]
+async fn scrubs_request_paths() {
+    let path = cassette_path("scrubs_request_paths");
+    let transport = RecordingTransport::new(
+        StaticTransport(r#"{"account": {"iban": "GB33BUKB20201555555555"}}"#),
+        &path,
+    );
+    for uri in [
+        "https://example.org/accounts/",
+        "https://example.org/accounts/?iban=GB33BUKB20201555555555",
+    ] {
+        let request = http::Request::get(uri).body(Vec::new()).unwrap();
+        transport.send(request).await.unwrap();
+    }
+
+    let cassette = Cassette::load(&path).unwrap();
+    assert_eq!(
+        cassette.interactions[1].request.uri,
+        "/accounts/?iban=GB00SCRUBBED0001"
+    );
+    assert_eq!(cassette, transport.cassette());
+}
+
+#[tokio::test]
+async fn replays_recorded_interactions() {
+    let path = cassette_path("replays_recorded_interactions");
+    let (account_id, booked) = record(&path).await;
+
+    // The server is gone, so every response comes from the cassette
+    let replay = std::sync::Arc::new(ReplayTransport::load(&path).unwrap());
+    let client = client(replay.clone(), "http://localhost:1").await;
+
+    client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+    let details = client.get_account_details(&account_id).await.unwrap();
+    assert_eq!(
+        details.account.unwrap().iban.as_deref(),
+        Some("GL00SCRUBBED0001")
+    );
+
+    let transactions = client.list_transactions(&account_id).await.unwrap();
+    assert_eq!(transactions.transactions.booked.len(), booked.len());
+    assert_eq!(replay.remaining(), 0);
+}
+
+#[tokio::test]
+async fn fails_on_unmatched_requests() {
+    let path = cassette_path("fails_on_unmatched_requests");
+    let (account_id, _) = record(&path).await;
+
+    let client = client(ReplayTransport::load(&path).unwrap(), "http://localhost:1").await;
+
+    let error = client.list_balances(&account_id).await.unwrap_err();
+    let unmatched = error.downcast_ref::<UnmatchedRequest>().unwrap();
+    assert_eq!(unmatched.method, "GET");
+    assert_eq!(
+        unmatched.uri,
+        format!("/api/v2/accounts/{account_id}/balances")
+    );
+    assert_eq!(unmatched.remaining.len(), 3);
+    assert!(error
+        .to_string()
+        .starts_with("no recorded interaction matches GET"));
+}
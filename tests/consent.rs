@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use gocardless_unofficial::testing::MockServer;
+use gocardless_unofficial::{Client, ConsentTracker, LinkFlow, LinkState, LinkStore};
+
+/// Runs a flow to completion, linking its requisition like the end user would.
+async fn link(server: &MockServer, client: &Client, flow: LinkFlow) -> LinkState {
+    let flow = flow.poll_interval(Duration::from_millis(1));
+    let state = flow.start(client).await.unwrap();
+    server.link_requisition(state.requisition_id.as_deref().unwrap());
+    flow.finish(client, &state).await.unwrap();
+    state
+}
+
+fn flow(store: &Arc<LinkStore>) -> LinkFlow {
+    LinkFlow::new(
+        "XX",
+        "SANDBOXFINANCE_SFIN0000",
+        "https://example.org/callback",
+    )
+    .store(store.clone())
+}
+
+/// Returns the number of days from 1970-01-01 to the date of a `YYYY-MM-DD` date or timestamp.
+fn days_since_epoch(date: &str) -> i64 {
+    let number = |range: std::ops::Range<usize>| date[range].parse::<i64>().unwrap();
+    let (year, month, day) = (number(0..4), number(5..7), number(8..10));
+    // Counts from March, so that leap days end the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days = year * 365 + year / 4 - year / 100 + year / 400 + day_of_year;
+    // 719_468 days separate 0000-03-01 from 1970-01-01
+    days - 719_468
+}
+
+#[tokio::test]
+async fn tracks_expiry_of_linked_flows() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let tracker = ConsentTracker::new(store.clone());
+
+    let state = link(&server, &client, flow(&store).access_valid_for_days(60)).await;
+    // Pending flows and flows whose acceptance is not recorded yet have no consent
+    flow(&store).start(&client).await.unwrap();
+    assert!(tracker.consents().is_empty());
+
+    assert_eq!(tracker.refresh(&client).await.unwrap(), 1);
+    assert_eq!(tracker.refresh(&client).await.unwrap(), 0);
+
+    let consents = tracker.consents();
+    assert_eq!(consents.len(), 1);
+    let consent = &consents[0];
+    assert_eq!(consent.state.reference, state.reference);
+    // Derived from the acceptance, so that the test passes when run across midnight
+    let accepted = days_since_epoch(&consent.accepted);
+    let today = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+        / 86_400;
+    assert_eq!(days_since_epoch(&consent.expires), accepted + 60);
+    assert_eq!(consent.days_remaining, accepted + 60 - today);
+    assert!(!consent.is_expired());
+    assert_eq!(consent.expires.len(), "YYYY-MM-DD".len());
+
+    let account_id = &consent.state.accounts[0];
+    assert_eq!(
+        tracker.consent_for_account(account_id).as_ref(),
+        Some(consent)
+    );
+    assert_eq!(tracker.consent_for_account("unknown"), None);
+}
+
+#[tokio::test]
+async fn lists_expiring_consents() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let tracker = ConsentTracker::new(store.clone());
+
+    link(&server, &client, flow(&store).access_valid_for_days(90)).await;
+    tracker.refresh(&client).await.unwrap();
+    let current = tracker.consents().remove(0);
+
+    // A consent accepted long ago has expired
+    let mut expired = current.state.clone();
+    expired.reference = "expired".to_string();
+    expired.agreement_accepted = Some("2020-01-01T12:00:00.000000Z".to_string());
+    expired.access_valid_for_days = Some(30);
+    store.insert(expired);
+
+    let expiring = tracker.expiring_within(7);
+    assert_eq!(expiring.len(), 1);
+    assert_eq!(expiring[0].state.reference, "expired");
+    assert_eq!(expiring[0].expires, "2020-01-31");
+    assert!(expiring[0].is_expired());
+
+    let references: Vec<_> = tracker
+        .expiring_within(90)
+        .into_iter()
+        .map(|consent| consent.state.reference)
+        .collect();
+    assert_eq!(
+        references,
+        vec!["expired".to_string(), current.state.reference]
+    );
+}
+
+#[tokio::test]
+async fn renews_consents_with_the_same_access() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let tracker = ConsentTracker::new(store.clone());
+
+    let state = link(
+        &server,
+        &client,
+        flow(&store)
+            .access_valid_for_days(5)
+            .access_scope(&["balances", "transactions"]),
+    )
+    .await;
+    tracker.refresh(&client).await.unwrap();
+
+    let consent = tracker.expiring_within(7).remove(0);
+    assert_eq!(consent.state.reference, state.reference);
+    assert_eq!(
+        consent.state.access_scope,
+        vec!["balances".to_string(), "transactions".to_string()]
+    );
+
+    let renewal = tracker.renew(&client, &consent).await.unwrap();
+    assert_ne!(renewal.reference, state.reference);
+    assert_eq!(renewal.renewal_of, Some(state.reference.clone()));
+    assert_eq!(renewal.institution_id, state.institution_id);
+    assert_eq!(renewal.redirect, state.redirect);
+    assert_eq!(renewal.access_valid_for_days, Some(5));
+    assert_eq!(renewal.access_scope, consent.state.access_scope);
+    assert_eq!(store.get(&renewal.reference), Some(renewal.clone()));
+
+    // The consent is still expiring until the renewal is linked
+    assert_eq!(tracker.expiring_within(7).len(), 1);
+    server.link_requisition(renewal.requisition_id.as_deref().unwrap());
+    LinkFlow::renewal(&consent.state)
+        .store(store.clone())
+        .poll_interval(Duration::from_millis(1))
+        .finish(&client, &renewal)
+        .await
+        .unwrap();
+    tracker.refresh(&client).await.unwrap();
+
+    let expiring = tracker.expiring_within(7);
+    assert_eq!(expiring.len(), 1);
+    assert_eq!(expiring[0].state.reference, renewal.reference);
+}
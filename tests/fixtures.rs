@@ -0,0 +1,218 @@
+//! Anonymized responses of the account endpoints, one directory of `tests/fixtures` per bank,
+//! capturing the quirks of its JSON. `transactions.json` files are parsed into
+//! `ListTransactionsResponse` and `details.json` files into `AccountDetailsResponse`.
+//!
+//! Names, IBANs, account numbers and IDs are made up. When adding a bank, replace any personal
+//! data with made up values of the same shape.
+
+use std::fs;
+use std::path::PathBuf;
+
+use gocardless_unofficial::{
+    AccountDetailsResponse, AccountStatus, AccountUsage, CurrencyExchange,
+    ListTransactionsResponse, TransactionAmount,
+};
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn transactions(bank: &str) -> ListTransactionsResponse {
+    let json = fs::read(fixtures().join(bank).join("transactions.json")).unwrap();
+    serde_json::from_slice(&json).unwrap()
+}
+
+fn details(bank: &str) -> AccountDetailsResponse {
+    let json = fs::read(fixtures().join(bank).join("details.json")).unwrap();
+    serde_json::from_slice(&json).unwrap()
+}
+
+fn amount(amount: &str, currency: &str) -> TransactionAmount {
+    TransactionAmount {
+        amount: amount.to_string(),
+        currency: currency.to_string(),
+    }
+}
+
+#[test]
+fn parses_every_fixture() {
+    for bank in fs::read_dir(fixtures()).unwrap() {
+        let bank = bank.unwrap().path();
+        for file in fs::read_dir(&bank).unwrap() {
+            let path = file.unwrap().path();
+            let json = fs::read(&path).unwrap();
+            let result = match path.file_name().and_then(|name| name.to_str()) {
+                Some("transactions.json") => {
+                    serde_json::from_slice::<ListTransactionsResponse>(&json)
+                        .map(|response| response.transactions.booked.is_empty())
+                }
+                Some("details.json") => serde_json::from_slice::<AccountDetailsResponse>(&json)
+                    .map(|response| response.account.is_none()),
+                _ => panic!("unexpected fixture {}", path.display()),
+            };
+
+            match result {
+                Ok(empty) => assert!(!empty, "{} parsed to nothing", path.display()),
+                Err(error) => panic!("{} failed to parse: {error}", path.display()),
+            }
+        }
+    }
+}
+
+#[test]
+fn sandboxfinance() {
+    let transactions = transactions("sandboxfinance").transactions;
+    let booked = &transactions.booked[1];
+    assert_eq!(booked.transaction_id.as_deref(), Some("2024041201927906-1"));
+    assert_eq!(booked.booking_date_time, None);
+    assert_eq!(booked.debtor_name.as_deref(), Some("MON MOTHMA"));
+    assert_eq!(
+        booked.debtor_account.as_ref().unwrap().iban.as_deref(),
+        Some("GL2010230000010234")
+    );
+    assert!(!booked.extra.contains_key("debtorAccount"));
+
+    // Pending transactions have neither ID nor booking date
+    let pending = &transactions.pending[0];
+    assert_eq!(pending.transaction_id, None);
+    assert_eq!(pending.booking_date, None);
+    assert_eq!(pending.value_date.as_deref(), Some("2024-04-12"));
+    assert_eq!(pending.transaction_amount, amount("10.00", "EUR"));
+
+    let account = details("sandboxfinance").account.unwrap();
+    assert_eq!(account.iban.as_deref(), Some("GL3510230000010234"));
+    assert_eq!(account.product.as_deref(), Some("Checkings"));
+}
+
+#[test]
+fn revolut() {
+    let transactions = transactions("revolut").transactions;
+
+    // The exchange rate is a single object, with the original amount
+    let booked = &transactions.booked[0];
+    assert_eq!(
+        booked.currency_exchange,
+        Some(vec![CurrencyExchange {
+            source_currency: "USD".to_string(),
+            exchange_rate: "0.924016".to_string(),
+            unit_currency: None,
+            target_currency: Some("EUR".to_string()),
+            instructed_amount: Some(amount("25.40", "USD")),
+            ..CurrencyExchange::default()
+        }])
+    );
+    assert_eq!(
+        booked.booking_date_time.as_deref(),
+        Some("2024-04-08T14:22:31.120412Z")
+    );
+    assert_eq!(booked.remittance_information_unstructured, None);
+    assert_eq!(
+        booked.remittance_information_unstructured_array,
+        Some(vec!["Golden Gate Diner".to_string()])
+    );
+
+    // Pending transactions have a booking time but no ID nor booking date
+    let pending = &transactions.pending[0];
+    assert_eq!(pending.transaction_id, None);
+    assert_eq!(pending.booking_date, None);
+    assert_eq!(
+        pending.booking_date_time.as_deref(),
+        Some("2024-04-09T18:45:02.000431Z")
+    );
+
+    let account = details("revolut").account.unwrap();
+    assert_eq!(account.bic.as_deref(), Some("REVOLT21"));
+    assert_eq!(account.name.as_deref(), Some("EUR"));
+    assert_eq!(account.usage, Some(AccountUsage::Private));
+}
+
+#[test]
+fn n26() {
+    let transactions = transactions("n26").transactions;
+
+    // Amounts are not always formatted with two decimals
+    let rent = &transactions.booked[0];
+    assert_eq!(rent.transaction_amount, amount("-950.0", "EUR"));
+    assert_eq!(
+        rent.creditor_account.as_ref().unwrap().iban.as_deref(),
+        Some("DE89370400440532013000")
+    );
+    assert_eq!(rent.extra["bankTransactionCode"], "PMNT-ICDT-STDO");
+    assert_eq!(rent.booking_date_time, None);
+
+    let pending = &transactions.pending[0];
+    assert_eq!(pending.transaction_id, None);
+    assert_eq!(
+        pending.remittance_information_unstructured.as_deref(),
+        Some("-")
+    );
+
+    let account = details("n26").account.unwrap();
+    assert_eq!(account.owner_name.as_deref(), Some("Max Mustermann"));
+    assert_eq!(account.product.as_deref(), Some("Standard"));
+}
+
+#[test]
+fn monzo() {
+    let transactions = transactions("monzo").transactions;
+    assert!(transactions.pending.is_empty());
+
+    // No value date, and the balance after the transaction is reported
+    let purchase = &transactions.booked[0];
+    assert_eq!(purchase.value_date, None);
+    assert_eq!(purchase.transaction_amount, amount("-38.50", "GBP"));
+    assert_eq!(
+        purchase.proprietary_bank_transaction_code.as_deref(),
+        Some("mastercard")
+    );
+    assert_eq!(purchase.extra["merchantCategoryCode"], "5812");
+    assert_eq!(
+        purchase.extra["balanceAfterTransaction"]["balanceAmount"]["amount"],
+        "1211.03"
+    );
+
+    // Domestic payments are identified by sort code and account number
+    let transfer = &transactions.booked[1];
+    let creditor_account = transfer.creditor_account.as_ref().unwrap();
+    assert_eq!(creditor_account.iban, None);
+    assert_eq!(creditor_account.bban.as_deref(), Some("04000412345678"));
+
+    let account = details("monzo").account.unwrap();
+    assert_eq!(account.bban.as_deref(), Some("04000412345678"));
+    assert_eq!(account.display_name.as_deref(), Some("Personal Account"));
+    assert_eq!(account.extra["sortCodeAccountNumber"], "04000412345678");
+}
+
+#[test]
+fn ing() {
+    let transactions = transactions("ing").transactions;
+
+    // The pending list is missing altogether
+    assert!(transactions.pending.is_empty());
+
+    // The exchange rate is an array
+    let purchase = &transactions.booked[0];
+    let currency_exchange = purchase.currency_exchange.as_ref().unwrap();
+    assert_eq!(currency_exchange.len(), 1);
+    assert_eq!(currency_exchange[0].source_currency, "CHF");
+    assert_eq!(currency_exchange[0].unit_currency.as_deref(), Some("CHF"));
+    assert_eq!(
+        currency_exchange[0].quotation_date.as_deref(),
+        Some("2024-01-14")
+    );
+    assert_eq!(purchase.extra["endToEndId"], "NOTPROVIDED");
+
+    let direct_debit = &transactions.booked[1];
+    assert_eq!(
+        direct_debit.remittance_information_structured.as_deref(),
+        Some("1234567890123456")
+    );
+    assert_eq!(direct_debit.extra["mandateId"], "CE-0001234");
+
+    let account = details("ing").account.unwrap();
+    assert_eq!(
+        account.owner_name.as_deref(),
+        Some("E. Mustermann,J. Mustermann")
+    );
+    assert_eq!(account.status, Some(AccountStatus::Enabled));
+}
@@ -0,0 +1,161 @@
+use gocardless_unofficial::{
+    CreditorAccount, Deduplicator, PendingMatch, Transaction, TransactionAmount,
+    TransactionFingerprint, Transactions,
+};
+
+fn transaction(id: Option<&str>, date: &str, amount: &str, creditor: &str) -> Transaction {
+    Transaction {
+        transaction_id: id.map(str::to_string),
+        booking_date: Some(date.to_string()),
+        value_date: Some(date.to_string()),
+        transaction_amount: TransactionAmount {
+            amount: amount.to_string(),
+            currency: "EUR".to_string(),
+        },
+        creditor_name: Some(creditor.to_string()),
+        remittance_information_unstructured: Some(format!("Card payment {creditor}")),
+        ..Transaction::default()
+    }
+}
+
+/// The pending version of a transaction, which has no ID or booking date yet.
+fn pending(transaction: &Transaction) -> Transaction {
+    Transaction {
+        transaction_id: None,
+        booking_date: None,
+        ..transaction.clone()
+    }
+}
+
+const FINGERPRINT: u64 = 10827176050820926506;
+
+#[test]
+fn fingerprints_are_stable() {
+    let transaction = transaction(Some("tx-1"), "2024-03-01", "-12.50", "Corner Bakery");
+    // Fingerprints are persisted, so they must never change between versions
+    assert_eq!(
+        transaction.fingerprint(),
+        TransactionFingerprint(FINGERPRINT)
+    );
+    assert_eq!(
+        transaction.fingerprint().to_string(),
+        format!("{FINGERPRINT:016x}")
+    );
+    assert_eq!(
+        serde_json::to_string(&transaction.fingerprint()).unwrap(),
+        FINGERPRINT.to_string()
+    );
+}
+
+#[test]
+fn fingerprints_ignore_formatting() {
+    let transaction = transaction(Some("tx-1"), "2024-03-01", "-12.50", "Corner Bakery");
+    let mut reformatted = transaction.clone();
+    reformatted.transaction_amount.amount = "-12.5".to_string();
+    reformatted.transaction_amount.currency = "eur".to_string();
+    reformatted.creditor_name = Some(" corner  BAKERY ".to_string());
+    reformatted.booking_date = None;
+    reformatted.booking_date_time = Some("2024-03-01T10:15:00Z".to_string());
+    assert_eq!(reformatted.fingerprint(), transaction.fingerprint());
+
+    // A missing ID differs from an empty one, while blank names count as missing
+    let mut without_id = transaction.clone();
+    without_id.transaction_id = None;
+    let mut empty_id = transaction.clone();
+    empty_id.transaction_id = Some(String::new());
+    assert_ne!(without_id.fingerprint(), empty_id.fingerprint());
+    let mut blank_name = transaction.clone();
+    blank_name.creditor_name = Some(" ".to_string());
+    let mut without_name = transaction.clone();
+    without_name.creditor_name = None;
+    assert_eq!(blank_name.fingerprint(), without_name.fingerprint());
+
+    // Any other difference changes the fingerprint
+    let mut different = transaction.clone();
+    different.creditor_account = Some(CreditorAccount {
+        iban: Some("DE89 3704 0044 0532 0130 00".to_string()),
+        bban: None,
+    });
+    assert_ne!(different.fingerprint(), transaction.fingerprint());
+    // The same account as debtor is a different transaction
+    let mut debtor = transaction.clone();
+    debtor.debtor_account = different.creditor_account.clone();
+    assert_ne!(debtor.fingerprint(), transaction.fingerprint());
+    assert_ne!(debtor.fingerprint(), different.fingerprint());
+    different.transaction_amount.amount = "-12.51".to_string();
+    assert_ne!(different.fingerprint(), transaction.fingerprint());
+
+    // Only the content fingerprint survives a new ID
+    let mut renamed = transaction.clone();
+    renamed.transaction_id = Some("tx-2".to_string());
+    assert_ne!(renamed.fingerprint(), transaction.fingerprint());
+    assert_eq!(
+        renamed.content_fingerprint(),
+        transaction.content_fingerprint()
+    );
+}
+
+#[test]
+fn matches_pending_transactions_with_booked_ones() {
+    let bakery = transaction(Some("tx-1"), "2024-03-02", "-4.20", "Corner Bakery");
+    let mut bakery_pending = pending(&bakery);
+    bakery_pending.value_date = Some("2024-03-01".to_string());
+    // Same amount, different shop, booked first
+    let cafe = transaction(Some("tx-2"), "2024-03-01", "-4.20", "Station Cafe");
+    let cafe_pending = pending(&cafe);
+    // Booked too late to be the same transaction
+    let late = transaction(Some("tx-3"), "2024-03-20", "-9.99", "Bookshop");
+    let mut late_pending = pending(&late);
+    late_pending.value_date = Some("2024-03-01".to_string());
+
+    let matches = Deduplicator::new().match_pending(
+        &[bakery_pending.clone(), cafe_pending, late_pending.clone()],
+        &[cafe, bakery, late.clone()],
+    );
+    assert_eq!(
+        matches,
+        vec![
+            PendingMatch {
+                pending: 0,
+                booked: 1
+            },
+            PendingMatch {
+                pending: 1,
+                booked: 0
+            },
+        ]
+    );
+
+    let matches = Deduplicator::new()
+        .max_days(30)
+        .match_pending(&[late_pending], &[late]);
+    assert_eq!(
+        matches,
+        vec![PendingMatch {
+            pending: 0,
+            booked: 0
+        }]
+    );
+}
+
+#[test]
+fn deduplicates_transactions() {
+    let bakery = transaction(Some("tx-1"), "2024-03-02", "-4.20", "Corner Bakery");
+    let coffee = transaction(None, "2024-03-02", "-3.00", "Station Cafe");
+    let cinema = transaction(None, "2024-03-03", "-11.00", "Cinema");
+    let transactions = Transactions {
+        // The bakery is returned twice, and two identical coffees were bought
+        booked: vec![
+            bakery.clone(),
+            bakery.clone(),
+            coffee.clone(),
+            coffee.clone(),
+        ],
+        pending: vec![pending(&bakery), pending(&cinema)],
+    };
+
+    assert_eq!(
+        Deduplicator::new().deduplicate(&transactions),
+        vec![bakery, coffee.clone(), coffee, pending(&cinema)]
+    );
+}
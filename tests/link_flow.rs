@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use gocardless_unofficial::testing::MockServer;
+use gocardless_unofficial::{LinkError, LinkFlow, LinkStore, RequisitionStatus};
+
+fn flow() -> LinkFlow {
+    LinkFlow::new(
+        "XX",
+        "SANDBOXFINANCE_SFIN0000",
+        "https://example.org/callback",
+    )
+    .poll_interval(Duration::from_millis(1))
+}
+
+#[tokio::test]
+async fn links_accounts() {
+    let server = MockServer::builder()
+        .auto_progress(true)
+        .accounts_per_requisition(2)
+        .start()
+        .unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+    let flow = flow().max_historical_days(365).store(store.clone());
+
+    let state = flow.start(&client).await.unwrap();
+    assert_eq!(state.status, Some(RequisitionStatus::Created));
+    assert!(state.link.is_some());
+    // The sandbox institution provides 90 days of history
+    assert_eq!(state.max_historical_days, 90);
+
+    let requisition_id = state.requisition_id.clone().unwrap();
+    let requisition = server.requisition(&requisition_id).unwrap();
+    assert_eq!(requisition.reference, state.reference);
+    assert_eq!(Some(requisition.agreement), state.agreement_id);
+
+    let accounts = flow.finish(&client, &state).await.unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(
+        accounts,
+        server.requisition(&requisition_id).unwrap().accounts
+    );
+
+    let stored = store.get(&state.reference).unwrap();
+    assert_eq!(stored.status, Some(RequisitionStatus::Linked));
+    assert_eq!(stored.accounts, accounts);
+}
+
+#[tokio::test]
+async fn generates_unique_references() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let first = flow().start(&client).await.unwrap();
+    let second = flow()
+        .reference_prefix("user-42")
+        .start(&client)
+        .await
+        .unwrap();
+
+    assert_ne!(first.reference, second.reference);
+    assert!(first.reference.starts_with("link-"));
+    assert!(second.reference.starts_with("user-42-"));
+}
+
+#[tokio::test]
+async fn resumes_from_persisted_state() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let store = Arc::new(LinkStore::new());
+
+    let state = flow().store(store.clone()).start(&client).await.unwrap();
+    let json = store.to_json().unwrap();
+
+    // e.g. after a restart
+    let store = Arc::new(LinkStore::from_json(&json).unwrap());
+    let state = store.get(&state.reference).unwrap();
+    server.link_requisition(state.requisition_id.as_deref().unwrap());
+
+    let accounts = flow()
+        .store(store.clone())
+        .finish(&client, &state)
+        .await
+        .unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(
+        store.get(&state.reference).unwrap().status,
+        Some(RequisitionStatus::Linked)
+    );
+}
+
+#[tokio::test]
+async fn fails_for_unknown_institutions() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let error = LinkFlow::new("GB", "SANDBOXFINANCE_SFIN0000", "https://example.org")
+        .start(&client)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<LinkError>(),
+        Some(&LinkError::InstitutionNotFound {
+            institution_id: "SANDBOXFINANCE_SFIN0000".to_string(),
+            country: "GB".to_string(),
+        })
+    );
+}
+
+#[tokio::test]
+async fn fails_for_rejected_and_expired_requisitions() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let state = flow().start(&client).await.unwrap();
+    let requisition_id = state.requisition_id.clone().unwrap();
+    server.set_requisition_status(&requisition_id, RequisitionStatus::Rejected);
+
+    let error = flow().finish(&client, &state).await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<LinkError>(),
+        Some(&LinkError::Rejected {
+            reference: state.reference,
+            requisition_id,
+        })
+    );
+
+    let state = flow().start(&client).await.unwrap();
+    let requisition_id = state.requisition_id.clone().unwrap();
+    server.set_requisition_status(&requisition_id, RequisitionStatus::Expired);
+
+    let error = flow().finish(&client, &state).await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<LinkError>(),
+        Some(&LinkError::Expired {
+            reference: state.reference,
+            requisition_id,
+        })
+    );
+}
+
+#[tokio::test]
+async fn times_out() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let flow = flow()
+        .poll_interval(Duration::from_millis(10))
+        .timeout(Some(Duration::from_millis(50)));
+
+    let state = flow.start(&client).await.unwrap();
+    let error = flow.finish(&client, &state).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<LinkError>(),
+        Some(LinkError::TimedOut {
+            status: RequisitionStatus::Created,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,397 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use gocardless_unofficial::testing::{MockFailure, MockServer, SandboxGenerator};
+use gocardless_unofficial::{
+    Client, Endpoint, Error, MetricsRecorder, QuotaTracker, RequestMetrics, RequisitionStatus,
+    RetryPolicy,
+};
+
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy {
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(10),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+async fn linked_account(server: &MockServer, client: &Client) -> String {
+    let requisition = client
+        .create_requisition(
+            "https://example.org/callback",
+            "SANDBOXFINANCE_SFIN0000",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(server.link_requisition(&requisition.id));
+
+    server.requisition(&requisition.id).unwrap().accounts[0].clone()
+}
+
+#[tokio::test]
+async fn lists_institutions_by_country() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let institutions = client.get_institutions("XX").await.unwrap();
+    assert_eq!(institutions.len(), 1);
+    assert_eq!(institutions[0].id, "SANDBOXFINANCE_SFIN0000");
+    assert_eq!(institutions[0].transaction_total_days, 90);
+
+    assert!(client.get_institutions("GB").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_unknown_credentials() {
+    let server = MockServer::builder()
+        .credentials("id", "key")
+        .start()
+        .unwrap();
+
+    let error = Client::builder("id".to_string(), "wrong".to_string())
+        .base_url(server.url())
+        .build()
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Api { status: 401, .. })
+    ));
+}
+
+#[tokio::test]
+async fn rejects_expired_tokens() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    server.expire_tokens();
+
+    let error = client.list_requisitions().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Api { status: 401, .. })
+    ));
+}
+
+#[tokio::test]
+async fn links_requisition_when_link_is_visited() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let agreement = client
+        .create_end_user_agreement("SANDBOXFINANCE_SFIN0000", 90)
+        .await
+        .unwrap();
+    let requisition = client
+        .create_requisition(
+            "https://example.org/callback",
+            "SANDBOXFINANCE_SFIN0000",
+            Some(&agreement.id),
+            Some("my reference"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(requisition.status, RequisitionStatus::Created);
+    assert!(requisition.accounts.is_empty());
+
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(&requisition.link)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 302);
+    assert_eq!(
+        response.headers()["location"],
+        "https://example.org/callback?ref=my%20reference"
+    );
+
+    let requisitions = client.list_requisitions().await.unwrap();
+    assert_eq!(requisitions.count, 1);
+    assert_eq!(requisitions.results[0].status, RequisitionStatus::Linked);
+    assert_eq!(requisitions.results[0].agreement, agreement.id);
+    assert_eq!(requisitions.results[0].accounts.len(), 1);
+}
+
+#[tokio::test]
+async fn rejects_duplicate_references() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let create = || {
+        client.create_requisition(
+            "https://example.org",
+            "SANDBOXFINANCE_SFIN0000",
+            None,
+            Some("reference"),
+        )
+    };
+    create().await.unwrap();
+
+    let error = create().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Api { status: 400, .. })
+    ));
+}
+
+#[tokio::test]
+async fn auto_progress_advances_requisition_on_every_poll() {
+    let server = MockServer::builder()
+        .auto_progress(true)
+        .accounts_per_requisition(2)
+        .start()
+        .unwrap();
+    let client = server.client().await.unwrap();
+
+    client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+
+    let mut statuses = Vec::new();
+    loop {
+        let requisition = client.list_requisitions().await.unwrap().results.remove(0);
+        statuses.push(requisition.status.clone());
+        if requisition.status.is_terminal() {
+            assert_eq!(requisition.accounts.len(), 2);
+            break;
+        }
+    }
+
+    assert_eq!(
+        statuses,
+        vec![
+            RequisitionStatus::GivingConsent,
+            RequisitionStatus::UndergoingAuthentication,
+            RequisitionStatus::GrantingAccess,
+            RequisitionStatus::Linked,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn serves_account_data_of_linked_accounts() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let account_id = linked_account(&server, &client).await;
+    let account = server.account(&account_id).unwrap();
+
+    let details = client.get_account_details(&account_id).await.unwrap();
+    assert_eq!(details.account, Some(account.details));
+
+    let balances = client.list_balances(&account_id).await.unwrap();
+    assert_eq!(balances.balances, account.balances);
+
+    let transactions = client.list_transactions(&account_id).await.unwrap();
+    assert_eq!(transactions.transactions, account.transactions);
+
+    let error = client.list_balances("unknown").await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Api { status: 404, .. })
+    ));
+}
+
+#[tokio::test]
+async fn serves_sandbox_accounts_in_creation_order() {
+    let sandbox = SandboxGenerator::new(7).end_date(2024, 4, 30);
+    let server = MockServer::builder()
+        .sandbox(sandbox.clone())
+        .accounts_per_requisition(2)
+        .start()
+        .unwrap();
+    let client = server.client().await.unwrap();
+
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+    server.link_requisition(&requisition.id);
+
+    let accounts = server.requisition(&requisition.id).unwrap().accounts;
+    for (index, account_id) in accounts.iter().enumerate() {
+        let transactions = client.list_transactions(account_id).await.unwrap();
+        assert_eq!(
+            transactions.transactions,
+            sandbox.account(index).transactions
+        );
+    }
+}
+
+#[tokio::test]
+async fn returns_injected_failures() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    server.fail_next(Endpoint::ListRequisitions, MockFailure::Status(503));
+
+    let error = client.list_requisitions().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Api { status: 503, .. })
+    ));
+    assert!(client.list_requisitions().await.is_ok());
+}
+
+#[tokio::test]
+async fn retries_injected_failures() {
+    let server = MockServer::start().unwrap();
+    let client = server
+        .client_builder()
+        .retry_policy(fast_retries())
+        .build()
+        .await
+        .unwrap();
+
+    server.fail_next(Endpoint::ListRequisitions, MockFailure::Status(502));
+    server.fail_next(Endpoint::ListRequisitions, MockFailure::Disconnect);
+
+    assert!(client.list_requisitions().await.is_ok());
+    assert_eq!(server.request_count(Endpoint::ListRequisitions), 3);
+}
+
+#[tokio::test]
+async fn reports_injected_rate_limits() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    server.fail_next(
+        Endpoint::GetInstitutions,
+        MockFailure::RateLimited { retry_after: 30 },
+    );
+
+    let error = client.get_institutions("XX").await.unwrap_err();
+    match error.downcast_ref::<Error>() {
+        Some(Error::RateLimited { retry_after, .. }) => {
+            assert_eq!(*retry_after, Some(Duration::from_secs(30)))
+        }
+        _ => panic!("expected a rate limited error, got {error}"),
+    }
+}
+
+#[tokio::test]
+async fn enforces_general_rate_limit() {
+    let server = MockServer::builder().rate_limit(2).start().unwrap();
+    let client = server.client().await.unwrap();
+
+    client.list_requisitions().await.unwrap();
+    assert_eq!(client.rate_limit().unwrap().remaining, Some(1));
+    client.list_requisitions().await.unwrap();
+
+    let error = client.list_requisitions().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::RateLimited { .. })
+    ));
+    assert!(client.rate_limit().unwrap().is_exhausted());
+
+    server.reset_rate_limits();
+    assert!(client.list_requisitions().await.is_ok());
+}
+
+#[tokio::test]
+async fn enforces_account_rate_limit() {
+    let server = MockServer::builder().account_rate_limit(1).start().unwrap();
+    let client = server.client().await.unwrap();
+    let account_id = linked_account(&server, &client).await;
+
+    client.list_balances(&account_id).await.unwrap();
+    assert_eq!(
+        client.account_rate_limit(&account_id).unwrap().remaining,
+        Some(0)
+    );
+
+    let error = client.list_balances(&account_id).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::RateLimited { .. })
+    ));
+
+    // The limit applies per endpoint
+    assert!(client.list_transactions(&account_id).await.is_ok());
+}
+
+#[derive(Default)]
+struct EventRecorder(Mutex<Vec<String>>);
+
+impl MetricsRecorder for EventRecorder {
+    fn request_started(&self, endpoint: Endpoint) {
+        self.0.lock().unwrap().push(format!("started {endpoint}"));
+    }
+
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("finished {}", metrics.endpoint));
+    }
+
+    fn request_refused(&self, endpoint: Endpoint) {
+        self.0.lock().unwrap().push(format!("refused {endpoint}"));
+    }
+
+    fn token_created(&self) {
+        self.0.lock().unwrap().push("token created".to_string());
+    }
+}
+
+#[tokio::test]
+async fn reports_refused_calls_separately() {
+    let server = MockServer::start().unwrap();
+    let account_id = linked_account(&server, &server.client().await.unwrap()).await;
+    let recorder = Arc::new(EventRecorder::default());
+    let client = server
+        .client_builder()
+        .quota_tracker(QuotaTracker::new(1))
+        .metrics_recorder(recorder.clone())
+        .build()
+        .await
+        .unwrap();
+
+    client.list_transactions(&account_id).await.unwrap();
+    let error = client.list_transactions(&account_id).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::QuotaExceeded { .. })
+    ));
+
+    let transactions = Endpoint::ListTransactions;
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec![
+            format!("started {}", Endpoint::CreateToken),
+            format!("finished {}", Endpoint::CreateToken),
+            "token created".to_string(),
+            format!("started {transactions}"),
+            format!("finished {transactions}"),
+            format!("refused {transactions}"),
+        ]
+    );
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn works_with_blocking_client() {
+    let server = MockServer::start().unwrap();
+    let client =
+        gocardless_unofficial::blocking::Client::builder("id".to_string(), "key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .unwrap();
+    server.link_requisition(&requisition.id);
+
+    let account_id = &server.requisition(&requisition.id).unwrap().accounts[0];
+    let transactions = client.list_transactions(account_id).unwrap();
+    assert!(!transactions.transactions.booked.is_empty());
+}
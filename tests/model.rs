@@ -0,0 +1,261 @@
+use gocardless_unofficial::{
+    Account, AccountStatus, AccountUsage, Balance, BalanceType, Institution, ListBalancesResponse,
+    Requisition, RequisitionStatus, SupportedFeature, Transaction,
+};
+use serde_json::{json, Value};
+
+/// Deserializes `json` into `T` and serializes it back.
+fn round_trip<T>(json: &Value) -> (T, Value)
+where
+    T: serde::de::DeserializeOwned + serde::Serialize,
+{
+    let value: T = serde_json::from_value(json.clone()).unwrap();
+    let serialized = serde_json::to_value(&value).unwrap();
+    (value, serialized)
+}
+
+#[test]
+fn keeps_unknown_fields_through_a_round_trip() {
+    let json = json!({
+        "transactionId": "T1",
+        "bookingDate": "2024-01-02",
+        "transactionAmount": { "amount": "-10.50", "currency": "EUR" },
+        "bankTransactionCode": "PMNT-ICDT-STDO",
+        "additionalDataStructured": { "cardAcceptorId": "123" }
+    });
+    let (transaction, serialized): (Transaction, _) = round_trip(&json);
+    assert_eq!(transaction.extra["bankTransactionCode"], "PMNT-ICDT-STDO");
+    assert_eq!(serialized["bankTransactionCode"], "PMNT-ICDT-STDO");
+    assert_eq!(
+        serialized["additionalDataStructured"],
+        json!({ "cardAcceptorId": "123" })
+    );
+    assert!(!transaction.extra.contains_key("transactionId"));
+
+    let json = json!({
+        "resourceId": "R1",
+        "currency": "EUR",
+        "ownerAddressStructured": { "townName": "Ghent" }
+    });
+    let (account, serialized): (Account, _) = round_trip(&json);
+    assert_eq!(account.extra.len(), 1);
+    assert_eq!(
+        serialized["ownerAddressStructured"],
+        json!({ "townName": "Ghent" })
+    );
+
+    let json = json!({
+        "balanceAmount": { "amount": "100.00", "currency": "EUR" },
+        "balanceType": "expected",
+        "referenceDate": "2024-01-02",
+        "lastCommittedTransaction": "T1"
+    });
+    let (_, serialized): (Balance, _) = round_trip(&json);
+    assert_eq!(serialized["lastCommittedTransaction"], "T1");
+
+    let json = json!({
+        "id": "REQ",
+        "created": "2024-01-02T00:00:00Z",
+        "redirect": "https://example.com",
+        "status": "LN",
+        "institution_id": "BANK",
+        "agreement": "AGR",
+        "reference": "ref",
+        "accounts": [],
+        "user_language": "EN",
+        "link": "https://example.com/link",
+        "account_selection": false,
+        "redirect_immediate": false,
+        "ssn": null
+    });
+    let (requisition, serialized): (Requisition, _) = round_trip(&json);
+    assert_eq!(requisition.extra["ssn"], Value::Null);
+    assert!(serialized.as_object().unwrap().contains_key("ssn"));
+
+    let json = json!({
+        "id": "BANK",
+        "name": "Bank",
+        "bic": "BANKBEBB",
+        "transaction_total_days": "90",
+        "countries": ["BE"],
+        "logo": "https://example.com/logo.png",
+        "pagination": true
+    });
+    let (_, serialized): (Institution, _) = round_trip(&json);
+    assert_eq!(serialized["pagination"], true);
+}
+
+#[test]
+fn keeps_unknown_codes_through_a_round_trip() {
+    fn assert_round_trips<T>(code: &str, known: T)
+    where
+        T: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug + PartialEq,
+    {
+        let value: T = serde_json::from_value(json!(code)).unwrap();
+        assert_eq!(value, known);
+        assert_eq!(serde_json::to_value(&value).unwrap(), json!(code));
+    }
+
+    assert_round_trips("LN", RequisitionStatus::Linked);
+    assert_round_trips("XX", RequisitionStatus::Unknown("XX".to_string()));
+    assert_round_trips("expected", BalanceType::Expected);
+    assert_round_trips("futureType", BalanceType::Unknown("futureType".to_string()));
+    assert_round_trips("blocked", AccountStatus::Blocked);
+    assert_round_trips("frozen", AccountStatus::Unknown("frozen".to_string()));
+    assert_round_trips("ORGA", AccountUsage::Professional);
+    assert_round_trips("SHARED", AccountUsage::Unknown("SHARED".to_string()));
+    assert_round_trips("payments", SupportedFeature::Payments);
+    assert_round_trips(
+        "instant_payments",
+        SupportedFeature::Unknown("instant_payments".to_string()),
+    );
+
+    // Codes are case sensitive, so differently cased codes are kept as they are
+    let status = RequisitionStatus::from("ln");
+    assert_eq!(status, RequisitionStatus::Unknown("ln".to_string()));
+    assert_eq!(status.to_string(), "ln");
+    assert_eq!(status.description(), "unknown");
+    assert!(!status.is_terminal());
+}
+
+fn balance(balance_type: &str, amount: &str) -> Value {
+    json!({
+        "balanceAmount": { "amount": amount, "currency": "EUR" },
+        "balanceType": balance_type,
+        "referenceDate": "2024-01-02"
+    })
+}
+
+fn best_available(balances: Vec<Value>) -> Option<(BalanceType, String)> {
+    let response: ListBalancesResponse =
+        serde_json::from_value(json!({ "balances": balances })).unwrap();
+    response.best_available().map(|balance| {
+        (
+            balance.balance_type.clone(),
+            balance.balance_amount.amount.clone(),
+        )
+    })
+}
+
+#[test]
+fn picks_the_best_available_balance() {
+    assert_eq!(
+        best_available(vec![
+            balance("closingBooked", "1"),
+            balance("futureType", "2"),
+            balance("interimBooked", "3"),
+            balance("expected", "4"),
+        ]),
+        Some((BalanceType::Expected, "4".to_string()))
+    );
+    assert_eq!(
+        best_available(vec![
+            balance("openingAvailable", "1"),
+            balance("forwardAvailable", "2"),
+            balance("closingAvailable", "3"),
+        ]),
+        Some((BalanceType::ClosingAvailable, "3".to_string()))
+    );
+
+    // Unknown and unranked types are only used when nothing better is returned, in API order
+    assert_eq!(
+        best_available(vec![
+            balance("futureType", "1"),
+            balance("information", "2"),
+        ]),
+        Some((
+            BalanceType::Unknown("futureType".to_string()),
+            "1".to_string()
+        ))
+    );
+    assert_eq!(
+        best_available(vec![
+            balance("information", "1"),
+            balance("futureType", "2"),
+            balance("openingBooked", "3"),
+        ]),
+        Some((BalanceType::OpeningBooked, "3".to_string()))
+    );
+
+    // The first of several balances of the same type wins
+    assert_eq!(
+        best_available(vec![
+            balance("interimAvailable", "1"),
+            balance("interimAvailable", "2"),
+        ]),
+        Some((BalanceType::InterimAvailable, "1".to_string()))
+    );
+    assert_eq!(best_available(Vec::new()), None);
+    let response: ListBalancesResponse = serde_json::from_value(json!({})).unwrap();
+    assert!(response.best_available().is_none());
+}
+
+fn institution(fields: Value) -> Institution {
+    let mut json = json!({
+        "id": "BANK",
+        "name": "Bank",
+        "bic": "BANKBEBB",
+        "countries": ["BE"],
+        "logo": "https://example.com/logo.png"
+    });
+    json.as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    serde_json::from_value(json).unwrap()
+}
+
+#[test]
+fn parses_institution_days_from_numbers_and_strings() {
+    let days = |value: Value| {
+        let institution = institution(json!({
+            "transaction_total_days": value.clone(),
+            "max_access_valid_for_days": value,
+        }));
+        (
+            institution.transaction_total_days,
+            institution.max_access_valid_for_days,
+        )
+    };
+    assert_eq!(days(json!("90")), (90, Some(90)));
+    assert_eq!(days(json!(" 730 ")), (730, Some(730)));
+    assert_eq!(days(json!(90)), (90, Some(90)));
+
+    let missing = institution(json!({}));
+    assert_eq!(missing.transaction_total_days, 0);
+    assert_eq!(missing.max_access_valid_for_days, None);
+    assert_eq!(
+        institution(json!({ "max_access_valid_for_days": "" })).max_access_valid_for_days,
+        None
+    );
+
+    let invalid = json!({
+        "id": "BANK",
+        "name": "Bank",
+        "bic": "BANKBEBB",
+        "transaction_total_days": "ninety",
+        "countries": ["BE"],
+        "logo": "https://example.com/logo.png"
+    });
+    assert!(serde_json::from_value::<Institution>(invalid).is_err());
+}
+
+#[test]
+fn parses_institution_features() {
+    let featured = institution(json!({
+        "supported_features": ["pending_transactions", "payments", "instant_payments"],
+        "supported_payments": { "single-payment": ["SCT", "ISCT"] }
+    }));
+    assert!(featured.supports(&SupportedFeature::PendingTransactions));
+    assert!(featured.supports(&SupportedFeature::Payments));
+    assert!(featured.supports(&SupportedFeature::Unknown("instant_payments".to_string())));
+    assert!(!featured.supports(&SupportedFeature::AccountSelection));
+    assert_eq!(
+        featured.supported_payments["single-payment"],
+        vec!["SCT".to_string(), "ISCT".to_string()]
+    );
+    assert!(featured.extra.is_empty());
+
+    let bare = institution(json!({}));
+    assert!(bare.supported_features.is_empty());
+    assert!(!bare.supports(&SupportedFeature::PendingTransactions));
+}
@@ -0,0 +1,249 @@
+use gocardless_unofficial::protocol::{self, DEFAULT_BASE_URL};
+use gocardless_unofficial::{CreateTokenResponse, Endpoint, ListBalancesResponse};
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::{json, Value};
+
+const BASE_URL: &str = "http://localhost:8080";
+
+fn token() -> SecretString {
+    SecretString::new("access-token".to_string())
+}
+
+fn body(request: &http::Request<Vec<u8>>) -> Value {
+    serde_json::from_slice(request.body()).unwrap()
+}
+
+fn response(status: u16, body: &str) -> http::Response<Vec<u8>> {
+    let mut response = http::Response::new(body.as_bytes().to_vec());
+    *response.status_mut() = http::StatusCode::from_u16(status).unwrap();
+    response
+}
+
+#[test]
+fn builds_token_requests_without_authorization() {
+    let request = protocol::create_token(
+        DEFAULT_BASE_URL,
+        &SecretString::new("my-secret-id".to_string()),
+        &SecretString::new("my-secret-key".to_string()),
+    )
+    .unwrap();
+
+    assert_eq!(request.method(), http::Method::POST);
+    assert_eq!(
+        request.uri(),
+        "https://bankaccountdata.gocardless.com/api/v2/token/new/"
+    );
+    assert_eq!(request.headers()["Content-Type"], "application/json");
+    assert_eq!(request.headers()["Accept"], "application/json");
+    assert!(request.headers().get(http::header::AUTHORIZATION).is_none());
+    assert_eq!(
+        body(&request),
+        json!({ "secret_id": "my-secret-id", "secret_key": "my-secret-key" })
+    );
+}
+
+#[test]
+fn builds_authorized_requests() {
+    let request = protocol::list_balances(BASE_URL, &token(), "account").unwrap();
+
+    assert_eq!(request.method(), http::Method::GET);
+    assert_eq!(
+        request.uri(),
+        "http://localhost:8080/api/v2/accounts/account/balances"
+    );
+    let authorization = &request.headers()[http::header::AUTHORIZATION];
+    assert_eq!(authorization, "Bearer access-token");
+    assert!(authorization.is_sensitive());
+    assert!(!format!("{request:?}").contains("access-token"));
+    assert!(request.body().is_empty());
+    assert!(request.headers().get("Content-Type").is_none());
+}
+
+#[test]
+fn builds_request_bodies() {
+    let request = protocol::create_end_user_agreement_with_access(
+        BASE_URL,
+        &token(),
+        "BANK",
+        180,
+        90,
+        &["transactions"],
+    )
+    .unwrap();
+    assert_eq!(
+        request.uri(),
+        "http://localhost:8080/api/v2/agreements/enduser/"
+    );
+    assert_eq!(
+        body(&request),
+        json!({
+            "institution_id": "BANK",
+            "max_historical_days": 180,
+            "access_valid_for_days": "90",
+            "access_scope": ["transactions"],
+        })
+    );
+
+    let request = protocol::create_requisition(
+        BASE_URL,
+        &token(),
+        "https://example.com/callback",
+        "BANK",
+        Some("AGREEMENT"),
+        None,
+    )
+    .unwrap();
+    assert_eq!(request.method(), http::Method::POST);
+    assert_eq!(request.uri(), "http://localhost:8080/api/v2/requisitions/");
+    assert_eq!(
+        body(&request),
+        json!({
+            "redirect": "https://example.com/callback",
+            "institution_id": "BANK",
+            "user_language": "EN",
+            "agreement": "AGREEMENT",
+        })
+    );
+}
+
+#[test]
+fn encodes_ids_and_query_values() {
+    let request = protocol::get_institutions(BASE_URL, &token(), "GB&foo=bar").unwrap();
+    assert_eq!(
+        request.uri(),
+        "http://localhost:8080/api/v2/institutions/?country=GB%26foo%3Dbar"
+    );
+
+    let request = protocol::get_requisition(BASE_URL, &token(), "a/b?c#d").unwrap();
+    assert_eq!(
+        request.uri(),
+        "http://localhost:8080/api/v2/requisitions/a%2Fb%3Fc%23d/"
+    );
+
+    let request = protocol::list_transactions_between(
+        BASE_URL,
+        &token(),
+        "caf\u{e9}",
+        Some("2024-01-01"),
+        Some("2024-02-01&date_from=2000-01-01"),
+    )
+    .unwrap();
+    assert_eq!(
+        request.uri(),
+        "http://localhost:8080/api/v2/accounts/caf%C3%A9/transactions?date_from=2024-01-01&date_to=2024-02-01%26date_from%3D2000-01-01"
+    );
+
+    let request =
+        protocol::list_transactions_between(BASE_URL, &token(), "account", None, None).unwrap();
+    assert_eq!(
+        request.uri(),
+        "http://localhost:8080/api/v2/accounts/account/transactions"
+    );
+}
+
+#[test]
+fn refuses_ids_which_change_the_path() {
+    for id in ["", ".", ".."] {
+        assert!(protocol::get_account_details(BASE_URL, &token(), id).is_err());
+        assert!(protocol::get_end_user_agreement(BASE_URL, &token(), id).is_err());
+        assert!(protocol::list_transactions(BASE_URL, &token(), id).is_err());
+    }
+    // Dots are only refused as whole segments
+    assert!(protocol::get_account_details(BASE_URL, &token(), "...").is_ok());
+}
+
+#[test]
+fn parses_successful_responses() {
+    let token: CreateTokenResponse = protocol::parse_response(&response(
+        200,
+        r#"{"access": "access", "access_expires": 86400, "refresh": "refresh", "refresh_expires": 2592000}"#,
+    ))
+    .unwrap();
+    assert_eq!(token.access.expose_secret(), "access");
+    assert_eq!(token.refresh_expires, 2592000);
+
+    let balances: ListBalancesResponse =
+        protocol::parse_response(&response(200, r#"{"balances": []}"#)).unwrap();
+    assert!(balances.balances.is_empty());
+
+    // Any successful status is parsed, e.g. `201 Created`
+    let balances: ListBalancesResponse = protocol::parse_response(&response(201, "{}")).unwrap();
+    assert!(balances.balances.is_empty());
+}
+
+#[test]
+fn reports_malformed_bodies() {
+    let error =
+        protocol::parse_response::<ListBalancesResponse>(&response(200, "not json")).unwrap_err();
+    assert!(error.downcast_ref::<serde_json::Error>().is_some());
+    let error = protocol::parse_response::<ListBalancesResponse>(&response(200, "")).unwrap_err();
+    assert!(error.downcast_ref::<serde_json::Error>().is_some());
+}
+
+#[test]
+fn builds_the_paths_of_the_endpoint_templates() {
+    let token = token();
+    let requests = [
+        (
+            Endpoint::CreateToken,
+            protocol::create_token(BASE_URL, &token, &token),
+        ),
+        (
+            Endpoint::GetInstitutions,
+            protocol::get_institutions(BASE_URL, &token, "GB"),
+        ),
+        (
+            Endpoint::CreateEndUserAgreement,
+            protocol::create_end_user_agreement(BASE_URL, &token, "BANK", 90),
+        ),
+        (
+            Endpoint::GetEndUserAgreement,
+            protocol::get_end_user_agreement(BASE_URL, &token, "ID"),
+        ),
+        (
+            Endpoint::ListRequisitions,
+            protocol::list_requisitions(BASE_URL, &token),
+        ),
+        (
+            Endpoint::CreateRequisition,
+            protocol::create_requisition(
+                BASE_URL,
+                &token,
+                "https://example.com",
+                "BANK",
+                None,
+                None,
+            ),
+        ),
+        (
+            Endpoint::GetRequisition,
+            protocol::get_requisition(BASE_URL, &token, "ID"),
+        ),
+        (
+            Endpoint::ListTransactions,
+            protocol::list_transactions(BASE_URL, &token, "ID"),
+        ),
+        (
+            Endpoint::ListTransactions,
+            protocol::list_transactions_between(BASE_URL, &token, "ID", Some("2024-01-01"), None),
+        ),
+        (
+            Endpoint::ListBalances,
+            protocol::list_balances(BASE_URL, &token, "ID"),
+        ),
+        (
+            Endpoint::GetAccountDetails,
+            protocol::get_account_details(BASE_URL, &token, "ID"),
+        ),
+    ];
+
+    for (endpoint, request) in requests {
+        let request = request.unwrap();
+        assert_eq!(
+            request.uri().path(),
+            endpoint.path().replace("{id}", "ID"),
+            "{endpoint}"
+        );
+        assert_eq!(request.method().as_str(), endpoint.method(), "{endpoint}");
+    }
+}
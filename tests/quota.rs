@@ -0,0 +1,167 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use gocardless_unofficial::{Endpoint, Error, QuotaTracker, RateLimit};
+
+const ACCOUNT: &str = "account";
+const DAY: u64 = 24 * 60 * 60;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// A tracker which recorded calls to the transactions endpoint `ages` seconds ago.
+fn tracker_with_calls(daily_limit: u32, ages: &[u64]) -> QuotaTracker {
+    let calls: Vec<u64> = ages.iter().map(|age| now() - age).collect();
+    let json = format!(
+        r#"[{{"account_id":"{ACCOUNT}","endpoint":"list_transactions","calls":{calls:?}}}]"#
+    );
+    QuotaTracker::from_json(daily_limit, &json).unwrap()
+}
+
+fn retry_after(result: Result<(), Error>) -> Option<Duration> {
+    match result {
+        Err(Error::QuotaExceeded { retry_after, .. }) => retry_after,
+        result => panic!("unexpected result: {result:?}"),
+    }
+}
+
+fn reported(remaining: u32, reset: u64) -> RateLimit {
+    RateLimit {
+        limit: Some(4),
+        remaining: Some(remaining),
+        reset: Some(Duration::from_secs(reset)),
+        observed_at: SystemTime::now(),
+    }
+}
+
+#[test]
+fn counts_calls_in_a_rolling_window() {
+    // Calls older than a day no longer count
+    let tracker = tracker_with_calls(2, &[DAY + 60, DAY - 60, 60]);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 0);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListBalances), 2);
+    assert_eq!(tracker.remaining("other", Endpoint::ListTransactions), 2);
+
+    // Another call is possible once the oldest one leaves the window
+    let retry_after = retry_after(tracker.check(ACCOUNT, Endpoint::ListTransactions)).unwrap();
+    assert!(retry_after <= Duration::from_secs(60) && retry_after >= Duration::from_secs(58));
+
+    let tracker = tracker_with_calls(2, &[DAY + 60, 60]);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 1);
+    tracker.check(ACCOUNT, Endpoint::ListTransactions).unwrap();
+    tracker.record_success(ACCOUNT, Endpoint::ListTransactions, None);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 0);
+}
+
+#[test]
+fn reserves_calls_until_recorded_or_released() {
+    let tracker = QuotaTracker::new(2);
+    tracker.check(ACCOUNT, Endpoint::ListTransactions).unwrap();
+    tracker.check(ACCOUNT, Endpoint::ListTransactions).unwrap();
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 0);
+    assert!(tracker.check(ACCOUNT, Endpoint::ListTransactions).is_err());
+
+    tracker.release(ACCOUNT, Endpoint::ListTransactions);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 1);
+
+    // Recording a reserved call doesn't count it twice
+    tracker.record_success(ACCOUNT, Endpoint::ListTransactions, None);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 1);
+    tracker.check(ACCOUNT, Endpoint::ListTransactions).unwrap();
+    tracker.record_rate_limited(ACCOUNT, Endpoint::ListTransactions, None, None);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 1);
+
+    // Releasing more than was reserved is harmless
+    tracker.release(ACCOUNT, Endpoint::ListTransactions);
+    tracker.release("other", Endpoint::ListTransactions);
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 1);
+}
+
+#[test]
+fn lets_concurrent_callers_reserve_only_the_remaining_calls() {
+    let tracker = Arc::new(QuotaTracker::new(4));
+    let threads: Vec<_> = (0..16)
+        .map(|_| {
+            let tracker = Arc::clone(&tracker);
+            std::thread::spawn(move || tracker.check(ACCOUNT, Endpoint::ListTransactions).is_ok())
+        })
+        .collect();
+    let allowed = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .filter(|allowed| *allowed)
+        .count();
+    assert_eq!(allowed, 4);
+}
+
+#[test]
+fn prefers_the_reported_rate_limit() {
+    let tracker = QuotaTracker::new(4);
+    tracker.record_success(ACCOUNT, Endpoint::ListTransactions, Some(&reported(0, 600)));
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 0);
+    let retry_after = retry_after(tracker.check(ACCOUNT, Endpoint::ListTransactions)).unwrap();
+    assert!(retry_after <= Duration::from_secs(600) && retry_after >= Duration::from_secs(598));
+
+    let tracker = tracker_with_calls(4, &[300, 200, 100]);
+    tracker.record_success(ACCOUNT, Endpoint::ListTransactions, Some(&reported(3, 600)));
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 3);
+
+    // Reported state without a reset time can't expire, so it is ignored
+    let tracker = QuotaTracker::new(4);
+    let without_reset = RateLimit {
+        reset: None,
+        ..reported(0, 0)
+    };
+    tracker.record_success(ACCOUNT, Endpoint::ListTransactions, Some(&without_reset));
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 3);
+
+    // Huge reset times saturate instead of overflowing
+    let tracker = QuotaTracker::new(4);
+    tracker.record_rate_limited(
+        ACCOUNT,
+        Endpoint::ListTransactions,
+        None,
+        Some(Duration::from_secs(u64::MAX)),
+    );
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 0);
+}
+
+#[test]
+fn expires_the_reported_rate_limit() {
+    let json = format!(
+        r#"[{{"account_id":"{ACCOUNT}","endpoint":"list_transactions","calls":[{}],"reported_remaining":0,"reported_reset_at":{}}}]"#,
+        now() - 60,
+        now() - 1
+    );
+    let tracker = QuotaTracker::from_json(4, &json).unwrap();
+    assert_eq!(tracker.remaining(ACCOUNT, Endpoint::ListTransactions), 3);
+}
+
+#[test]
+fn persists_its_state() {
+    let tracker = tracker_with_calls(4, &[DAY + 60, 120, 60]);
+    tracker.record_success(ACCOUNT, Endpoint::ListBalances, Some(&reported(1, 600)));
+    tracker.check("other", Endpoint::GetAccountDetails).unwrap();
+
+    let json = tracker.to_json().unwrap();
+    let restored = QuotaTracker::from_json(4, &json).unwrap();
+    assert_eq!(restored.to_json().unwrap(), json);
+    assert_eq!(restored.remaining(ACCOUNT, Endpoint::ListTransactions), 2);
+    assert_eq!(restored.remaining(ACCOUNT, Endpoint::ListBalances), 1);
+    // Reservations are not persisted, as the calls can't be recorded by another run
+    assert_eq!(restored.remaining("other", Endpoint::GetAccountDetails), 4);
+
+    let path = std::env::temp_dir().join(format!("quota-{}.json", std::process::id()));
+    tracker.save(&path).unwrap();
+    let loaded = QuotaTracker::load(4, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.to_json().unwrap(), json);
+
+    let missing = QuotaTracker::load(4, &path).unwrap();
+    assert_eq!(missing.to_json().unwrap(), "[]");
+    assert!(QuotaTracker::from_json(4, "not json").is_err());
+}
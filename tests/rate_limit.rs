@@ -0,0 +1,208 @@
+use std::time::{Duration, SystemTime};
+
+use gocardless_unofficial::{protocol, Error, ListBalancesResponse, RateLimit, RateLimits};
+use http::{HeaderMap, HeaderValue};
+
+fn headers(headers: &[(&'static str, &'static str)]) -> HeaderMap {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                http::HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            )
+        })
+        .collect()
+}
+
+fn response(status: u16, headers: HeaderMap, body: &str) -> http::Response<Vec<u8>> {
+    let mut response = http::Response::new(body.as_bytes().to_vec());
+    *response.status_mut() = http::StatusCode::from_u16(status).unwrap();
+    *response.headers_mut() = headers;
+    response
+}
+
+fn parse_error(response: &http::Response<Vec<u8>>) -> Error {
+    let error = protocol::parse_response::<ListBalancesResponse>(response).unwrap_err();
+    match error.downcast::<Error>() {
+        Ok(error) => *error,
+        Err(error) => panic!("unexpected error: {error}"),
+    }
+}
+
+#[test]
+fn reads_both_header_spellings() {
+    let rate_limits = RateLimits::from_headers(&headers(&[
+        ("http_x_ratelimit_limit", "100"),
+        ("http_x_ratelimit_remaining", " 99 "),
+        ("http_x_ratelimit_reset", "60"),
+        ("x-ratelimit-account-success-limit", "4"),
+        ("x-ratelimit-account-success-remaining", "0"),
+        ("x-ratelimit-account-success-reset", "3600"),
+    ]));
+
+    let general = rate_limits.general.unwrap();
+    assert_eq!(general.limit, Some(100));
+    assert_eq!(general.remaining, Some(99));
+    assert_eq!(general.reset, Some(Duration::from_secs(60)));
+    assert!(!general.is_exhausted());
+    assert_eq!(
+        general.reset_at(),
+        Some(general.observed_at + Duration::from_secs(60))
+    );
+
+    let account = rate_limits.account_success.unwrap();
+    assert_eq!(account.limit, Some(4));
+    assert!(account.is_exhausted());
+    assert_eq!(account.reset, Some(Duration::from_secs(3600)));
+}
+
+#[test]
+fn ignores_missing_and_garbage_headers() {
+    assert_eq!(
+        RateLimits::from_headers(&HeaderMap::new()),
+        RateLimits::default()
+    );
+    assert_eq!(
+        RateLimits::from_headers(&headers(&[
+            ("http_x_ratelimit_limit", "lots"),
+            ("http_x_ratelimit_remaining", "-1"),
+            ("http_x_ratelimit_reset", "1.5"),
+        ])),
+        RateLimits::default()
+    );
+
+    let rate_limits = RateLimits::from_headers(&headers(&[
+        ("http_x_ratelimit_limit", "100"),
+        ("http_x_ratelimit_remaining", ""),
+    ]));
+    let general = rate_limits.general.unwrap();
+    assert_eq!(general.limit, Some(100));
+    assert_eq!(general.remaining, None);
+    assert_eq!(general.reset, None);
+    assert_eq!(general.reset_at(), None);
+    assert_eq!(rate_limits.account_success, None);
+}
+
+#[test]
+fn handles_huge_header_values() {
+    let rate_limits = RateLimits::from_headers(&headers(&[
+        ("http_x_ratelimit_limit", "4294967296"),
+        ("http_x_ratelimit_remaining", "18446744073709551615"),
+        ("http_x_ratelimit_reset", "18446744073709551615"),
+    ]));
+    let general = rate_limits.general.unwrap();
+    assert_eq!(general.limit, None);
+    assert_eq!(general.remaining, None);
+    assert_eq!(general.reset, Some(Duration::from_secs(u64::MAX)));
+    assert_eq!(general.reset_at(), None);
+
+    // Values beyond `u64` are ignored like any other garbage
+    assert_eq!(
+        RateLimits::from_headers(&headers(&[(
+            "http_x_ratelimit_reset",
+            "18446744073709551616"
+        )])),
+        RateLimits::default()
+    );
+
+    let rate_limit = RateLimit {
+        limit: None,
+        remaining: None,
+        reset: Some(Duration::MAX),
+        observed_at: SystemTime::now(),
+    };
+    assert_eq!(rate_limit.reset_at(), None);
+}
+
+#[test]
+fn maps_too_many_requests_to_rate_limited() {
+    let error = parse_error(&response(
+        429,
+        headers(&[
+            ("retry-after", "30"),
+            ("http_x_ratelimit_remaining", "0"),
+            ("http_x_ratelimit_reset", "60"),
+        ]),
+        "",
+    ));
+    match error {
+        Error::RateLimited {
+            retry_after,
+            rate_limits,
+        } => {
+            // `Retry-After` takes precedence over the reset of the exhausted rate limit
+            assert_eq!(retry_after, Some(Duration::from_secs(30)));
+            assert!(rate_limits.general.unwrap().is_exhausted());
+        }
+        error => panic!("unexpected error: {error}"),
+    }
+    assert_eq!(
+        parse_error(&response(429, headers(&[("retry-after", "30")]), "")).to_string(),
+        "rate limited, retry after 30 seconds"
+    );
+}
+
+#[test]
+fn falls_back_to_the_exhausted_rate_limit() {
+    // HTTP dates are not sent by GoCardless and are ignored
+    let error = parse_error(&response(
+        429,
+        headers(&[
+            ("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ("http_x_ratelimit_remaining", "5"),
+            ("http_x_ratelimit_reset", "60"),
+            ("http_x_ratelimit_account_success_remaining", "0"),
+            ("http_x_ratelimit_account_success_reset", "3600"),
+        ]),
+        "",
+    ));
+    assert!(matches!(
+        error,
+        Error::RateLimited {
+            retry_after: Some(retry_after),
+            ..
+        } if retry_after == Duration::from_secs(3600)
+    ));
+
+    let error = parse_error(&response(429, HeaderMap::new(), ""));
+    assert!(matches!(
+        error,
+        Error::RateLimited {
+            retry_after: None,
+            ..
+        }
+    ));
+    assert_eq!(error.to_string(), "rate limited");
+}
+
+#[test]
+fn maps_other_failures_to_api_errors() {
+    let error = parse_error(&response(
+        401,
+        HeaderMap::new(),
+        r#"{"summary": "Authentication failed", "detail": "Token is invalid", "status_code": 401}"#,
+    ));
+    assert!(matches!(
+        &error,
+        Error::Api {
+            status: 401,
+            summary: Some(summary),
+            detail: Some(detail),
+        } if summary == "Authentication failed" && detail == "Token is invalid"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "request failed with status 401: Authentication failed (Token is invalid)"
+    );
+
+    let error = parse_error(&response(502, HeaderMap::new(), "<html>Bad gateway</html>"));
+    assert!(matches!(
+        error,
+        Error::Api {
+            status: 502,
+            summary: None,
+            detail: None,
+        }
+    ));
+}
@@ -0,0 +1,145 @@
+use gocardless_unofficial::{parse_payment_references, PaymentReference, Transaction};
+
+#[test]
+fn validates_creditor_references() {
+    assert_eq!(
+        PaymentReference::parse_creditor("rf18 5390 0754 7034"),
+        Some(PaymentReference::Creditor("RF18539007547034".to_string()))
+    );
+    assert_eq!(PaymentReference::parse_creditor("RF19539007547034"), None);
+    assert_eq!(PaymentReference::parse_creditor("RF18"), None);
+    assert_eq!(PaymentReference::parse_creditor("XX18539007547034"), None);
+}
+
+#[test]
+fn validates_belgian_structured_communications() {
+    let reference = PaymentReference::parse_belgian_structured("+++090/9337/55493+++").unwrap();
+    assert_eq!(reference.as_str(), "090933755493");
+    assert_eq!(reference.to_string(), "+++090/9337/55493+++");
+    assert_eq!(
+        PaymentReference::parse_belgian_structured("***090/9337/55493***"),
+        Some(reference.clone())
+    );
+    assert_eq!(
+        PaymentReference::parse_belgian_structured("090933755493"),
+        Some(reference)
+    );
+
+    assert_eq!(
+        PaymentReference::parse_belgian_structured("+++090/9337/55494+++"),
+        None
+    );
+    assert_eq!(
+        PaymentReference::parse_belgian_structured("+++0909/337/55493+++"),
+        None
+    );
+
+    // References built directly aren't validated, but still display without panicking
+    let unvalidated = PaymentReference::BelgianStructured("12é45678901".to_string());
+    assert_eq!(unvalidated.to_string(), "12é45678901");
+}
+
+#[test]
+fn validates_finnish_references() {
+    assert_eq!(
+        PaymentReference::parse_finnish("1234561"),
+        Some(PaymentReference::Finnish("1234561".to_string()))
+    );
+    assert_eq!(
+        PaymentReference::parse_finnish("12345 67890 7"),
+        Some(PaymentReference::Finnish("12345678907".to_string()))
+    );
+    assert_eq!(PaymentReference::parse_finnish("1234562"), None);
+    assert_eq!(PaymentReference::parse_finnish("123"), None);
+}
+
+#[test]
+fn validates_norwegian_kids() {
+    // MOD10 (Luhn)
+    assert_eq!(
+        PaymentReference::parse_norwegian_kid("123456782"),
+        Some(PaymentReference::NorwegianKid("123456782".to_string()))
+    );
+    // MOD11, including a remainder of 10 written as `-`
+    assert_eq!(
+        PaymentReference::parse_norwegian_kid("123456785"),
+        Some(PaymentReference::NorwegianKid("123456785".to_string()))
+    );
+    assert_eq!(
+        PaymentReference::parse_norwegian_kid("100008-"),
+        Some(PaymentReference::NorwegianKid("100008-".to_string()))
+    );
+
+    assert_eq!(PaymentReference::parse_norwegian_kid("123456783"), None);
+    assert_eq!(PaymentReference::parse_norwegian_kid("100009-"), None);
+}
+
+#[test]
+fn finds_references_in_remittance_information() {
+    assert_eq!(
+        parse_payment_references("Invoice 42, RF18 5390 0754 7034, thanks", None),
+        vec![PaymentReference::Creditor("RF18539007547034".to_string())]
+    );
+    assert_eq!(
+        parse_payment_references("+++090/9337/55493+++ +++090/9337/55493+++", None),
+        vec![PaymentReference::BelgianStructured(
+            "090933755493".to_string()
+        )]
+    );
+}
+
+#[test]
+fn disambiguates_bare_numbers_by_keyword_and_currency() {
+    let finnish = PaymentReference::Finnish("1234561".to_string());
+
+    // A bare number making up the whole text is validated according to the currency
+    assert_eq!(
+        parse_payment_references("1234561", Some("eur")),
+        vec![finnish.clone()]
+    );
+    assert_eq!(parse_payment_references("1234561", None), vec![]);
+    assert_eq!(
+        parse_payment_references("123456785", Some("NOK")),
+        vec![PaymentReference::NorwegianKid("123456785".to_string())]
+    );
+
+    // Elsewhere, it must follow a keyword
+    assert_eq!(
+        parse_payment_references("Order 1234561", Some("EUR")),
+        vec![]
+    );
+    assert_eq!(
+        parse_payment_references("Ref 1234561", Some("EUR")),
+        vec![finnish]
+    );
+    assert_eq!(parse_payment_references("Ref 1234561", Some("GBP")), vec![]);
+
+    // Specific keywords take precedence over the currency, and digit groups are joined
+    assert_eq!(
+        parse_payment_references("Viite 12345 67890 7", Some("NOK")),
+        vec![PaymentReference::Finnish("12345678907".to_string())]
+    );
+    assert_eq!(
+        parse_payment_references("KID: 123456785", Some("EUR")),
+        vec![PaymentReference::NorwegianKid("123456785".to_string())]
+    );
+}
+
+#[test]
+fn finds_references_of_transactions() {
+    let mut transaction = Transaction::default();
+    transaction.transaction_amount.currency = "EUR".to_string();
+    transaction.remittance_information_structured = Some("RF18539007547034".to_string());
+    transaction.remittance_information_unstructured_array = Some(vec![
+        "viite 1234561".to_string(),
+        "RF18 5390 0754 7034".to_string(),
+    ]);
+
+    assert_eq!(
+        transaction.payment_references(),
+        vec![
+            PaymentReference::Creditor("RF18539007547034".to_string()),
+            PaymentReference::Finnish("1234561".to_string()),
+        ]
+    );
+}
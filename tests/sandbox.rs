@@ -0,0 +1,166 @@
+use gocardless_unofficial::testing::SandboxGenerator;
+use gocardless_unofficial::{
+    AccountDetailsResponse, BalanceType, ListBalancesResponse, ListTransactionsResponse,
+};
+
+fn sandbox() -> SandboxGenerator {
+    SandboxGenerator::new(42).end_date(2024, 4, 30)
+}
+
+fn cents(amount: &str) -> i64 {
+    let (units, decimals) = amount.trim_start_matches('-').split_once('.').unwrap();
+    let cents = units.parse::<i64>().unwrap() * 100 + decimals.parse::<i64>().unwrap();
+    if amount.starts_with('-') {
+        -cents
+    } else {
+        cents
+    }
+}
+
+#[test]
+fn generates_the_same_data_for_the_same_seed() {
+    assert_eq!(sandbox().transactions(0), sandbox().transactions(0));
+    assert_eq!(sandbox().account(1), sandbox().account(1));
+
+    assert_ne!(sandbox().transactions(0), sandbox().transactions(1));
+    assert_ne!(
+        sandbox().transactions(0),
+        SandboxGenerator::new(43)
+            .end_date(2024, 4, 30)
+            .transactions(0)
+    );
+}
+
+#[test]
+fn generates_the_same_data_every_day_by_default() {
+    assert_eq!(
+        SandboxGenerator::new(42).transactions(0),
+        sandbox().transactions(0)
+    );
+    assert_eq!(SandboxGenerator::default(), SandboxGenerator::new(0));
+
+    let today = SandboxGenerator::new(42).end_date_today();
+    assert_ne!(today.transactions(0), sandbox().transactions(0));
+    assert_eq!(today.end_date(2024, 4, 30), sandbox());
+}
+
+#[test]
+fn generates_parseable_responses() {
+    let details: AccountDetailsResponse =
+        serde_json::from_value(sandbox().account_details(0)).unwrap();
+    let account = details.account.unwrap();
+    assert_eq!(account.currency, "EUR");
+    assert_eq!(account.cash_account_type.as_deref(), Some("CACC"));
+    assert!(account.owner_name.is_some());
+
+    let balances: ListBalancesResponse = serde_json::from_value(sandbox().balances(0)).unwrap();
+    let types: Vec<_> = balances
+        .balances
+        .iter()
+        .map(|balance| balance.balance_type.clone())
+        .collect();
+    assert_eq!(
+        types,
+        vec![BalanceType::Expected, BalanceType::InterimAvailable]
+    );
+    assert_eq!(balances.balances[0].reference_date, "2024-04-30");
+
+    let transactions: ListTransactionsResponse =
+        serde_json::from_value(sandbox().transactions(0)).unwrap();
+    assert!(!transactions.transactions.booked.is_empty());
+}
+
+#[test]
+fn generates_valid_ibans() {
+    for index in 0..10 {
+        let details = sandbox().account_details(index);
+        let iban = details["account"]["iban"].as_str().unwrap();
+        assert_eq!(iban.len(), 18);
+        assert!(iban.starts_with("GL"));
+
+        let rearranged = format!("{}{}", &iban[4..], "1621") + &iban[2..4];
+        let remainder = rearranged.bytes().fold(0u64, |remainder, digit| {
+            (remainder * 10 + (digit - b'0') as u64) % 97
+        });
+        assert_eq!(remainder, 1, "invalid IBAN {iban}");
+    }
+}
+
+#[test]
+fn generates_realistic_history() {
+    let transactions = sandbox().account(0).transactions;
+    let remittance = |text: &str| {
+        transactions
+            .booked
+            .iter()
+            .filter(|transaction| {
+                transaction
+                    .remittance_information_unstructured
+                    .as_deref()
+                    .unwrap_or_default()
+                    .starts_with(text)
+            })
+            .count()
+    };
+
+    // 90 days up to April 30th cover three salaries and three rents
+    assert_eq!(remittance("Salary"), 3);
+    assert_eq!(remittance("Rent"), 3);
+    assert!(transactions
+        .booked
+        .iter()
+        .any(|transaction| transaction.currency_exchange.is_some()));
+
+    assert!(transactions
+        .booked
+        .iter()
+        .all(|transaction| transaction.booking_date.as_deref() <= Some("2024-04-28")));
+    assert!(transactions
+        .booked
+        .iter()
+        .all(|transaction| transaction.booking_date.as_deref() >= Some("2024-02-01")));
+    assert!(transactions
+        .pending
+        .iter()
+        .all(|transaction| transaction.booking_date.is_none()
+            && transaction.value_date.as_deref() >= Some("2024-04-29")));
+
+    let dates: Vec<_> = transactions
+        .booked
+        .iter()
+        .map(|transaction| transaction.booking_date_time.as_deref())
+        .collect();
+    let mut sorted = dates.clone();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    assert_eq!(dates, sorted);
+}
+
+#[test]
+fn generates_pending_transactions() {
+    let pending = (0..10)
+        .map(|index| sandbox().account(index).transactions.pending.len())
+        .sum::<usize>();
+    assert!(pending > 0);
+}
+
+#[test]
+fn balances_include_every_transaction() {
+    for index in 0..10 {
+        let account = sandbox().account(index);
+        let total: i64 = account
+            .transactions
+            .booked
+            .iter()
+            .chain(&account.transactions.pending)
+            .map(|transaction| cents(&transaction.transaction_amount.amount))
+            .sum();
+
+        // The history starts with an opening balance between 500.00 and 3000.00
+        let opening = cents(&account.balances[0].balance_amount.amount) - total;
+        assert!((50_000..=300_000).contains(&opening), "{opening}");
+        assert_eq!(
+            account.balances[0].balance_amount,
+            account.balances[1].balance_amount
+        );
+    }
+}
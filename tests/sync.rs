@@ -0,0 +1,274 @@
+use gocardless_unofficial::testing::{MockAccount, MockServer};
+use gocardless_unofficial::{
+    Client, Endpoint, Error, QuotaTracker, SyncError, Transaction, TransactionChange,
+    TransactionSync,
+};
+
+/// Creates a linked account, with a pending card payment on top of its generated history.
+async fn account(server: &MockServer, client: &Client) -> (String, MockAccount) {
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap();
+    server.link_requisition(&requisition.id);
+    let account_id = server.requisition(&requisition.id).unwrap().accounts[0].clone();
+
+    let mut account = server.account(&account_id).unwrap();
+    let mut pending = account.transactions.booked[0].clone();
+    pending.transaction_id = None;
+    pending.booking_date = None;
+    pending.booking_date_time = None;
+    pending.remittance_information_unstructured = Some("Corner Bakery".to_string());
+    account.transactions.pending.insert(0, pending);
+    server.set_account(&account_id, account.clone());
+
+    (account_id, account)
+}
+
+fn amount(transaction: &Transaction) -> &str {
+    &transaction.transaction_amount.amount
+}
+
+#[tokio::test]
+async fn first_sync_adds_every_transaction() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let (account_id, account) = account(&server, &client).await;
+    let sync = TransactionSync::new();
+
+    let report = sync.sync(&client, &account_id).await.unwrap();
+    assert_eq!(report.date_from, None);
+    assert_eq!(
+        report.changes.len(),
+        account.transactions.booked.len() + account.transactions.pending.len()
+    );
+    let pending = report
+        .changes
+        .iter()
+        .filter(|change| matches!(change, TransactionChange::Added { pending: true, .. }))
+        .count();
+    assert_eq!(pending, account.transactions.pending.len());
+
+    let newest = account.transactions.booked[0].booking_date.clone();
+    assert_eq!(sync.last_booking_date(&account_id), newest);
+
+    // Nothing changed, and only the last week is requested again
+    let report = sync.sync(&client, &account_id).await.unwrap();
+    assert_eq!(report.changes, vec![]);
+    let date_from = report.date_from.unwrap();
+    assert!(date_from < newest.unwrap());
+    let request = server.requests().pop().unwrap();
+    assert_eq!(request.endpoint, Some(Endpoint::ListTransactions));
+    assert_eq!(request.query, Some(format!("date_from={date_from}")));
+}
+
+#[tokio::test]
+async fn reports_new_changed_removed_and_booked_transactions() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let (account_id, mut account) = account(&server, &client).await;
+    let sync = TransactionSync::new();
+    sync.sync(&client, &account_id).await.unwrap();
+
+    let booked = &mut account.transactions.booked;
+    // A new transaction, booked on the same day as the newest one
+    let mut added = booked[0].clone();
+    added.transaction_id = Some("new-transaction".to_string());
+    // The newest transaction is amended, the next one is removed
+    let previous = booked[0].clone();
+    booked[0].transaction_amount.amount = "-1.00".to_string();
+    let removed = booked.remove(1);
+    // Old transactions outside of the overlap are not requested again, so not reported as removed
+    booked.pop();
+    booked.insert(0, added.clone());
+    // The pending payment is booked
+    let pending = account.transactions.pending.remove(0);
+    let mut booked_payment = pending.clone();
+    booked_payment.booking_date = pending.value_date.clone();
+    account
+        .transactions
+        .booked
+        .insert(0, booked_payment.clone());
+    server.set_account(&account_id, account.clone());
+
+    let report = sync.sync(&client, &account_id).await.unwrap();
+    assert_eq!(
+        report.changes,
+        vec![
+            TransactionChange::Booked {
+                pending,
+                booked: booked_payment,
+            },
+            TransactionChange::Added {
+                transaction: added,
+                pending: false,
+            },
+            TransactionChange::Changed {
+                current: account.transactions.booked[2].clone(),
+                previous,
+                pending: false,
+            },
+            TransactionChange::Removed {
+                transaction: removed,
+                pending: false,
+            },
+        ]
+    );
+    assert_eq!(amount(&account.transactions.booked[2]), "-1.00");
+
+    assert_eq!(
+        sync.sync(&client, &account_id).await.unwrap().changes,
+        vec![]
+    );
+}
+
+#[tokio::test]
+async fn matches_transactions_whose_id_changed() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let (account_id, mut account) = account(&server, &client).await;
+    let sync = TransactionSync::new();
+    sync.sync(&client, &account_id).await.unwrap();
+
+    // The bank gives the newest transaction a new ID
+    let previous = account.transactions.booked[0].clone();
+    account.transactions.booked[0].transaction_id = Some("renumbered".to_string());
+    // The pending payment is booked the next day with an ID, and a shorter remittance
+    let pending = account.transactions.pending.remove(0);
+    let value_date = pending.value_date.as_deref().unwrap();
+    let mut booked_payment = pending.clone();
+    booked_payment.transaction_id = Some("booked-payment".to_string());
+    booked_payment.booking_date = Some(next_day(value_date));
+    booked_payment.remittance_information_unstructured = Some("Bakery".to_string());
+    account
+        .transactions
+        .booked
+        .insert(0, booked_payment.clone());
+    server.set_account(&account_id, account.clone());
+
+    let report = sync.sync(&client, &account_id).await.unwrap();
+    assert_eq!(
+        report.changes,
+        vec![
+            TransactionChange::Booked {
+                pending,
+                booked: booked_payment,
+            },
+            TransactionChange::Changed {
+                current: account.transactions.booked[1].clone(),
+                previous,
+                pending: false,
+            },
+        ]
+    );
+}
+
+/// Returns the `YYYY-MM-DD` date following a date, within the same month for simplicity.
+fn next_day(date: &str) -> String {
+    let day: u32 = date[8..10].parse().unwrap();
+    if day < 28 {
+        format!("{}{:02}", &date[..8], day + 1)
+    } else {
+        date.to_string()
+    }
+}
+
+#[tokio::test]
+async fn respects_the_daily_quota() {
+    let server = MockServer::start().unwrap();
+    let client = server
+        .client_builder()
+        .quota_tracker(QuotaTracker::new(4))
+        .build()
+        .await
+        .unwrap();
+    let (account_id, _) = account(&server, &client).await;
+    let sync = TransactionSync::new().reserve_quota(2);
+
+    sync.sync(&client, &account_id).await.unwrap();
+    sync.sync(&client, &account_id).await.unwrap();
+    let last_booking_date = sync.last_booking_date(&account_id);
+
+    // Two calls are left for other uses
+    let error = sync.sync(&client, &account_id).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::QuotaExceeded {
+            endpoint: Endpoint::ListTransactions,
+            retry_after: None,
+            ..
+        })
+    ));
+    assert_eq!(sync.last_booking_date(&account_id), last_booking_date);
+    assert_eq!(server.request_count(Endpoint::ListTransactions), 2);
+
+    // Once exhausted, the quota reports when calls are possible again
+    let sync = TransactionSync::new();
+    sync.sync(&client, &account_id).await.unwrap();
+    sync.sync(&client, &account_id).await.unwrap();
+    let error = sync.sync(&client, &account_id).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::QuotaExceeded {
+            retry_after: Some(_),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn refuses_concurrent_syncs_of_an_account() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let (account_id, _) = account(&server, &client).await;
+    let sync = TransactionSync::new();
+
+    let (first, second) = tokio::join!(
+        sync.sync(&client, &account_id),
+        sync.sync(&client, &account_id)
+    );
+    assert!(!first.unwrap().changes.is_empty());
+    assert_eq!(
+        second.unwrap_err().downcast_ref::<SyncError>(),
+        Some(&SyncError::InProgress {
+            account_id: account_id.clone()
+        })
+    );
+    assert_eq!(server.request_count(Endpoint::ListTransactions), 1);
+
+    // The account can be synced again once the first sync finished
+    assert_eq!(
+        sync.sync(&client, &account_id).await.unwrap().changes,
+        vec![]
+    );
+}
+
+#[tokio::test]
+async fn resumes_from_persisted_state() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let (account_id, _) = account(&server, &client).await;
+
+    let sync = TransactionSync::new().initial_history_days(Some(30));
+    let report = sync.sync(&client, &account_id).await.unwrap();
+    let date_from = report.date_from.unwrap();
+    assert!(report.changes.iter().all(|change| match change {
+        TransactionChange::Added {
+            transaction,
+            pending: false,
+        } => transaction.booking_date.as_deref() >= Some(date_from.as_str()),
+        TransactionChange::Added { pending: true, .. } => true,
+        _ => false,
+    }));
+
+    let json = sync.to_json().unwrap();
+    let sync = TransactionSync::from_json(&json).unwrap();
+    assert!(sync.last_booking_date(&account_id).is_some());
+    assert_eq!(
+        sync.sync(&client, &account_id).await.unwrap().changes,
+        vec![]
+    );
+
+    sync.reset(&account_id);
+    assert_eq!(sync.last_booking_date(&account_id), None);
+}
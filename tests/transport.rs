@@ -0,0 +1,233 @@
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use gocardless_unofficial::{
+    Client, Endpoint, Error, ErrorKind, HttpTransport, MetricsRecorder, QuotaTracker,
+    RequestMetrics, RetryPolicy, TowerTransport, TransportFuture,
+};
+use serde_json::json;
+
+/// Answers token requests with a token and balance requests with the given statuses in turn, recording the requests it receives.
+#[derive(Clone, Default)]
+struct FakeApi {
+    requests: Arc<Mutex<Vec<String>>>,
+    balance_statuses: Arc<Mutex<Vec<u16>>>,
+}
+
+impl FakeApi {
+    fn with_balance_statuses(statuses: &[u16]) -> Self {
+        FakeApi {
+            balance_statuses: Arc::new(Mutex::new(statuses.to_vec())),
+            ..FakeApi::default()
+        }
+    }
+
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(&self, request: &http::Request<Vec<u8>>) -> http::Response<Vec<u8>> {
+        self.requests.lock().unwrap().push(format!(
+            "{} {}",
+            request.method(),
+            request.uri().path()
+        ));
+
+        let (status, body) = if request.uri().path() == "/api/v2/token/new/" {
+            let body = json!({
+                "access": "access-token",
+                "access_expires": 86400,
+                "refresh": "refresh-token",
+                "refresh_expires": 2592000,
+            });
+            (200, body)
+        } else {
+            let status = self.balance_statuses.lock().unwrap().remove(0);
+            let body = json!({
+                "balances": [{
+                    "balanceAmount": { "amount": "10.00", "currency": "EUR" },
+                    "balanceType": "expected",
+                    "referenceDate": "2024-01-02",
+                }]
+            });
+            (status, body)
+        };
+
+        let mut response = http::Response::new(serde_json::to_vec(&body).unwrap());
+        *response.status_mut() = http::StatusCode::from_u16(status).unwrap();
+        response
+    }
+}
+
+/// A custom transport which doesn't override `sleep`, so retries are delayed by the default timer.
+struct FakeTransport(FakeApi);
+
+impl HttpTransport for FakeTransport {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        let response = self.0.respond(&request);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// A transport which creates tokens but never answers any other request.
+struct HangingTransport(FakeApi);
+
+impl HttpTransport for HangingTransport {
+    fn send(&self, request: http::Request<Vec<u8>>) -> TransportFuture<'_> {
+        if request.uri().path() == "/api/v2/token/new/" {
+            let response = self.0.respond(&request);
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(std::future::pending())
+    }
+}
+
+#[derive(Default)]
+struct FinishedRecorder(Mutex<Vec<(Endpoint, Option<ErrorKind>)>>);
+
+impl MetricsRecorder for FinishedRecorder {
+    fn request_finished(&self, metrics: &RequestMetrics) {
+        self.0
+            .lock()
+            .unwrap()
+            .push((metrics.endpoint, metrics.error_kind));
+    }
+}
+
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        initial_backoff: Duration::from_millis(50),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn sends_requests_through_a_custom_transport() {
+    let api = FakeApi::with_balance_statuses(&[503, 502, 200]);
+    let client = Client::builder("my-secret-id".to_string(), "my-secret-key".to_string())
+        .base_url("http://localhost")
+        .transport(FakeTransport(api.clone()))
+        .retry_policy(retry_policy())
+        .build()
+        .await
+        .unwrap();
+
+    let started_at = Instant::now();
+    let balances = client.list_balances("account").await.unwrap();
+    // Retries wait 50ms, then 100ms
+    assert!(started_at.elapsed() >= Duration::from_millis(150));
+    assert_eq!(balances.balances[0].balance_amount.amount, "10.00");
+    assert_eq!(
+        api.requests(),
+        vec![
+            "POST /api/v2/token/new/",
+            "GET /api/v2/accounts/account/balances",
+            "GET /api/v2/accounts/account/balances",
+            "GET /api/v2/accounts/account/balances",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reports_dropped_requests_as_cancelled() {
+    let recorder = Arc::new(FinishedRecorder::default());
+    let client = Client::builder("my-secret-id".to_string(), "my-secret-key".to_string())
+        .base_url("http://localhost")
+        .transport(HangingTransport(FakeApi::default()))
+        .quota_tracker(QuotaTracker::new(1))
+        .metrics_recorder(recorder.clone())
+        .build()
+        .await
+        .unwrap();
+
+    let call = tokio::time::timeout(Duration::from_millis(50), client.list_balances("account"));
+    assert!(call.await.is_err());
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec![
+            (Endpoint::CreateToken, None),
+            (Endpoint::ListBalances, Some(ErrorKind::Cancelled)),
+        ]
+    );
+    // The quota reserved for the dropped request is released
+    let tracker = client.quota_tracker().unwrap();
+    assert_eq!(tracker.remaining("account", Endpoint::ListBalances), 1);
+}
+
+#[tokio::test]
+async fn wakes_concurrent_sleeps_of_the_default_timer() {
+    let transport = FakeTransport(FakeApi::default());
+    let started_at = Instant::now();
+    let sleeps = [300, 100, 200, 0].map(|millis| {
+        let sleep = transport.sleep(Duration::from_millis(millis));
+        tokio::spawn(async move {
+            sleep.await;
+            started_at.elapsed()
+        })
+    });
+
+    for (sleep, millis) in sleeps.into_iter().zip([300, 100, 200, 0]) {
+        let elapsed = sleep.await.unwrap();
+        assert!(elapsed >= Duration::from_millis(millis));
+        assert!(elapsed < Duration::from_millis(millis + 250));
+    }
+}
+
+#[tokio::test]
+async fn sends_requests_through_a_tower_service() {
+    let api = FakeApi::with_balance_statuses(&[500]);
+    let authorizations = Arc::new(Mutex::new(Vec::new()));
+    let service = {
+        let api = api.clone();
+        let authorizations = authorizations.clone();
+        tower::service_fn(move |request: http::Request<Vec<u8>>| {
+            let authorization = request
+                .headers()
+                .get(http::header::AUTHORIZATION)
+                .map(|value| value.to_str().unwrap().to_string());
+            authorizations.lock().unwrap().push(authorization);
+            let response = api.respond(&request);
+            async move { Ok::<_, Infallible>(response) }
+        })
+    };
+
+    let client = Client::builder("my-secret-id".to_string(), "my-secret-key".to_string())
+        .base_url("http://localhost")
+        .transport(TowerTransport::new(service))
+        .build()
+        .await
+        .unwrap();
+
+    let error = client.list_balances("account").await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Api { status: 500, .. })
+    ));
+    assert_eq!(
+        authorizations.lock().unwrap().clone(),
+        vec![None, Some("Bearer access-token".to_string())]
+    );
+    assert_eq!(api.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn reports_errors_of_a_tower_service() {
+    let service = tower::service_fn(|_: http::Request<Vec<u8>>| async {
+        Err::<http::Response<Vec<u8>>, _>(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "connection refused",
+        ))
+    });
+
+    let error = Client::builder("my-secret-id".to_string(), "my-secret-key".to_string())
+        .base_url("http://localhost")
+        .transport(TowerTransport::new(service))
+        .build()
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "connection refused");
+}
@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use gocardless_unofficial::testing::MockServer;
+use gocardless_unofficial::{Client, RequisitionStatus, StatusTransition, WaitError, WaitOptions};
+
+async fn create_requisition(client: &Client) -> String {
+    client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .await
+        .unwrap()
+        .id
+}
+
+fn options() -> WaitOptions {
+    WaitOptions::new()
+        .interval(Duration::from_millis(1))
+        .max_interval(Duration::from_millis(5))
+}
+
+#[tokio::test]
+async fn reports_every_transition() {
+    let server = MockServer::builder().auto_progress(true).start().unwrap();
+    let client = server.client().await.unwrap();
+    let requisition_id = create_requisition(&client).await;
+
+    let transitions = Arc::new(Mutex::new(Vec::<StatusTransition>::new()));
+    let recorded = transitions.clone();
+    let options = options()
+        .on_transition(move |transition| recorded.lock().unwrap().push(transition.clone()));
+
+    let requisition = client
+        .wait_for_requisition(&requisition_id, &options)
+        .await
+        .unwrap();
+    assert_eq!(requisition.status, RequisitionStatus::Linked);
+    assert_eq!(requisition.accounts.len(), 1);
+
+    // The mock server advances the requisition on every poll
+    let transitions = transitions.lock().unwrap();
+    let steps: Vec<_> = transitions
+        .iter()
+        .map(|transition| (transition.from.clone(), transition.to.clone()))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            (None, RequisitionStatus::GivingConsent),
+            (
+                Some(RequisitionStatus::GivingConsent),
+                RequisitionStatus::UndergoingAuthentication
+            ),
+            (
+                Some(RequisitionStatus::UndergoingAuthentication),
+                RequisitionStatus::GrantingAccess
+            ),
+            (
+                Some(RequisitionStatus::GrantingAccess),
+                RequisitionStatus::Linked
+            ),
+        ]
+    );
+    assert!(transitions
+        .iter()
+        .all(|transition| transition.requisition_id == requisition_id));
+}
+
+#[tokio::test]
+async fn returns_other_terminal_statuses() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let requisition_id = create_requisition(&client).await;
+    server.set_requisition_status(&requisition_id, RequisitionStatus::Rejected);
+
+    let requisition = client
+        .wait_for_requisition(&requisition_id, &options())
+        .await
+        .unwrap();
+    assert_eq!(requisition.status, RequisitionStatus::Rejected);
+}
+
+#[tokio::test]
+async fn times_out() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+    let requisition_id = create_requisition(&client).await;
+
+    let polls = Arc::new(Mutex::new(0));
+    let counted = polls.clone();
+    let options = options()
+        .timeout(Some(Duration::from_millis(50)))
+        .on_transition(move |_| *counted.lock().unwrap() += 1);
+
+    let error = client
+        .wait_for_requisition(&requisition_id, &options)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<WaitError>(),
+        Some(&WaitError::TimedOut {
+            requisition_id,
+            status: RequisitionStatus::Created,
+        })
+    );
+    // The status never changed after the first poll
+    assert_eq!(*polls.lock().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn fails_for_unknown_requisitions() {
+    let server = MockServer::start().unwrap();
+    let client = server.client().await.unwrap();
+
+    let error = client
+        .wait_for_requisition("unknown", &options())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<gocardless_unofficial::Error>(),
+        Some(gocardless_unofficial::Error::Api { status: 404, .. })
+    ));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn works_with_blocking_client() {
+    let server = MockServer::builder().auto_progress(true).start().unwrap();
+    let client =
+        gocardless_unofficial::blocking::Client::builder("id".to_string(), "key".to_string())
+            .base_url(server.url())
+            .build()
+            .unwrap();
+    let requisition = client
+        .create_requisition("https://example.org", "SANDBOXFINANCE_SFIN0000", None, None)
+        .unwrap();
+
+    let requisition = client
+        .wait_for_requisition(&requisition.id, &options())
+        .unwrap();
+    assert!(requisition.status.is_linked());
+}